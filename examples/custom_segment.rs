@@ -34,14 +34,14 @@ fn main() {
     )
     .unwrap();
 
-    let arrow = Segment::new(
-        Box::new(Constant::new("<--".into())),
-        // the constant is never updated
-        None,
-        vec![],
-    )
-    .unwrap();
+    // the constant is never updated
+    let arrow = Segment::builder(Box::new(Constant::new("<--".into())))
+        .left_separator(" ")
+        .build()
+        .unwrap();
 
     let segments = vec![custom_segment, arrow];
-    block_on(run(segments)).unwrap()
+    // runs until SIGTERM or SIGINT arrives
+    let handle = run(segments).unwrap();
+    block_on(handle.wait())
 }
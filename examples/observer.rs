@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use async_std::task::{block_on, sleep};
+use dwmblocksrs::run_with_observer;
+use dwmblocksrs::segments::{program_output::ProgramOutput, Segment};
+
+fn main() {
+    let date = Segment::new(
+        Box::new(ProgramOutput::new("date".into(), vec!["+%T".into()], true)),
+        Duration::from_secs(1).into(),
+        vec![],
+    )
+    .unwrap();
+
+    // print the status instead of displaying it in dwm
+    let handle = run_with_observer(vec![date], |status| println!("{status}")).unwrap();
+
+    block_on(async {
+        sleep(Duration::from_secs(5)).await;
+        handle.shutdown();
+        handle.wait().await;
+    });
+}
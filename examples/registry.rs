@@ -0,0 +1,45 @@
+use async_std::task::block_on;
+use dwmblocksrs::run_with_config_and_registry;
+use dwmblocksrs::segments::SegmentKind;
+use dwmblocksrs::Registry;
+use serde::Deserialize;
+
+/// Counts the updates, starting at the number from the config file:
+/// ```yaml
+/// segments:
+///     - custom: "counter"
+///       options: { start: 10 }
+///       update_interval: 1
+/// ```
+#[derive(Debug)]
+struct Counter {
+    count: u64,
+}
+
+#[derive(Deserialize)]
+struct CounterOptions {
+    #[serde(default)]
+    start: u64,
+}
+
+impl SegmentKind for Counter {
+    fn compute_value(&mut self) -> String {
+        self.count += 1;
+        self.count.to_string()
+    }
+}
+
+fn main() {
+    let config_path = std::env::args()
+        .nth(1)
+        .expect("usage: registry <config file>");
+
+    let mut registry = Registry::new();
+    registry.register("counter", |options: CounterOptions| {
+        Ok(Box::new(Counter {
+            count: options.start,
+        }))
+    });
+
+    block_on(run_with_config_and_registry(config_path.into(), registry)).unwrap()
+}
@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+use async_std::task::{block_on, sleep};
+use dwmblocksrs::run;
+use dwmblocksrs::segments::{stream::SegmentStream, Segment};
+use futures_lite::stream;
+
+fn main() {
+    // the segment is updated whenever the stream produces a value
+    let ticks = stream::unfold(0, |tick| async move {
+        sleep(Duration::from_secs(2)).await;
+        Some((format!("tick {tick}"), tick + 1))
+    });
+    let segment = Segment::builder(Box::new(SegmentStream::new(ticks)))
+        .build()
+        .unwrap();
+
+    let handle = run(vec![segment]).unwrap();
+    block_on(handle.wait())
+}
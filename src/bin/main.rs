@@ -1,21 +1,75 @@
-use clap::{Command, Arg};
+use clap::{Arg, Command};
+use dwmblocksrs::{
+    check_config, config_schema, init_config, list_signals, migrate_blocks, print_once,
+    run_with_config, update_segment,
+};
+use log::{error, info, Level};
 use std::path::PathBuf;
-use dwmblocksrs::run_with_config;
-use log::{Level, error, info};
 
 #[async_std::main]
 async fn main() {
     simple_logger::init_with_level(Level::Info).unwrap();
 
-    let matches = Command::new("dwmblocksrs")
-        .arg(
-            Arg::new("config")
-                .long("config")
-                .short('c')
-                .value_name("config_path")
-                .help("the path to the configuration file"),
-        )
-        .get_matches();
+    let command =
+        Command::new("dwmblocksrs")
+            .arg(
+                Arg::new("config")
+                    .long("config")
+                    .short('c')
+                    .value_name("config_path")
+                    .help("the path to the configuration file")
+                    .global(true),
+            )
+            .arg(
+                Arg::new("once")
+                    .long("once")
+                    .help("prints the status text once instead of setting it"),
+            )
+            .subcommand(
+                Command::new("init").about("writes a config file to start from, if there is none"),
+            )
+            .subcommand(
+                Command::new("migrate")
+                    .about("prints the config for the blocks.h of dwmblocks")
+                    .arg(
+                        Arg::new("blocks")
+                            .required(true)
+                            .help("the path to blocks.h"),
+                    ),
+            )
+            .subcommand(
+                Command::new("schema").about("prints the JSON Schema of the configuration file"),
+            )
+            .subcommand(
+                Command::new("check")
+                    .about("checks the configuration file without starting the status bar"),
+            )
+            .subcommand(Command::new("list-signals").about(
+                "prints the names of the signals in the configuration file and their offsets",
+            ))
+            .subcommand(
+                Command::new("update")
+                    .about("updates a segment of the running instance")
+                    .arg(
+                        Arg::new("name")
+                            .required(true)
+                            .help("the name of the segment"),
+                    ),
+            );
+
+    #[cfg(feature = "ipc")]
+    let command = command.subcommand(
+        Command::new("set")
+            .about("displays text in a segment of the running instance")
+            .arg(
+                Arg::new("name")
+                    .required(true)
+                    .help("the name of the segment"),
+            )
+            .arg(Arg::new("text").required(true).help("the text to display")),
+    );
+
+    let matches = command.get_matches();
 
     // use the path provided as the argument for the configuration
     let config_path = matches
@@ -23,11 +77,103 @@ async fn main() {
         .map(PathBuf::from)
         // or else look for the configuration file in the config directory
         .unwrap_or_else(|| {
-            let mut config_path = dirs::config_dir().expect("config directory does not exist");
-            config_path.push(PathBuf::from("dwmblocksrs/dwmblocksrs.yaml"));
-            config_path
+            let config_dir = dirs::config_dir()
+                .expect("config directory does not exist")
+                .join("dwmblocksrs");
+            // the first config file that exists, in any of the formats
+            ["yaml", "toml", "json"]
+                .into_iter()
+                .map(|extension| config_dir.join(format!("dwmblocksrs.{extension}")))
+                .find(|path| path.exists())
+                .unwrap_or_else(|| config_dir.join("dwmblocksrs.yaml"))
         });
 
+    if matches.subcommand_matches("init").is_some() {
+        match init_config(&config_path) {
+            Ok(()) => println!("wrote the config file '{}'", config_path.display()),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(migrate) = matches.subcommand_matches("migrate") {
+        match migrate_blocks(migrate.value_of("blocks").unwrap().as_ref()) {
+            Ok(config) => print!("{config}"),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if matches.subcommand_matches("schema").is_some() {
+        println!("{}", config_schema());
+        return;
+    }
+
+    if !config_path.exists() {
+        eprintln!(
+            "the config file '{}' does not exist, `dwmblocksrs init` creates one",
+            config_path.display()
+        );
+        std::process::exit(1);
+    }
+
+    if matches.is_present("once") {
+        match print_once(config_path).await {
+            Ok(text) => println!("{text}"),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if matches.subcommand_matches("check").is_some() {
+        match check_config(config_path) {
+            Ok(()) => println!("the configuration is valid"),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if matches.subcommand_matches("list-signals").is_some() {
+        match list_signals(config_path) {
+            Ok(signals) => print!("{signals}"),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(update) = matches.subcommand_matches("update") {
+        if let Err(e) = update_segment(config_path, update.value_of("name").unwrap()) {
+            error!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    #[cfg(feature = "ipc")]
+    if let Some(set) = matches.subcommand_matches("set") {
+        let name = set.value_of("name").unwrap();
+        if let Err(e) = dwmblocksrs::set_segment(config_path, name, set.value_of("text").unwrap()) {
+            error!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     info!("loading config file '{}'", config_path.to_str().unwrap());
 
     if let Err(e) = run_with_config(config_path).await {
@@ -0,0 +1,98 @@
+use std::{
+    io,
+    process::Command,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+};
+
+use log::warn;
+
+/// more than the number of signals on linux
+const SIGNALS: usize = 65;
+
+/// the mouse button of the last click for every signal, 0 if there was no click since
+static CLICKED_BUTTONS: [AtomicI32; SIGNALS] = [const { AtomicI32::new(0) }; SIGNALS];
+static REGISTERED: [AtomicBool; SIGNALS] = [const { AtomicBool::new(false) }; SIGNALS];
+
+/// Remembers the mouse buttons that the statuscmd patch of dwm sends with the signal.
+/// dwm sends the button as the integer value of the signal (`sigqueue`).
+pub(crate) fn register(signal: i32) -> Result<(), String> {
+    let index = signal as usize;
+    if REGISTERED[index].swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let action = move |info: &libc::siginfo_t| {
+        // sival_int is stored at the start of the sigval union
+        let value = unsafe { info.si_value() }.sival_ptr as usize;
+        let button = i32::from_ne_bytes(value.to_ne_bytes()[..4].try_into().unwrap());
+        CLICKED_BUTTONS[index].store(button, Ordering::SeqCst);
+    };
+    unsafe { signal_hook_registry::register_sigaction(signal, action) }
+        .map(|_| ())
+        .map_err(|e| format!("error registering click handler: {e}"))
+}
+
+/// returns the button of the last click since the last call, if there was one
+pub(crate) fn take_button(signal: i32) -> Option<i32> {
+    match CLICKED_BUTTONS[signal as usize].swap(0, Ordering::SeqCst) {
+        0 => None,
+        button => Some(button),
+    }
+}
+
+/// Runs the command with the mouse button in `$BUTTON` and waits for it to finish
+pub(crate) fn run_command(command: &str, button: i32) {
+    match Command::new("/bin/sh")
+        .arg("-c")
+        .arg(command)
+        .env("BUTTON", button.to_string())
+        // stdout can be the output of the status bar
+        .stdout(io::stderr())
+        .status()
+    {
+        Ok(status) if !status.success() => {
+            warn!("click command '{command}' exited with non-zero error code ({status})")
+        }
+        Ok(_) => {}
+        Err(e) => warn!("error running click command '{command}': {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn button_environment() {
+        let file = env::temp_dir().join(format!("dwmblocksrs-click-{}", std::process::id()));
+        run_command(&format!("echo $BUTTON > {}", file.display()), 3);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "3\n");
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn clicked_button() {
+        let signal = libc::SIGRTMIN() + 20;
+        register(signal).unwrap();
+        assert_eq!(take_button(signal), None);
+
+        unsafe {
+            let value = libc::sigval {
+                sival_ptr: 2usize as *mut libc::c_void,
+            };
+            libc::sigqueue(libc::getpid(), signal, value);
+        }
+        // the signal might be handled by another thread
+        let mut button = None;
+        for _ in 0..100 {
+            button = take_button(signal);
+            if button.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(button, Some(2));
+        assert_eq!(take_button(signal), None);
+    }
+}
@@ -1,31 +1,108 @@
 use std::borrow::Cow;
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum Color {
+    /// a color of the statuscolors patch
     Colored(u8),
+    /// a hex color for the status2d patch
+    Rgb(u8, u8, u8),
+    #[default]
     Uncolored,
 }
 
+/// How colors are written into the status text
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorFormat {
+    /// the color number as control byte, for the statuscolors patch
+    #[default]
+    Statuscolors,
+    /// `^c#rrggbb^` sequences, for the status2d patch
+    Status2d,
+}
+
 pub(crate) trait Colorable {
-    fn color(&self, color: Color) -> Cow<'_, str>;
+    fn color(&self, color: Color, background: Color, format: ColorFormat) -> Cow<'_, str>;
 }
 
 impl<A: AsRef<str>> Colorable for A {
-    fn color(&self, color: Color) -> Cow<'_, str> {
+    fn color(&self, color: Color, background: Color, format: ColorFormat) -> Cow<'_, str> {
         let text = self.as_ref();
-        match color {
-            Color::Uncolored => Cow::Borrowed(text),
-            Color::Colored(c) => Cow::Owned(format!("{}{}\x01", c as char, text)),
+        match format {
+            ColorFormat::Statuscolors => match color {
+                Color::Colored(c) => Cow::Owned(format!("{}{}\x01", c as char, text)),
+                // colors that the format can't express are checked when parsing the config
+                _ => Cow::Borrowed(text),
+            },
+            ColorFormat::Status2d => {
+                let mut start = String::new();
+                if let Some(hex) = color.hex() {
+                    start += &format!("^c{hex}^");
+                }
+                if let Some(hex) = background.hex() {
+                    start += &format!("^b{hex}^");
+                }
+                match start.is_empty() {
+                    true => Cow::Borrowed(text),
+                    // ^d^ resets both colors
+                    false => Cow::Owned(format!("{start}{text}^d^")),
+                }
+            }
         }
     }
 }
 
-impl Default for Color {
-    fn default() -> Self {
-        Color::Uncolored
+/// Removes the colors (and the other control bytes of dwm) from the text
+pub(crate) fn strip_colors(text: &str, format: ColorFormat) -> String {
+    split_colors(text, format)
+        .into_iter()
+        .filter(|(_, escape)| !escape)
+        .flat_map(|(part, _)| part.chars())
+        .filter(|c| !c.is_control())
+        .collect()
+}
+
+/// Splits the text into the parts that are displayed and the color escapes between them,
+/// which are marked by `true`
+pub(crate) fn split_colors(text: &str, format: ColorFormat) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    match format {
+        ColorFormat::Statuscolors => {
+            let mut start = 0;
+            for (i, c) in text.char_indices() {
+                if c.is_control() {
+                    parts.push((&text[start..i], false));
+                    parts.push((&text[i..i + c.len_utf8()], true));
+                    start = i + c.len_utf8();
+                }
+            }
+            parts.push((&text[start..], false));
+        }
+        ColorFormat::Status2d => {
+            let mut start = 0;
+            let mut rest = 0;
+            // the commands of status2d look like `^c#ffffff^` or `^d^`
+            while let Some(offset) = text[rest..].find('^') {
+                let caret = rest + offset;
+                let command = &text[caret + 1..];
+                match command.find('^') {
+                    Some(end) if command.starts_with(['c', 'b', 'd', 'r', 'f']) => {
+                        parts.push((&text[start..caret], false));
+                        rest = caret + end + 2;
+                        parts.push((&text[caret..rest], true));
+                        start = rest;
+                    }
+                    _ => rest = caret + 1,
+                }
+            }
+            parts.push((&text[start..], false));
+        }
     }
+    parts.retain(|(part, _)| !part.is_empty());
+    parts
 }
 
 impl From<Option<u8>> for Color {
@@ -38,6 +115,24 @@ impl From<Option<u8>> for Color {
 }
 
 impl Color {
+    /// Parses `#rrggbb`
+    pub(crate) fn from_hex(hex: &str) -> Option<Color> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// `#rrggbb` for hex colors
+    pub(crate) fn hex(self) -> Option<String> {
+        match self {
+            Color::Rgb(r, g, b) => Some(format!("#{r:02x}{g:02x}{b:02x}")),
+            _ => None,
+        }
+    }
+
     fn or_default(self, default: Color) -> Self {
         match self {
             Self::Uncolored => default,
@@ -46,12 +141,16 @@ impl Color {
     }
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, JsonSchema, Debug, Default)]
 pub struct SegmentColoring {
     pub text: Color,
     pub left_separator: Color,
     pub right_separator: Color,
     pub icon: Color,
+    pub text_background: Color,
+    pub left_separator_background: Color,
+    pub right_separator_background: Color,
+    pub icon_background: Color,
 }
 
 impl SegmentColoring {
@@ -65,6 +164,18 @@ impl SegmentColoring {
                 .right_separator
                 .or_default(default_coloring.right_separator),
             icon: self.icon.or_default(default_coloring.icon),
+            text_background: self
+                .text_background
+                .or_default(default_coloring.text_background),
+            left_separator_background: self
+                .left_separator_background
+                .or_default(default_coloring.left_separator_background),
+            right_separator_background: self
+                .right_separator_background
+                .or_default(default_coloring.right_separator_background),
+            icon_background: self
+                .icon_background
+                .or_default(default_coloring.icon_background),
         }
     }
 }
@@ -76,6 +187,34 @@ mod tests {
     #[test]
     fn test() {
         let c = Color::Colored(2);
-        assert_eq!("test".color(c), "\x02test\x01");
+        assert_eq!(
+            "test".color(c, Color::Uncolored, ColorFormat::Statuscolors),
+            "\x02test\x01"
+        );
+    }
+
+    #[test]
+    fn status2d() {
+        let c = Color::from_hex("#00ff7f").unwrap();
+        assert_eq!(c, Color::Rgb(0, 255, 127));
+        assert_eq!(
+            "test".color(c, Color::Uncolored, ColorFormat::Status2d),
+            "^c#00ff7f^test^d^"
+        );
+        assert_eq!(
+            "test".color(Color::Uncolored, c, ColorFormat::Status2d),
+            "^b#00ff7f^test^d^"
+        );
+        assert_eq!(Color::from_hex("00ff7f"), None);
+        assert_eq!(Color::from_hex("#00ff7"), None);
+    }
+
+    #[test]
+    fn strip() {
+        assert_eq!(strip_colors("\x02a\x01b", ColorFormat::Statuscolors), "ab");
+        assert_eq!(
+            strip_colors("^c#00ff7f^a^d^ 2^3", ColorFormat::Status2d),
+            "a 2^3"
+        );
     }
 }
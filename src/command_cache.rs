@@ -0,0 +1,183 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::PathBuf,
+    process::Output,
+    sync::{Arc, Mutex},
+};
+
+use async_std::task;
+use event_listener::Event;
+use lazy_static::lazy_static;
+
+/// identifies a command, two commands with the same key produce the same output
+pub(crate) type CommandKey = (PathBuf, Vec<String>);
+
+/// the result of a command, errors are kept as their kind and message to share them
+type SharedResult = Result<Output, (io::ErrorKind, String)>;
+
+/// A command that is running, the segments that run it as well wait for its result
+#[derive(Default)]
+struct Running {
+    result: Mutex<Option<SharedResult>>,
+    finished: Event,
+}
+
+impl Running {
+    /// waits for the result without holding a lock while the command runs
+    fn wait(&self) -> io::Result<Output> {
+        loop {
+            let finished = self.finished.listen();
+            if let Some(result) = &*self.result.lock().unwrap() {
+                return result
+                    .clone()
+                    .map_err(|(kind, message)| io::Error::new(kind, message));
+            }
+            task::block_on(finished);
+        }
+    }
+}
+
+lazy_static! {
+    static ref RUNNING: Mutex<HashMap<CommandKey, Arc<Running>>> = Default::default();
+}
+
+/// Runs the command, unless another segment is running the same command right now.
+/// In that case, its output is waited for and reused.
+/// Commands that already finished are run again, so every update sees changes.
+pub(crate) fn run_deduplicated(
+    key: CommandKey,
+    run: impl FnOnce() -> io::Result<Output>,
+) -> io::Result<Output> {
+    let running = {
+        let mut commands = RUNNING.lock().unwrap();
+        if let Some(running) = commands.get(&key) {
+            let running = running.clone();
+            drop(commands);
+            return running.wait();
+        }
+        let running = Arc::new(Running::default());
+        commands.insert(key.clone(), running.clone());
+        running
+    };
+    // the waiting segments get a result even if the command panics
+    let publish = Publish { key, running };
+
+    let output = run();
+    publish.finish(match &output {
+        Ok(output) => Ok(output.clone()),
+        Err(e) => Err((e.kind(), e.to_string())),
+    });
+    output
+}
+
+/// Hands the result of a command to the segments that wait for it
+struct Publish {
+    key: CommandKey,
+    running: Arc<Running>,
+}
+
+impl Publish {
+    fn finish(&self, result: SharedResult) {
+        let mut commands = RUNNING.lock().unwrap();
+        // the same command can already run again
+        if commands
+            .get(&self.key)
+            .is_some_and(|running| Arc::ptr_eq(running, &self.running))
+        {
+            commands.remove(&self.key);
+        }
+        drop(commands);
+        let mut shared = self.running.result.lock().unwrap();
+        if shared.is_none() {
+            *shared = Some(result);
+        }
+        drop(shared);
+        self.running.finished.notify(usize::MAX);
+    }
+}
+
+impl Drop for Publish {
+    fn drop(&mut self) {
+        self.finish(Err((
+            io::ErrorKind::Other,
+            "the command was not run".into(),
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{process::Command, sync::Barrier, thread, time::Duration};
+
+    fn run_counted(key: &CommandKey, runs: &mut usize) {
+        run_deduplicated(key.clone(), || {
+            *runs += 1;
+            Command::new("true").output()
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn finished_commands_run_again() {
+        let key: CommandKey = ("dedup-test".into(), vec![]);
+        let mut runs = 0;
+        run_counted(&key, &mut runs);
+        run_counted(&key, &mut runs);
+        assert_eq!(runs, 2);
+        assert!(!RUNNING.lock().unwrap().contains_key(&key));
+    }
+
+    #[test]
+    fn running_commands() {
+        let key: CommandKey = ("dedup-test-running".into(), vec![]);
+        let started = Arc::new(Barrier::new(2));
+        let runner = {
+            let (key, started) = (key.clone(), started.clone());
+            thread::spawn(move || {
+                run_deduplicated(key, || {
+                    started.wait();
+                    thread::sleep(Duration::from_millis(200));
+                    Command::new("echo").arg("shared").output()
+                })
+                .unwrap()
+            })
+        };
+        started.wait();
+        // waits for the output of the running command
+        let output = run_deduplicated(key, || unreachable!()).unwrap();
+        assert_eq!(output.stdout, b"shared\n");
+        runner.join().unwrap();
+    }
+
+    #[test]
+    fn shared_errors() {
+        let key: CommandKey = ("dedup-test-errors".into(), vec![]);
+        let started = Arc::new(Barrier::new(2));
+        let runner = {
+            let (key, started) = (key.clone(), started.clone());
+            thread::spawn(move || {
+                run_deduplicated(key, || {
+                    started.wait();
+                    thread::sleep(Duration::from_millis(100));
+                    Err(io::Error::new(io::ErrorKind::NotFound, "missing"))
+                })
+            })
+        };
+        started.wait();
+        let error = run_deduplicated(key, || unreachable!()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(runner.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn different_args() {
+        let mut runs = 0;
+        for arg in ["a", "b"] {
+            let key = ("dedup-test-args".into(), vec![arg.into()]);
+            run_counted(&key, &mut runs);
+        }
+        assert_eq!(runs, 2);
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::BTreeSet, sync::Mutex};
+
+use event_listener::Event;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref LIMIT: CommandLimit = CommandLimit::default();
+}
+
+/// Limits the number of commands that run at the same time, `None` for no limit
+pub(crate) fn set_max_concurrent(max: Option<usize>) {
+    LIMIT.state.lock().unwrap().max = max;
+    LIMIT.changed.notify(usize::MAX);
+}
+
+/// Waits until fewer commands than the limit are running, the command may run while the slot is kept.
+/// Waiting commands get their slots in the order they arrived.
+pub(crate) async fn acquire() -> Slot<'static> {
+    LIMIT.acquire().await
+}
+
+#[derive(Default)]
+struct CommandLimit {
+    state: Mutex<State>,
+    changed: Event,
+}
+
+#[derive(Default)]
+struct State {
+    max: Option<usize>,
+    running: usize,
+    /// the ticket of the next waiting command and of the next command that may run
+    next_ticket: u64,
+    next_to_run: u64,
+    /// the tickets of commands that stopped waiting, they are skipped
+    cancelled: BTreeSet<u64>,
+}
+
+impl State {
+    fn next(&mut self) {
+        self.next_to_run += 1;
+        while self.cancelled.remove(&self.next_to_run) {
+            self.next_to_run += 1;
+        }
+    }
+}
+
+impl CommandLimit {
+    async fn acquire(&self) -> Slot<'_> {
+        let ticket = {
+            let mut state = self.state.lock().unwrap();
+            state.next_ticket += 1;
+            state.next_ticket - 1
+        };
+        // the ticket is skipped if the waiting is cancelled
+        let mut waiting = Waiting(self, Some(ticket));
+        loop {
+            let changed = self.changed.listen();
+            {
+                let mut state = self.state.lock().unwrap();
+                if ticket == state.next_to_run && state.max.is_none_or(|max| state.running < max) {
+                    state.next();
+                    state.running += 1;
+                    break;
+                }
+            }
+            changed.await;
+        }
+        waiting.1 = None;
+        // the next command may be allowed to run as well
+        self.changed.notify(usize::MAX);
+        Slot(self)
+    }
+}
+
+/// A command that waits for its turn
+struct Waiting<'a>(&'a CommandLimit, Option<u64>);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.1 {
+            let mut state = self.0.state.lock().unwrap();
+            match state.next_to_run == ticket {
+                true => state.next(),
+                false => {
+                    state.cancelled.insert(ticket);
+                }
+            }
+            drop(state);
+            self.0.changed.notify(usize::MAX);
+        }
+    }
+}
+
+/// Given back when it is dropped, even if the command panics
+pub(crate) struct Slot<'a>(&'a CommandLimit);
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().running -= 1;
+        self.0.changed.notify(usize::MAX);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    fn limit(max: usize) -> &'static CommandLimit {
+        let limit = Box::leak(Box::<CommandLimit>::default());
+        limit.state.get_mut().unwrap().max = Some(max);
+        limit
+    }
+
+    #[async_std::test]
+    async fn limit_running() {
+        let limit = limit(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+
+        let tasks = (0..6)
+            .map(|_| {
+                let (running, most) = (running.clone(), most.clone());
+                task::spawn(async move {
+                    let _slot = limit.acquire().await;
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    task::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await;
+        }
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+    }
+
+    #[async_std::test]
+    async fn order() {
+        let limit = limit(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let first = limit.acquire().await;
+        let mut waiting = Vec::new();
+        for i in 0..3 {
+            let order = order.clone();
+            waiting.push(task::spawn(async move {
+                let _slot = limit.acquire().await;
+                order.lock().unwrap().push(i);
+            }));
+            // the tasks queue up in this order
+            task::sleep(Duration::from_millis(20)).await;
+        }
+        drop(first);
+        for task in waiting {
+            task.await;
+        }
+        assert_eq!(*order.lock().unwrap(), [0, 1, 2]);
+    }
+
+    #[async_std::test]
+    async fn cancelled() {
+        let limit = limit(1);
+        let first = limit.acquire().await;
+        let next = task::spawn(async move {
+            let _slot = limit.acquire().await;
+        });
+        task::sleep(Duration::from_millis(20)).await;
+        // stops waiting before it is its turn
+        assert!(
+            async_std::future::timeout(Duration::from_millis(20), limit.acquire())
+                .await
+                .is_err()
+        );
+        drop(first);
+        next.await;
+        async_std::future::timeout(Duration::from_secs(1), limit.acquire())
+            .await
+            .unwrap();
+    }
+}
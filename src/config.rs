@@ -1,93 +1,766 @@
-use std::{collections::HashMap, fs::read_to_string, path::PathBuf, time::Duration};
+mod fields;
+mod signals;
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    fs::read_to_string,
+    io::Read,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use format_serde_error::SerdeError;
-use serde::Deserialize;
+use lazy_static::lazy_static;
+use log::info;
+use regex::Regex;
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{Schema, SchemaObject, SubschemaValidation},
+    JsonSchema,
+};
+use serde::{
+    de::{DeserializeOwned, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
 
+use self::{fields::UnknownField, signals::SegmentSignals};
 use crate::{
-    color::{Color, SegmentColoring},
-    segments::{self, Segment, SegmentKind},
+    color::{Color, ColorFormat, SegmentColoring},
+    duration::{self, DurationValue},
+    menu::Menu,
+    notification::FailureNotifier,
+    number_format::NumberFormat,
+    output::OutputConfig,
+    registry::Registry,
+    segments::{
+        self,
+        bar::{Bar, BarConfig},
+        graph::{Graph, GraphConfig},
+        program_output::InvalidUtf8,
+        scroll::{Scroll, ScrollConfig},
+        AlignTo, Area, IconPosition, OnError, Retry, Segment, SegmentKind, SignalValue,
+    },
+    status_bar::{Overflow, StatusBarConfig},
+    template::Align,
+    theme::ColorSource,
+    transform::Transform,
 };
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 struct ConfigFile {
     segments: Vec<SegmentConfig>,
 
+    // `include` and `templates` are handled by `read_config` before the config is deserialized,
+    // they are only listed for the schema
+    /// other config files whose segments and settings are added
+    #[serde(default)]
+    #[allow(dead_code)]
+    include: Vec<String>,
+    /// settings that segments can use with `template: <name>`
+    #[serde(default)]
+    #[allow(dead_code)]
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
+    templates: HashMap<String, serde_yaml::Value>,
+
     left_separator: Option<String>,
     right_separator: Option<String>,
+    /// replaces line breaks and NUL bytes in the values of the segments
+    replacement_character: Option<String>,
 
-    update_all_signal: Option<u32>,
+    update_all_signal: Option<UpdateAllSignal>,
+    /// names of signal offsets, which can be used instead of the numbers
+    #[serde(default)]
+    signal_names: BTreeMap<String, u32>,
     script_dir: Option<String>,
 
+    failure_notifications: Option<FailureNotificationConfig>,
+
+    #[serde(default)]
+    on_start: Vec<String>,
+    #[serde(default)]
+    on_exit: Vec<String>,
+
+    #[serde(default)]
+    deduplicate_commands: bool,
+    /// the number of programs and scripts that may run at the same time
+    max_concurrent_commands: Option<usize>,
+    /// spreads the first computation of the segments over a second
+    #[serde(default)]
+    stagger_startup: bool,
+
+    area_delimiter: Option<String>,
+    /// put before and after the status
+    #[serde(default)]
+    prefix: String,
+    #[serde(default)]
+    suffix: String,
+    /// put between the segments
+    #[serde(default)]
+    separator: String,
+    /// in bytes, dwm cuts off longer statuses
+    #[serde(default = "max_length_default")]
+    max_length: Option<usize>,
+    #[serde(default)]
+    overflow: Overflow,
+    /// in milliseconds or a duration like `50ms`, updates within this time are displayed together
+    #[serde(
+        default = "debounce_default",
+        deserialize_with = "duration::milliseconds",
+        serialize_with = "duration::text"
+    )]
+    #[schemars(with = "DurationValue")]
+    debounce: Duration,
+    /// displayed on exit instead of the status before the start
+    exit_text: Option<String>,
+
+    control_fifo: Option<String>,
+    control_socket: Option<String>,
+
+    #[serde(default)]
+    output: OneOrMany<OutputFileConfig>,
+
+    #[serde(default)]
+    color_format: ColorFormat,
+    /// imports the colors of the desktop theme, in addition to `colors`
+    colors_from: Option<ColorSource>,
     #[serde(default)]
-    colors: HashMap<String, u8>,
+    colors: HashMap<String, ColorValue>,
     #[serde(flatten)]
     coloring: SegmentColorConfig,
 }
 
-#[derive(Deserialize, Debug)]
+/// a signal offset or its name, or `true` to use a signal that nothing else uses
+#[derive(Deserialize, JsonSchema, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+enum UpdateAllSignal {
+    Free(bool),
+    Offset(#[schemars(with = "SignalValue")] u32),
+}
+
+/// a color number of the statuscolors patch or a hex color
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(untagged)]
+enum ColorValue {
+    Number(u8),
+    Hex(String),
+}
+
+impl ColorValue {
+    fn into_color(self, name: &str, format: ColorFormat) -> Result<Color, String> {
+        match (self, format) {
+            (ColorValue::Number(c), ColorFormat::Statuscolors) => Ok(Color::Colored(c)),
+            (ColorValue::Hex(hex), ColorFormat::Status2d) => Color::from_hex(&hex)
+                .ok_or_else(|| format!("color {name} is no hex color (#rrggbb): {hex}")),
+            (ColorValue::Number(_), ColorFormat::Status2d) => Err(format!(
+                "color {name} has to be a hex color (#rrggbb) with the status2d color format"
+            )),
+            (ColorValue::Hex(_), ColorFormat::Statuscolors) => Err(format!(
+                "color {name} has to be a number with the statuscolors color format"
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum OutputFileConfig {
+    /// i3bar in Wayland sessions, xroot otherwise
+    Auto {
+        #[serde(default)]
+        colors: HashMap<String, String>,
+    },
+    Xroot,
+    I3bar {
+        /// hex colors for the names of the colors
+        #[serde(default)]
+        colors: HashMap<String, String>,
+    },
+    Stdout,
+    File {
+        path: String,
+    },
+}
+
+/// a single value or a list of them
+#[derive(Debug)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+/// unlike an untagged enum, this keeps the errors of the values
+impl<'de, T: serde::de::DeserializeOwned> Deserialize<'de> for OneOrMany<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+        match value {
+            serde_yaml::Value::Sequence(_) => serde_yaml::from_value(value).map(Self::Many),
+            _ => serde_yaml::from_value(value).map(Self::One),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for OneOrMany<T> {
+    fn schema_name() -> String {
+        format!("OneOrMany_{}", T::schema_name())
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    gen.subschema_for::<T>(),
+                    gen.subschema_for::<Vec<T>>(),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl<T: Default> Default for OneOrMany<T> {
+    fn default() -> Self {
+        Self::One(T::default())
+    }
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            Self::One(value) => vec![value],
+            Self::Many(values) => values,
+        }
+    }
+}
+
+impl Default for OutputFileConfig {
+    fn default() -> Self {
+        Self::Auto {
+            colors: HashMap::new(),
+        }
+    }
+}
+
+/// the time over which the start of the segments is spread with `stagger_startup`
+const STAGGER_WINDOW: Duration = Duration::from_secs(1);
+
+/// the size of the status text of dwm
+fn max_length_default() -> Option<usize> {
+    Some(256)
+}
+
+fn debounce_default() -> Duration {
+    Duration::from_millis(20)
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+struct FailureNotificationConfig {
+    #[serde(default = "failure_threshold_default")]
+    threshold: u32,
+    #[serde(
+        default = "failure_interval_default",
+        deserialize_with = "duration::seconds",
+        serialize_with = "duration::text"
+    )]
+    #[schemars(with = "DurationValue")]
+    interval: Duration,
+}
+
+fn failure_threshold_default() -> u32 {
+    1
+}
+
+fn failure_interval_default() -> Duration {
+    Duration::from_secs(300)
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
 struct SegmentConfig {
     #[serde(flatten)]
     kind: SegmentKindConfig,
-    update_interval: Option<u64>,
+    name: Option<String>,
+    /// the name of the template whose settings are used, see `ConfigFile::templates`
+    #[serde(default)]
+    #[allow(dead_code)]
+    template: Option<String>,
+    /// the X screens the segment is displayed on, all if empty.
+    /// Only separate X screens get their own status, not the monitors of a single screen with Xinerama.
+    #[serde(default)]
+    monitors: Vec<usize>,
+    /// the names of the segments whose updates also update this one
+    #[serde(default)]
+    depends_on: Vec<String>,
+    /// the segment is left out unless the condition holds
+    enable_if: Option<Condition>,
+    /// in seconds or a duration like `5m` or `1h30m`
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    #[schemars(with = "Option<DurationValue>")]
+    update_interval: Option<Duration>,
+    /// updates at the full second, minute or hour
+    align_to: Option<AlignTo>,
+    /// in the format of crontab, instead of the update interval
+    schedule: Option<String>,
+    #[schemars(with = "Vec<SignalValue>")]
     #[serde(default)]
     signals: Vec<u32>,
+    /// in seconds or a duration like `500ms`
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    #[schemars(with = "Option<DurationValue>")]
+    timeout: Option<Duration>,
+    /// in seconds or a duration, updates are not more frequent
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    #[schemars(with = "Option<DurationValue>")]
+    min_update_interval: Option<Duration>,
+    /// in seconds or a duration, updates within this time reuse the last value
+    #[serde(default, deserialize_with = "duration::optional_seconds")]
+    #[schemars(with = "Option<DurationValue>")]
+    cache_ttl: Option<Duration>,
+    #[serde(default = "segments::timeout_text_default")]
+    timeout_text: String,
+    /// displayed when the value could not be computed
+    on_error: Option<OnErrorConfig>,
+    retry: Option<RetryConfig>,
+    /// displays the last value instead of the error
+    #[serde(default)]
+    keep_last_on_error: bool,
+    #[serde(default)]
+    stale_marker: String,
 
     left_separator: Option<String>,
     right_separator: Option<String>,
     icon: Option<String>,
+    /// the icon is picked by the number in the value
+    #[serde(default)]
+    icons: Vec<String>,
+    #[serde(default)]
+    icon_position: IconPosition,
+    /// between the icon and the value
+    #[serde(default)]
+    icon_separator: String,
     #[serde(default)]
     hide_if_empty: bool,
+    /// patterns of values that hide the segment or are required to show it
+    hide_if: Option<String>,
+    show_if: Option<String>,
+    #[serde(default)]
+    transforms: Vec<Transform>,
+    number_format: Option<NumberFormat>,
+    /// arranges `{left}`, `{icon}`, `{value}` and `{right}`
+    format: Option<String>,
+    #[serde(default)]
+    min_width: usize,
+    #[serde(default)]
+    align: Align,
+    max_width: Option<usize>,
+    #[serde(default = "segments::ellipsis_default")]
+    ellipsis: String,
+    /// scrolls values that are longer than the width
+    scroll: Option<ScrollConfig>,
+    /// displays the last values as a sparkline
+    graph: Option<GraphConfig>,
+    /// displays the value as a progress bar
+    bar: Option<BarConfig>,
+    #[serde(default)]
+    area: Area,
+
+    menu: Option<MenuConfig>,
+    /// run when the segment is clicked, requires the statuscmd patch of dwm
+    click_command: Option<String>,
+    #[schemars(with = "Option<SignalValue>")]
+    toggle_signal: Option<u32>,
 
     #[serde(flatten)]
     coloring: SegmentColorConfig,
+    /// text colors for values that match the patterns, the first match wins
+    #[serde(default, deserialize_with = "ordered_map")]
+    #[schemars(with = "HashMap<String, String>")]
+    color_map: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+struct PluginConfig {
+    /// the shared object
+    path: String,
+    /// the function that creates the segment
+    #[serde(default = "plugin_symbol_default")]
+    symbol: String,
+}
+
+fn plugin_symbol_default() -> String {
+    "create_segment".into()
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+struct GroupConfig {
+    segments: Vec<SegmentConfig>,
+    /// between the segments of the group
+    #[serde(default = "group_separator_default")]
+    separator: String,
+    /// collapses and expands the group
+    #[schemars(with = "Option<SignalValue>")]
+    collapse_signal: Option<u32>,
+    /// displayed while the group is collapsed
+    #[serde(default = "collapsed_default")]
+    collapsed: String,
+}
+
+fn group_separator_default() -> String {
+    " ".into()
+}
+
+fn collapsed_default() -> String {
+    "…".into()
+}
+
+/// When a segment is used, all of the given conditions have to hold
+#[derive(Deserialize, JsonSchema, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct Condition {
+    /// the name of the machine
+    hostname: Option<String>,
+    /// `NAME` for a variable that is set and not empty, or `NAME=value`
+    env: Option<String>,
+    path_exists: Option<String>,
+}
+
+impl Condition {
+    fn holds(&self) -> Result<bool, String> {
+        if let Some(hostname) = &self.hostname {
+            if hostname != &local_hostname()? {
+                return Ok(false);
+            }
+        }
+        if let Some(env) = &self.env {
+            let holds = match env.split_once('=') {
+                Some((name, value)) => std::env::var(name).is_ok_and(|v| v == value),
+                None => std::env::var_os(env).is_some_and(|v| !v.is_empty()),
+            };
+            if !holds {
+                return Ok(false);
+            }
+        }
+        if let Some(path) = &self.path_exists {
+            if !expand_path(path)?.exists() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
 }
 
-#[derive(Deserialize, Debug)]
+fn local_hostname() -> Result<String, String> {
+    let mut name = [0u8; 256];
+    if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } != 0 {
+        return Err(format!(
+            "error getting the hostname: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    Ok(String::from_utf8_lossy(&name[..end]).into_owned())
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+struct OnErrorConfig {
+    #[serde(default = "on_error_text_default")]
+    text: String,
+    color: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+struct RetryConfig {
+    #[serde(default = "retry_attempts_default")]
+    attempts: u32,
+    /// in seconds or a duration, doubles with every retry
+    #[serde(
+        default = "retry_backoff_default",
+        deserialize_with = "duration::seconds",
+        serialize_with = "duration::text"
+    )]
+    #[schemars(with = "DurationValue")]
+    backoff: Duration,
+}
+
+fn retry_attempts_default() -> u32 {
+    3
+}
+
+fn retry_backoff_default() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn on_error_text_default() -> String {
+    "{error}".into()
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+struct MenuConfig {
+    #[schemars(with = "SignalValue")]
+    signal: u32,
+    #[serde(default = "menu_program_default")]
+    program: String,
+    #[serde(deserialize_with = "ordered_map")]
+    #[schemars(with = "HashMap<String, String>")]
+    items: Vec<(String, String)>,
+}
+
+fn menu_program_default() -> String {
+    "dmenu".into()
+}
+
+/// Deserializes a map while keeping the order of its entries
+fn ordered_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, String)>, D::Error> {
+    struct OrderedMapVisitor;
+
+    impl<'de> Visitor<'de> for OrderedMapVisitor {
+        type Value = Vec<(String, String)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of strings")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut entries = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(OrderedMapVisitor)
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(untagged)]
 enum SegmentKindConfig {
     Program {
         program: String,
         #[serde(default)]
         args: Vec<String>,
-        #[serde(default="true_default")]
-        trim: bool
+        #[serde(default = "true_default")]
+        trim: bool,
+        /// added to the environment of the program
+        #[serde(default)]
+        env: HashMap<String, String>,
+        /// the working directory of the program
+        cwd: Option<String>,
+        /// written to the standard input of the program on every run
+        stdin: Option<String>,
+        #[serde(default)]
+        on_invalid_utf8: InvalidUtf8,
     },
     ShellScript {
         script: String,
         #[serde(default)]
         args: Vec<String>,
-        #[serde(default="true_default")]
-        trim: bool
+        /// runs the script, e.g. `/bin/bash` or `python3 -u`,
+        /// by default executable scripts with a shebang line are run directly and others with `/bin/sh`
+        shell: Option<String>,
+        #[serde(default = "true_default")]
+        trim: bool,
+        /// added to the environment of the script
+        #[serde(default)]
+        env: HashMap<String, String>,
+        /// the working directory of the script
+        cwd: Option<String>,
+        /// written to the standard input of the script on every run
+        stdin: Option<String>,
+        #[serde(default)]
+        on_invalid_utf8: InvalidUtf8,
+    },
+    Shell {
+        sh: String,
+        #[serde(default = "true_default")]
+        trim: bool,
+        #[serde(default)]
+        on_invalid_utf8: InvalidUtf8,
     },
     Constant {
         constant: String,
     },
+    /// values of the shared context, e.g. of other segments
+    Context {
+        context: String,
+    },
+    /// several segments displayed as one
+    Group {
+        group: GroupConfig,
+    },
+    /// one of several segments, the next one is shown on the view signal
+    Views {
+        views: Vec<SegmentConfig>,
+        #[schemars(with = "Option<SignalValue>")]
+        view_signal: Option<u32>,
+    },
+    /// a segment from a shared object
+    Plugin {
+        plugin: PluginConfig,
+        #[serde(default)]
+        options: serde_json::Value,
+    },
+    /// a segment computed by a Lua script
+    Lua {
+        lua: String,
+    },
+    /// a segment kind of the registry of the program that embeds dwmblocksrs
+    Custom {
+        custom: String,
+        #[serde(default)]
+        #[schemars(with = "serde_json::Value")]
+        options: serde_yaml::Value,
+    },
+    BluetoothBattery {
+        bluetooth_battery: segments::bluetooth::BluetoothBatteryConfig,
+    },
+    Sysfs {
+        sysfs: segments::sysfs::SysfsConfig,
+    },
+    Battery {
+        battery: segments::battery::BatteryConfig,
+    },
+    Cpu {
+        cpu: segments::cpu::CpuConfig,
+    },
+    Disk {
+        disk: segments::disk::DiskConfig,
+    },
+    Volume {
+        volume: segments::volume::VolumeConfig,
+    },
+    Wifi {
+        wifi: segments::wifi::WifiConfig,
+    },
+    Mpd {
+        mpd: segments::mpd::MpdConfig,
+    },
+    Load {
+        load: segments::load::LoadConfig,
+    },
+    Maildir {
+        maildir: segments::maildir::MaildirConfig,
+    },
+    Updates {
+        updates: segments::updates::UpdatesConfig,
+    },
+    Upower {
+        upower: segments::upower::UpowerConfig,
+    },
+    Dunst {
+        dunst: segments::dunst::DunstConfig,
+    },
+    Timer {
+        timer: segments::timer::TimerConfig,
+    },
+    Tasks {
+        tasks: segments::tasks::TasksConfig,
+    },
+    Ticker {
+        ticker: segments::ticker::TickerConfig,
+    },
+    Astro {
+        astro: segments::astro::AstroConfig,
+    },
+    ActiveWindow {
+        active_window: segments::active_window::ActiveWindowConfig,
+    },
+    IdleInhibit {
+        idle_inhibit: segments::idle_inhibit::IdleInhibitConfig,
+    },
+    Sysstat {
+        sysstat: segments::sysstat::SysstatConfig,
+    },
+    File {
+        file: segments::file::FileConfig,
+    },
+    Dbus {
+        dbus: segments::dbus::DbusConfig,
+    },
+    Persistent {
+        persistent: String,
+    },
+    Manual {
+        manual: String,
+    },
 }
 
 fn true_default() -> bool {
     true
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 struct SegmentColorConfig {
     text_color: Option<String>,
     left_separator_color: Option<String>,
     right_separator_color: Option<String>,
     icon_color: Option<String>,
+    text_background: Option<String>,
+    left_separator_background: Option<String>,
+    right_separator_background: Option<String>,
+    icon_background: Option<String>,
 }
 
 #[derive(Debug, Default)]
 pub struct Configuration {
     pub script_dir: PathBuf,
     pub update_all_signal: Option<u32>,
+    /// the offsets of the signals with names
+    pub signal_names: BTreeMap<String, u32>,
+    pub failure_notifier: Option<FailureNotifier>,
+    pub on_start: Vec<String>,
+    pub on_exit: Vec<String>,
+    pub deduplicate_commands: bool,
+    /// the number of programs and scripts that may run at the same time
+    pub max_concurrent_commands: Option<usize>,
+    pub status_bar: StatusBarConfig,
+    pub control_fifo: Option<PathBuf>,
+    pub control_socket: Option<PathBuf>,
+    pub color_format: ColorFormat,
+    /// files besides the config whose changes reload the config
+    pub watched_files: Vec<PathBuf>,
 
     // defaults
     pub coloring: SegmentColoring,
     pub left_separator: Option<String>,
     pub right_separator: Option<String>,
+    pub replacement_character: Option<String>,
+}
+
+pub(crate) fn parse_config(config: PathBuf) -> Result<(Configuration, Vec<Segment>), String> {
+    parse(config, false, &Registry::default())
+}
+
+/// Like `parse_config`, but the segments can have the custom kinds of the registry
+pub(crate) fn parse_config_with_registry(
+    config: PathBuf,
+    registry: &Registry,
+) -> Result<(Configuration, Vec<Segment>), String> {
+    parse(config, false, registry)
+}
+
+/// Parses the configuration and also checks that the scripts and programs of the segments exist.
+/// All errors that were found are returned together.
+pub(crate) fn check_config(config: PathBuf) -> Result<(), String> {
+    parse(config, true, &Registry::default()).map(|_| ())
 }
 
-pub(crate) fn parse_config(config: PathBuf) -> Result<Vec<Segment>, String> {
+fn parse(
+    config: PathBuf,
+    check_commands: bool,
+    registry: &Registry,
+) -> Result<(Configuration, Vec<Segment>), String> {
     let config_str = read_to_string(&config).map_err(|e| {
         format!(
             "Error reading config file '{}': {}",
@@ -95,17 +768,45 @@ pub(crate) fn parse_config(config: PathBuf) -> Result<Vec<Segment>, String> {
             e
         )
     })?;
+    let format = ConfigFormat::of(&config);
+    let (config_file, parts, origins) = read_config(&config, config_str, format)?;
 
     let ConfigFile {
         segments,
+        include: _,
+        templates: _,
         left_separator,
         right_separator,
+        replacement_character,
         update_all_signal,
+        signal_names,
         script_dir,
-        colors,
+        failure_notifications,
+        on_start,
+        on_exit,
+        deduplicate_commands,
+        max_concurrent_commands,
+        stagger_startup,
+        area_delimiter,
+        prefix,
+        suffix,
+        separator,
+        max_length,
+        overflow,
+        debounce,
+        exit_text,
+        control_fifo,
+        control_socket,
+        output,
+        color_format,
+        colors_from,
+        mut colors,
         coloring,
-    } = serde_yaml::from_str(&config_str)
-        .map_err(|e| SerdeError::new(config_str, e).to_string())?;
+    } = config_file;
+
+    if max_concurrent_commands == Some(0) {
+        return Err("max_concurrent_commands has to be at least 1".into());
+    }
 
     let script_dir = match script_dir {
         // if a script directory was set in the config
@@ -126,71 +827,483 @@ pub(crate) fn parse_config(config: PathBuf) -> Result<Vec<Segment>, String> {
         None => Default::default(),
     };
 
+    // the colors in the config win over the imported ones
+    if let Some(source) = colors_from {
+        for (name, hex) in source.load()? {
+            colors.entry(name).or_insert(ColorValue::Hex(hex));
+        }
+    }
+    let colors = colors
+        .into_iter()
+        .map(|(name, color)| Ok((name.clone(), color.into_color(&name, color_format)?)))
+        .collect::<Result<HashMap<_, _>, String>>()?;
     let coloring = SegmentColoring::from(coloring, &colors)?;
 
-    let configuration = Configuration {
+    // a free signal is picked once the signals of the segments are known
+    let (update_all_signal, free_update_all_signal) = match update_all_signal {
+        Some(UpdateAllSignal::Offset(signal)) => (Some(signal), false),
+        Some(UpdateAllSignal::Free(free)) => (None, free),
+        None => (None, false),
+    };
+    if let Some(signal) = update_all_signal.filter(|s| *s > segments::max_signal_offset()) {
+        return Err(format!(
+            "update_all_signal {signal} is greater than SIGRTMAX-SIGRTMIN ({})",
+            segments::max_signal_offset()
+        ));
+    }
+    if let Some((name, signal)) = signal_names
+        .iter()
+        .find(|(_, signal)| **signal > segments::max_signal_offset())
+    {
+        return Err(format!(
+            "the signal {name} ({signal}) is greater than SIGRTMAX-SIGRTMIN ({})",
+            segments::max_signal_offset()
+        ));
+    }
+
+    let mut configuration = Configuration {
         left_separator,
         right_separator,
+        replacement_character,
 
         script_dir,
         update_all_signal,
+        signal_names,
+        failure_notifier: failure_notifications
+            .map(|n| FailureNotifier::new(n.threshold, n.interval)),
+        on_start,
+        on_exit,
+        deduplicate_commands,
+        max_concurrent_commands,
+        status_bar: StatusBarConfig {
+            area_delimiter: area_delimiter.unwrap_or_else(|| ";".into()),
+            prefix,
+            suffix,
+            separator,
+            max_length,
+            overflow,
+            debounce,
+            exit_text,
+            color_format,
+            outputs: output
+                .into_vec()
+                .into_iter()
+                .map(|output| output.into_output(&colors, color_format))
+                .collect::<Result<_, _>>()?,
+        },
+        control_fifo: control_fifo.map(expand_path).transpose()?,
+        control_socket: control_socket.map(expand_path).transpose()?,
+        color_format,
+        watched_files: colors_from
+            .map(ColorSource::path)
+            .transpose()?
+            .into_iter()
+            .chain(parts.iter().skip(1).map(|part| part.path.clone()))
+            .collect(),
         coloring,
     };
 
-    let segments = segments
-        .into_iter()
-        .map(|segment_config| parse_segment(segment_config, &configuration, &colors))
-        .collect::<Result<Vec<Segment>, String>>()?;
+    let mut errors = Vec::new();
+    let mut parsed_segments = Vec::new();
+    // the names of the segments whose conditions don't hold
+    let mut disabled = HashSet::new();
+    let mut signals = Vec::new();
+    for (index, segment_config) in segments.into_iter().enumerate() {
+        let name = segment_config.name.clone();
+        let enabled = match &segment_config.enable_if {
+            Some(condition) => condition.holds(),
+            None => Ok(true),
+        };
+        match enabled {
+            Ok(true) => {}
+            Ok(false) => {
+                disabled.extend(segment_config.name);
+                continue;
+            }
+            Err(e) => {
+                errors.push(segment_error(&parts, origins[index], name.as_deref(), e));
+                continue;
+            }
+        }
+        let checked = match check_commands {
+            true => check_command(&segment_config.kind, &configuration),
+            false => Ok(()),
+        };
+        let mut segment_signals = SegmentSignals::of(
+            segment_label(&parts, origins[index], name.as_deref()),
+            &segment_config,
+        );
+        match checked.and_then(|_| parse_segment(segment_config, &configuration, &colors, registry))
+        {
+            Ok(segment) => {
+                segment_signals.add_control_signals(segment.control_signals());
+                signals.push(segment_signals);
+                parsed_segments.push(segment);
+            }
+            Err(e) => errors.push(segment_error(&parts, origins[index], name.as_deref(), e)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    let mut segments = parsed_segments;
+    if free_update_all_signal {
+        let signal = signals::free_signal(&signals, &configuration.signal_names)
+            .ok_or("there is no free signal left for the update_all_signal")?;
+        info!("the update_all_signal is {signal}, `pkill -RTMIN+{signal} dwmblocksrs` updates all segments");
+        for segment in &mut segments {
+            segment.add_signal(signal)?;
+        }
+        configuration.update_all_signal = Some(signal);
+    }
+    signals::check_signals(&signals, configuration.update_all_signal)?;
+    if stagger_startup {
+        let count = segments.len() as u32;
+        for (index, segment) in segments.iter_mut().enumerate() {
+            segment.startup_delay = STAGGER_WINDOW * index as u32 / count;
+        }
+    }
+
+    let mut names = HashSet::new();
+    if let Some(name) = segments
+        .iter()
+        .filter_map(|s| s.name.as_ref())
+        .find(|name| !names.insert(*name))
+    {
+        return Err(format!("there are several segments named '{name}'"));
+    }
+    for segment in &mut segments {
+        segment.depends_on.retain(|name| !disabled.contains(name));
+    }
+    check_dependencies(&segments)?;
 
-    Ok(segments)
+    Ok((configuration, segments))
+}
+
+/// Checks that the segments depend on existing segments, without cycles
+fn check_dependencies(segments: &[Segment]) -> Result<(), String> {
+    let dependencies = segments
+        .iter()
+        .filter_map(|s| s.name.as_deref().map(|name| (name, &s.depends_on)))
+        .collect::<HashMap<_, _>>();
+    for segment in segments {
+        if let Some(name) = segment
+            .depends_on
+            .iter()
+            .find(|name| !dependencies.contains_key(name.as_str()))
+        {
+            return Err(format!("there is no segment named '{name}' to depend on"));
+        }
+    }
+
+    // follows the dependencies of every segment, a cycle would update the segments forever
+    for &start in dependencies.keys() {
+        let mut visited = HashSet::new();
+        let mut pending = vec![start];
+        while let Some(name) = pending.pop() {
+            for dependency in dependencies[name] {
+                if dependency == start {
+                    return Err(format!("segment '{start}' depends on itself"));
+                }
+                if visited.insert(dependency.as_str()) {
+                    pending.push(dependency);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The program and the arguments that run the script with the shell.
+/// Without a shell, executable scripts with a shebang line are run directly and others with `/bin/sh`.
+fn script_command(
+    shell: Option<&str>,
+    script: PathBuf,
+    mut args: Vec<String>,
+) -> Result<(PathBuf, Vec<String>), String> {
+    let executable = script
+        .metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        && std::fs::File::open(&script)
+            .and_then(|mut file| {
+                let mut start = [0; 2];
+                file.read_exact(&mut start).map(|_| start)
+            })
+            .is_ok_and(|start| &start == b"#!");
+    let mut shell = match shell {
+        Some(shell) => shell.split_whitespace().map(String::from).collect(),
+        None if executable => return Ok((script, args)),
+        None => vec!["/bin/sh".into()],
+    };
+    if shell.is_empty() {
+        return Err("the shell is empty".into());
+    }
+
+    let program = expand_path(shell.remove(0))?;
+    shell.push(script.to_string_lossy().into_owned());
+    shell.append(&mut args);
+    Ok((program, shell))
 }
 
 fn parse_segment(
     segment_config: SegmentConfig,
     config: &Configuration,
-    colors: &HashMap<String, u8>,
+    colors: &HashMap<String, Color>,
+    registry: &Registry,
 ) -> Result<Segment, String> {
     let SegmentConfig {
         kind,
+        name,
+        template: _,
+        monitors,
+        depends_on,
+        enable_if: _,
         update_interval,
+        align_to,
+        schedule,
         mut signals,
+        timeout,
+        min_update_interval,
+        cache_ttl,
+        timeout_text,
+        on_error,
+        retry,
+        keep_last_on_error,
+        stale_marker,
         left_separator,
         right_separator,
         icon,
+        icons,
+        icon_position,
+        icon_separator,
         hide_if_empty,
+        hide_if,
+        show_if,
+        transforms,
+        number_format,
+        format,
+        min_width,
+        align,
+        max_width,
+        ellipsis,
+        scroll,
+        graph,
+        bar,
+        area,
+        menu,
+        click_command,
+        toggle_signal,
 
         coloring,
+        color_map,
     } = segment_config;
 
+    // the first signal of the segment identifies it for clicks with the statuscmd patch
+    let click = click_command.map(|command| (signals.first().copied(), command));
+
+    // checking for updates is expensive, so it is not done too often by default
+    let update_interval = match kind {
+        _ if schedule.is_some() => update_interval,
+        SegmentKindConfig::Updates { .. } => update_interval.or(Some(Duration::from_secs(3600))),
+        // the remaining time is shown in seconds
+        SegmentKindConfig::Timer { .. } => update_interval.or(Some(Duration::from_secs(1))),
+        _ => update_interval,
+    };
+
+    // failing programs are only reported when there is a text for it, they are retried,
+    // the last value is kept or notifications are sent
+    let fail_on_error = on_error.is_some()
+        || retry.is_some()
+        || keep_last_on_error
+        || config.failure_notifier.is_some();
+
     let kind: Box<dyn SegmentKind> = match kind {
-        SegmentKindConfig::Program { program, args , trim } => Box::new(
-            segments::program_output::ProgramOutput::new(expand_path(program)?, args, trim),
+        SegmentKindConfig::Program {
+            program,
+            args,
+            trim,
+            env,
+            cwd,
+            stdin,
+            on_invalid_utf8,
+        } => Box::new(
+            segments::program_output::ProgramOutput::new(expand_path(program)?, args, trim)
+                .deduplicate(config.deduplicate_commands)
+                .fail_on_error(fail_on_error)
+                .timeout(timeout)
+                .env(env)
+                .current_dir(cwd.map(expand_path).transpose()?)
+                .stdin(stdin)
+                .on_invalid_utf8(on_invalid_utf8),
         ),
-        SegmentKindConfig::ShellScript { script, mut args , trim} => {
+        SegmentKindConfig::ShellScript {
+            script,
+            args,
+            shell,
+            trim,
+            env,
+            cwd,
+            stdin,
+            on_invalid_utf8,
+        } => {
             let mut script_path = config.script_dir.clone();
             script_path.push(expand_path(script)?);
-            args.insert(0, script_path.to_str().unwrap().into());
+            let (program, args) = script_command(shell.as_deref(), script_path, args)?;
 
-            Box::new(segments::program_output::ProgramOutput::new(
+            Box::new(
+                segments::program_output::ProgramOutput::new(program, args, trim)
+                    .deduplicate(config.deduplicate_commands)
+                    .fail_on_error(fail_on_error)
+                    .timeout(timeout)
+                    .env(env)
+                    .current_dir(cwd.map(expand_path).transpose()?)
+                    .stdin(stdin)
+                    .on_invalid_utf8(on_invalid_utf8),
+            )
+        }
+        SegmentKindConfig::Shell {
+            sh,
+            trim,
+            on_invalid_utf8,
+        } => Box::new(
+            segments::program_output::ProgramOutput::new(
                 "/bin/sh".into(),
-                args,
-                trim
+                vec!["-c".into(), sh],
+                trim,
+            )
+            .deduplicate(config.deduplicate_commands)
+            .fail_on_error(fail_on_error)
+            .timeout(timeout)
+            .on_invalid_utf8(on_invalid_utf8),
+        ),
+        SegmentKindConfig::Custom { custom, options } => registry.create(&custom, options)?,
+        #[cfg(feature = "plugins")]
+        SegmentKindConfig::Plugin { plugin, options } => Box::new(segments::plugin::Plugin::load(
+            &expand_path(plugin.path)?,
+            &plugin.symbol,
+            &options.to_string(),
+        )?),
+        #[cfg(not(feature = "plugins"))]
+        SegmentKindConfig::Plugin { plugin, options } => {
+            let _ = (plugin.path, plugin.symbol, options);
+            return Err("plugin segments need dwmblocksrs built with the plugins feature".into());
+        }
+        #[cfg(feature = "lua")]
+        SegmentKindConfig::Lua { lua } => Box::new(segments::lua::Lua::new(&lua, colors.clone())?),
+        #[cfg(not(feature = "lua"))]
+        SegmentKindConfig::Lua { lua } => {
+            let _ = lua;
+            return Err("lua segments need dwmblocksrs built with the lua feature".into());
+        }
+        SegmentKindConfig::Group { group } => {
+            let segments = group
+                .segments
+                .into_iter()
+                .map(|segment| parse_segment(segment, config, colors, registry))
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("in the group: {e}"))?;
+            Box::new(segments::group::Group::new(
+                segments,
+                group.separator,
+                group.collapse_signal,
+                group.collapsed,
             ))
         }
+        SegmentKindConfig::Views { views, view_signal } => {
+            let views = views
+                .into_iter()
+                .map(|segment| parse_segment(segment, config, colors, registry))
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("in the views: {e}"))?;
+            Box::new(segments::views::Views::new(views, view_signal)?)
+        }
+        SegmentKindConfig::Context { context } => {
+            Box::new(segments::context::Context::new(context))
+        }
         SegmentKindConfig::Constant { constant } => {
             Box::new(segments::constant::Constant::new(constant))
         }
+        SegmentKindConfig::BluetoothBattery { bluetooth_battery } => Box::new(
+            segments::bluetooth::BluetoothBattery::new(bluetooth_battery),
+        ),
+        SegmentKindConfig::Battery { battery } => {
+            Box::new(segments::battery::Battery::new(battery))
+        }
+        SegmentKindConfig::Cpu { cpu } => Box::new(segments::cpu::Cpu::new(cpu)),
+        SegmentKindConfig::Disk { disk } => Box::new(segments::disk::Disk::new(disk)),
+        SegmentKindConfig::Volume { volume } => Box::new(segments::volume::Volume::new(volume)),
+        SegmentKindConfig::Wifi { wifi } => Box::new(segments::wifi::Wifi::new(wifi)),
+        SegmentKindConfig::Mpd { mpd } => Box::new(segments::mpd::Mpd::new(mpd)),
+        SegmentKindConfig::Load { load } => Box::new(segments::load::Load::new(load)),
+        SegmentKindConfig::Maildir { maildir } => Box::new(segments::maildir::Maildir::new(
+            maildir
+                .paths
+                .iter()
+                .map(expand_path)
+                .collect::<Result<_, _>>()?,
+            maildir.format,
+        )),
+        SegmentKindConfig::Updates { updates } => {
+            Box::new(segments::updates::Updates::new(updates))
+        }
+        SegmentKindConfig::Upower { upower } => Box::new(segments::upower::Upower::new(upower)),
+        SegmentKindConfig::Dunst { dunst } => Box::new(segments::dunst::Dunst::new(dunst)),
+        SegmentKindConfig::Timer { timer } => Box::new(segments::timer::Timer::new(timer)),
+        SegmentKindConfig::Tasks { tasks } => Box::new(segments::tasks::Tasks::new(
+            tasks.source.clone(),
+            expand_path(tasks.source.path())?,
+            tasks.format,
+        )),
+        SegmentKindConfig::Ticker { ticker } => Box::new(segments::ticker::Ticker::new(ticker)),
+        SegmentKindConfig::Astro { astro } => Box::new(segments::astro::Astro::new(astro)),
+        SegmentKindConfig::ActiveWindow { active_window } => {
+            Box::new(segments::active_window::ActiveWindow::new(active_window))
+        }
+        SegmentKindConfig::IdleInhibit { idle_inhibit } => {
+            Box::new(segments::idle_inhibit::IdleInhibit::new(idle_inhibit))
+        }
+        SegmentKindConfig::Sysstat { sysstat } => {
+            Box::new(segments::sysstat::Sysstat::new(sysstat))
+        }
+        SegmentKindConfig::File { file } => Box::new(segments::file::File::new(
+            expand_path(file.path)?,
+            file.last_line,
+        )),
+        SegmentKindConfig::Dbus { dbus } => Box::new(segments::dbus::DbusProperty::new(dbus)?),
+        SegmentKindConfig::Persistent { persistent } => {
+            Box::new(segments::persistent::Persistent::new(persistent))
+        }
+        SegmentKindConfig::Manual { manual } => Box::new(segments::manual::Manual::new(manual)),
+        SegmentKindConfig::Sysfs { sysfs } => Box::new(segments::sysfs::Sysfs::new(
+            expand_path(sysfs.path)?,
+            sysfs.scale,
+            sysfs.offset,
+            sysfs.precision,
+            sysfs.unit,
+        )),
     };
 
-    let coloring = SegmentColoring::from(coloring, &colors)?;
+    let kind: Box<dyn SegmentKind> = match bar {
+        Some(bar) => Box::new(Bar::new(kind, bar)),
+        None => kind,
+    };
+    let kind: Box<dyn SegmentKind> = match graph {
+        Some(graph) => Box::new(Graph::new(kind, graph)),
+        None => kind,
+    };
+    let kind: Box<dyn SegmentKind> = match scroll {
+        Some(scroll) => Box::new(Scroll::new(kind, scroll, update_interval)),
+        None => kind,
+    };
 
+    let coloring = SegmentColoring::from(coloring, colors)?;
+
+    let update_signal = signals.first().copied();
     if let Some(offset) = config.update_all_signal {
         signals.push(offset);
     }
 
-    let update_interval = update_interval.map(Duration::from_secs);
-
-    Segment::new_from_config(
+    let mut segment = Segment::new_from_config(
         kind,
         update_interval,
         signals,
@@ -200,7 +1313,669 @@ fn parse_segment(
         hide_if_empty,
         coloring,
         config,
-    )
+    )?;
+    segment.area = area;
+    segment.icons = icons;
+    segment.icon_position = icon_position;
+    segment.icon_separator = icon_separator;
+    segment.transforms = transforms;
+    segment.number_format = number_format;
+    segment.format = format;
+    segment.min_width = min_width;
+    segment.align = align;
+    segment.max_width = max_width;
+    segment.ellipsis = ellipsis;
+    segment.name = name;
+    segment.update_signal = update_signal;
+    segment.monitors = monitors;
+    segment.depends_on = depends_on;
+    segment.color_map = color_map
+        .into_iter()
+        .map(|(pattern, color)| {
+            let pattern =
+                Regex::new(&pattern).map_err(|e| format!("invalid pattern in color_map: {e}"))?;
+            Ok((pattern, SegmentColoring::color_lookup(Some(color), colors)?))
+        })
+        .collect::<Result<_, String>>()?;
+    let pattern = |pattern: Option<String>, option: &str| {
+        pattern
+            .map(|pattern| Regex::new(&pattern))
+            .transpose()
+            .map_err(|e| format!("invalid pattern in {option}: {e}"))
+    };
+    segment.hide_if = pattern(hide_if, "hide_if")?;
+    segment.show_if = pattern(show_if, "show_if")?;
+    segment.timeout = timeout;
+    if align_to.is_some() && update_interval.is_none() {
+        return Err("align_to requires an update_interval".into());
+    }
+    segment.align_to = align_to;
+    if schedule.is_some() && update_interval.is_some() {
+        return Err("a segment can't have both a schedule and an update_interval".into());
+    }
+    segment.schedule = schedule
+        .map(|schedule| schedule.parse())
+        .transpose()
+        .map_err(|e| format!("invalid schedule: {e}"))?;
+    segment.retry = retry.map(|retry| Retry {
+        attempts: retry.attempts,
+        backoff: retry.backoff,
+    });
+    segment.keep_last_on_error = keep_last_on_error;
+    segment.stale_marker = stale_marker;
+    segment.cache_ttl = cache_ttl;
+    segment.min_update_interval = min_update_interval;
+    segment.timeout_text = timeout_text;
+    segment.on_error = on_error
+        .map(|on_error| {
+            let color = on_error
+                .color
+                .map(|color| SegmentColoring::color_lookup(Some(color), colors))
+                .transpose()?;
+            Ok::<_, String>(OnError {
+                text: on_error.text,
+                color,
+            })
+        })
+        .transpose()?;
+
+    if let Some(MenuConfig {
+        signal,
+        program,
+        items,
+    }) = menu
+    {
+        segment.set_menu(signal, Menu::new(program, items))?;
+    }
+
+    if let Some((signal, command)) = click {
+        segment.set_click_command(signal, command)?;
+    }
+
+    if let Some(signal) = toggle_signal {
+        segment.set_toggle_signal(signal)?;
+    }
+
+    Ok(segment)
+}
+
+/// Checks that the program or script the segment runs exists
+fn check_command(kind: &SegmentKindConfig, config: &Configuration) -> Result<(), String> {
+    match kind {
+        SegmentKindConfig::Program { program, .. } => {
+            let program = expand_path(program)?;
+            let found = match program.components().count() {
+                // programs without a directory are searched in PATH
+                1 => std::env::var_os("PATH")
+                    .map(|path| {
+                        std::env::split_paths(&path).any(|dir| dir.join(&program).is_file())
+                    })
+                    .unwrap_or(false),
+                _ => program.is_file(),
+            };
+            match found {
+                true => Ok(()),
+                false => Err(format!("program '{}' not found", program.display())),
+            }
+        }
+        SegmentKindConfig::ShellScript { script, .. } => {
+            let script = config.script_dir.join(expand_path(script)?);
+            match script.is_file() {
+                true => Ok(()),
+                false => Err(format!("script '{}' does not exist", script.display())),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A file of the configuration, the main one or an included one
+struct ConfigPart {
+    path: PathBuf,
+    text: String,
+    format: ConfigFormat,
+}
+
+/// the index of the file of a segment and the index of the segment in that file
+type Origin = (usize, usize);
+
+/// The JSON Schema of the config file, for editors that validate and complete it
+pub(crate) fn schema() -> String {
+    serde_json::to_string_pretty(&schemars::schema_for!(ConfigFile))
+        .expect("the schema is valid JSON")
+}
+
+/// Reads the config, merges the files it includes into it and applies the templates of the segments.
+/// Returns the files and the origins of the segments.
+fn read_config(
+    path: &Path,
+    text: String,
+    format: ConfigFormat,
+) -> Result<(ConfigFile, Vec<ConfigPart>, Vec<Origin>), String> {
+    let value: serde_yaml::Value = format.deserialize(&text)?;
+    let mut parts = vec![ConfigPart {
+        path: path.to_path_buf(),
+        text,
+        format,
+    }];
+
+    // without includes, templates and signal names, errors point to their exact place in the file
+    let uses_templates = value.get("templates").is_some()
+        || value
+            .get("segments")
+            .and_then(serde_yaml::Value::as_sequence)
+            .is_some_and(|segments| segments.iter().any(|s| s.get("template").is_some()));
+    let schema =
+        serde_json::to_value(schemars::schema_for!(ConfigFile)).expect("the schema is valid JSON");
+    if value.get("include").is_none() && value.get("signal_names").is_none() && !uses_templates {
+        let mut settings = value.clone();
+        let segments = match settings.as_mapping_mut() {
+            Some(settings) => match settings.remove(&"segments".into()) {
+                Some(serde_yaml::Value::Sequence(segments)) => segments,
+                _ => Vec::new(),
+            },
+            None => Vec::new(),
+        };
+        let origins = (0..segments.len())
+            .map(|index| (0, index))
+            .collect::<Vec<_>>();
+        check_fields(
+            &parts,
+            &schema,
+            &settings,
+            segments.iter().zip(origins.iter().copied()),
+        )?;
+
+        let config: ConfigFile = format
+            .deserialize(&parts[0].text)
+            .map_err(|e| name_segments(&e, &segments))?;
+        return Ok((config, parts, origins));
+    }
+
+    let (mut config, segments) = merge_includes(value, &mut parts, &mut Vec::new())?;
+    let templates = match config.remove(&"templates".into()) {
+        Some(templates) => {
+            serde_yaml::from_value(templates).map_err(|e| format!("invalid templates: {e}"))?
+        }
+        None => HashMap::new(),
+    };
+
+    // the segments are deserialized one by one, so that errors point to the segment
+    let mut segment_configs = Vec::new();
+    let mut origins = Vec::new();
+    let mut errors = Vec::new();
+    let mut applied = Vec::new();
+    for (segment, origin) in segments {
+        match apply_template(segment, &templates) {
+            Ok(segment) => applied.push((segment, origin)),
+            Err(e) => errors.push(segment_error(&parts, origin, None, e)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    let mut settings = serde_yaml::Value::Mapping(config);
+    check_fields(
+        &parts,
+        &schema,
+        &settings,
+        applied.iter().map(|(segment, origin)| (segment, *origin)),
+    )?;
+
+    let signal_names: BTreeMap<String, u32> = match settings.get("signal_names") {
+        Some(names) => serde_yaml::from_value(names.clone())
+            .map_err(|e| format!("invalid signal_names: {e}"))?,
+        None => BTreeMap::new(),
+    };
+    signals::resolve_names(&mut settings, &schema, &schema, &signal_names)?;
+    let segment_schema = &schema["definitions"]["SegmentConfig"];
+    for (segment, origin) in &mut applied {
+        if let Err(e) = signals::resolve_names(segment, segment_schema, &schema, &signal_names) {
+            errors.push(segment_error(&parts, *origin, segment_name(segment), e));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
+    for (segment, origin) in applied {
+        let name = segment_name(&segment).map(String::from);
+        match serde_yaml::from_value(segment) {
+            Ok(segment) => {
+                segment_configs.push(segment);
+                origins.push(origin);
+            }
+            Err(e) => errors.push(segment_error(
+                &parts,
+                origin,
+                name.as_deref(),
+                fields::suggest(&e.to_string()),
+            )),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
+    let serde_yaml::Value::Mapping(mut config) = settings else {
+        unreachable!("the settings are a mapping")
+    };
+    config.insert("segments".into(), serde_yaml::Value::Sequence(Vec::new()));
+    let mut config: ConfigFile = serde_yaml::from_value(serde_yaml::Value::Mapping(config))
+        .map_err(|e| fields::suggest(&e.to_string()))?;
+    config.segments = segment_configs;
+    Ok((config, parts, origins))
+}
+
+/// The settings of the template of the segment, replaced by the ones of the segment
+fn apply_template(
+    segment: serde_yaml::Value,
+    templates: &HashMap<String, serde_yaml::Mapping>,
+) -> Result<serde_yaml::Value, String> {
+    let serde_yaml::Value::Mapping(mut segment) = segment else {
+        return Ok(segment);
+    };
+    let name = match segment.remove(&"template".into()) {
+        Some(serde_yaml::Value::String(name)) => name,
+        Some(_) => return Err("the template has to be a name".into()),
+        None => return Ok(serde_yaml::Value::Mapping(segment)),
+    };
+    let mut settings = templates
+        .get(&name)
+        .ok_or_else(|| format!("there is no template named '{name}'"))?
+        .clone();
+    settings.extend(segment);
+    Ok(serde_yaml::Value::Mapping(settings))
+}
+
+/// The error of a segment, pointing to the line where the segment starts in its file
+fn segment_error(
+    parts: &[ConfigPart],
+    (part, index): Origin,
+    name: Option<&str>,
+    error: String,
+) -> String {
+    let (line, column) = match parts[part]
+        .format
+        .segment_lines(&parts[part].text)
+        .get(index)
+    {
+        Some((line, column)) => (Some(*line), Some(*column)),
+        None => (None, None),
+    };
+    positioned_segment_error(parts, (part, index), name, error, line, column)
+}
+
+fn positioned_segment_error(
+    parts: &[ConfigPart],
+    (part, index): Origin,
+    name: Option<&str>,
+    error: String,
+    line: Option<usize>,
+    column: Option<usize>,
+) -> String {
+    let error = format!("{}: {}", segment_label(parts, (part, index), name), error);
+    SerdeError::new(parts[part].text.clone(), (error.into(), line, column)).to_string()
+}
+
+/// The segment in messages, e.g. `segment 2 (volume)`
+fn segment_label(parts: &[ConfigPart], (part, index): Origin, name: Option<&str>) -> String {
+    let name = name.map(|name| format!(" ({name})")).unwrap_or_default();
+    match part {
+        0 => format!("segment {}{}", index + 1, name),
+        _ => format!(
+            "segment {}{} of '{}'",
+            index + 1,
+            name,
+            parts[part].path.display()
+        ),
+    }
+}
+
+fn segment_name(segment: &serde_yaml::Value) -> Option<&str> {
+    segment.get("name").and_then(serde_yaml::Value::as_str)
+}
+
+/// Replaces the indices of segments in errors, e.g. `segments[2]`, with their number and name
+fn name_segments(error: &str, segments: &[serde_yaml::Value]) -> String {
+    lazy_static! {
+        static ref SEGMENT_INDEX: Regex = Regex::new(r"segments\[(\d+)\]\.?").unwrap();
+    }
+    SEGMENT_INDEX
+        .replace_all(error, |captures: &regex::Captures| {
+            let index: usize = captures[1].parse().unwrap();
+            match segments.get(index).and_then(segment_name) {
+                Some(name) => format!("segment {} ({}): ", index + 1, name),
+                None => format!("segment {}: ", index + 1),
+            }
+        })
+        .into_owned()
+}
+
+/// Reports the fields of the settings and the segments that don't exist, e.g. because of typos
+fn check_fields<'a>(
+    parts: &[ConfigPart],
+    schema: &serde_json::Value,
+    settings: &serde_yaml::Value,
+    segments: impl Iterator<Item = (&'a serde_yaml::Value, Origin)>,
+) -> Result<(), String> {
+    let mut errors = Vec::new();
+
+    for field in fields::unknown_fields(settings, schema, schema) {
+        // the settings of included files are merged, so the field is looked for in all of them
+        let position = parts.iter().find_map(|part| {
+            field_position(&part.text, 1, field.name()).map(|position| (part, position))
+        });
+        errors.push(match position {
+            Some((part, (line, column))) => SerdeError::new(
+                part.text.clone(),
+                (field.to_string().into(), Some(line), Some(column)),
+            )
+            .to_string(),
+            None => field.to_string(),
+        });
+    }
+
+    let segment_schema = &schema["definitions"]["SegmentConfig"];
+    for (segment, (part, index)) in segments {
+        for field in fields::unknown_fields(segment, segment_schema, schema) {
+            errors.push(unknown_segment_field(
+                parts,
+                (part, index),
+                segment_name(segment),
+                &field,
+            ));
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join("\n")),
+    }
+}
+
+/// The error of an unknown field of a segment, pointing to the line of the field
+fn unknown_segment_field(
+    parts: &[ConfigPart],
+    (part, index): Origin,
+    name: Option<&str>,
+    field: &UnknownField,
+) -> String {
+    let text = &parts[part].text;
+    let start = parts[part].format.segment_lines(text).get(index).copied();
+    let position = start.and_then(|(line, _)| field_position(text, line, field.name()));
+    match position.or(start) {
+        Some((line, column)) => positioned_segment_error(
+            parts,
+            (part, index),
+            name,
+            field.to_string(),
+            Some(line),
+            Some(column),
+        ),
+        None => segment_error(parts, (part, index), name, field.to_string()),
+    }
+}
+
+/// The line and column of the first key with the name at or after the line
+fn field_position(text: &str, from_line: usize, name: &str) -> Option<(usize, usize)> {
+    text.lines()
+        .enumerate()
+        .skip(from_line.saturating_sub(1))
+        .find_map(|(number, line)| {
+            let column = line.match_indices(name).find_map(|(column, _)| {
+                let before = line[..column].trim_end_matches(['"', '\'']);
+                let after = line[column + name.len()..].trim_start_matches(['"', '\'']);
+                let starts_key =
+                    before.trim_end().is_empty() || before.ends_with([' ', '\t', '-', '{', ',']);
+                let ends_key = after.trim_start().starts_with([':', '=']);
+                (starts_key && ends_key).then_some(column)
+            })?;
+            Some((number + 1, column))
+        })
+}
+
+/// Merges the files that the config of the last part includes into it, recursively.
+/// Their segments are appended and their other settings replace the ones of the including file.
+/// Returns the settings and the segments with their origins.
+fn merge_includes(
+    value: serde_yaml::Value,
+    parts: &mut Vec<ConfigPart>,
+    including: &mut Vec<PathBuf>,
+) -> Result<(serde_yaml::Mapping, Vec<(serde_yaml::Value, Origin)>), String> {
+    let part = parts.len() - 1;
+    let path = parts[part].path.clone();
+    let mut config = match value {
+        serde_yaml::Value::Mapping(config) => config,
+        serde_yaml::Value::Null => Default::default(),
+        _ => return Err(format!("'{}' contains no settings", path.display())),
+    };
+
+    let segments = match config.remove(&"segments".into()) {
+        Some(segments) => serde_yaml::from_value::<Vec<serde_yaml::Value>>(segments)
+            .map_err(|e| format!("the segments of '{}': {e}", path.display()))?,
+        None => Vec::new(),
+    };
+    let mut segments = segments
+        .into_iter()
+        .enumerate()
+        .map(|(index, segment)| (segment, (part, index)))
+        .collect::<Vec<_>>();
+    let includes = match config.remove(&"include".into()) {
+        Some(includes) => serde_yaml::from_value::<Vec<String>>(includes)
+            .map_err(|e| format!("the includes of '{}': {e}", path.display()))?,
+        None => Vec::new(),
+    };
+
+    including.push(path.clone());
+    for include in includes {
+        // relative paths start at the including file
+        let include = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(expand_path(include)?);
+        if including.contains(&include) {
+            return Err(format!("'{}' includes itself", include.display()));
+        }
+        let text = read_to_string(&include).map_err(|e| {
+            format!(
+                "Error reading included config file '{}': {}",
+                include.display(),
+                e
+            )
+        })?;
+        let format = ConfigFormat::of(&include);
+        let value = format
+            .deserialize(&text)
+            .map_err(|e| format!("in '{}':\n{e}", include.display()))?;
+        parts.push(ConfigPart {
+            path: include,
+            text,
+            format,
+        });
+
+        let (mut included, included_segments) = merge_includes(value, parts, including)?;
+        // the templates of all files can be used, the included ones replace those with the same name
+        if let Some(serde_yaml::Value::Mapping(templates)) = config.get_mut(&"templates".into()) {
+            if let Some(serde_yaml::Value::Mapping(included_templates)) =
+                included.remove(&"templates".into())
+            {
+                templates.extend(included_templates);
+            }
+        }
+        config.extend(included);
+        segments.extend(included_segments);
+    }
+    including.pop();
+
+    Ok((config, segments))
+}
+
+/// The format of a config file, by the extension of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("json") => Self::Json,
+            _ => Self::Yaml,
+        }
+    }
+
+    /// errors point to their place in the text
+    fn deserialize<T: DeserializeOwned>(self, text: &str) -> Result<T, String> {
+        match self {
+            Self::Yaml => serde_yaml::from_str(text)
+                .map_err(|e| fields::suggest(&SerdeError::new(text.to_string(), e).to_string())),
+            Self::Json => serde_json::from_str(text)
+                .map_err(|e| fields::suggest(&SerdeError::new(text.to_string(), e).to_string())),
+            Self::Toml => toml::from_str(text).map_err(|e| {
+                let (line, column) = match e.span() {
+                    Some(span) => {
+                        let before = &text[..span.start];
+                        let line = before.matches('\n').count() + 1;
+                        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1);
+                        (Some(line), Some(column))
+                    }
+                    None => (None, None),
+                };
+                let message = fields::suggest(e.message());
+                SerdeError::new(text.to_string(), (message.into(), line, column)).to_string()
+            }),
+        }
+    }
+
+    /// the line (starting at 1) and column of the start of every segment
+    fn segment_lines(self, config: &str) -> Vec<(usize, usize)> {
+        match self {
+            Self::Yaml => segment_lines(config),
+            // every segment is a table of the array
+            Self::Toml => config
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| line.trim_start().starts_with("[[segments]]"))
+                .map(|(number, line)| (number + 1, line.len() - line.trim_start().len()))
+                .collect(),
+            Self::Json => json_segment_lines(config),
+        }
+    }
+}
+
+/// Finds the line (starting at 1) and column where each segment of a JSON config starts,
+/// the elements of the `segments` array of the outermost object
+fn json_segment_lines(config: &str) -> Vec<(usize, usize)> {
+    let mut starts = Vec::new();
+    // the nesting of objects and arrays
+    let mut depth = 0;
+    // the depth inside of the segments array, once it started
+    let mut segments_depth = None;
+    let mut in_string = false;
+    let mut escaped = false;
+    // the last string of the outermost object, and whether it is a key
+    let mut last_string = String::new();
+    let mut string_start = 0;
+    let mut key = None::<String>;
+    // whether the next value in the segments array is a segment
+    let mut expecting = false;
+    for (number, line) in config.lines().enumerate() {
+        for (column, c) in line.char_indices() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => {
+                        in_string = false;
+                        if depth == 1 {
+                            last_string = line[string_start..column].to_string();
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if c.is_whitespace() {
+                continue;
+            }
+            if segments_depth == Some(depth) {
+                match c {
+                    ',' => expecting = true,
+                    ']' => {}
+                    _ if expecting => {
+                        starts.push((number + 1, column));
+                        expecting = false;
+                    }
+                    _ => {}
+                }
+            }
+            match c {
+                '"' => {
+                    in_string = true;
+                    string_start = column + 1;
+                }
+                ':' if depth == 1 => key = Some(std::mem::take(&mut last_string)),
+                '{' | '[' => {
+                    depth += 1;
+                    if c == '[' && depth == 2 && key.as_deref() == Some("segments") {
+                        segments_depth = Some(depth);
+                        expecting = true;
+                    }
+                }
+                '}' | ']' => {
+                    if segments_depth == Some(depth) {
+                        return starts;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+            if depth == 1 && !matches!(c, ':' | '"' | ',') {
+                key = None;
+            }
+        }
+    }
+    starts
+}
+
+/// Finds the line (starting at 1) and column where each segment starts.
+/// Only segments written as block sequence (`- ...`) are found.
+fn segment_lines(config: &str) -> Vec<(usize, usize)> {
+    let mut lines = config.lines().enumerate();
+    let is_segments_key = |line: &str| {
+        line.strip_prefix("segments:")
+            .map(|rest| rest.trim().is_empty() || rest.trim_start().starts_with('#'))
+            .unwrap_or(false)
+    };
+    if !lines.any(|(_, line)| is_segments_key(line)) {
+        return Vec::new();
+    }
+
+    let mut starts = Vec::new();
+    let mut indentation = None;
+    for (number, line) in lines {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let is_item = trimmed == "-" || trimmed.starts_with("- ");
+
+        match indentation {
+            None if is_item => indentation = Some(indent),
+            None => break,
+            Some(indentation) if indent < indentation => break,
+            Some(indentation) if indent == indentation && !is_item => break,
+            Some(indentation) if indent > indentation => continue,
+            Some(_) => {}
+        }
+        starts.push((number + 1, indent));
+    }
+    starts
 }
 
 fn expand_path<T: AsRef<str>>(path_str: T) -> Result<PathBuf, String> {
@@ -208,38 +1983,403 @@ fn expand_path<T: AsRef<str>>(path_str: T) -> Result<PathBuf, String> {
     Ok(PathBuf::from(str.as_ref()))
 }
 
+impl OutputFileConfig {
+    fn into_output(
+        self,
+        mapping: &HashMap<String, Color>,
+        color_format: ColorFormat,
+    ) -> Result<OutputConfig, String> {
+        // the hex values are looked up by the numbers the segments use
+        let hex_colors = |colors: HashMap<String, String>| {
+            colors
+                .into_iter()
+                .map(|(name, hex)| match mapping.get(&name) {
+                    Some(Color::Colored(c)) => Ok((*c, hex)),
+                    Some(_) => Err(format!("color {name} is no color number")),
+                    None => Err(format!("undefined color: {name}")),
+                })
+                .collect::<Result<_, String>>()
+        };
+
+        Ok(match self {
+            Self::Auto { colors } => OutputConfig::detect(hex_colors(colors)?, color_format),
+            Self::Xroot => OutputConfig::XRoot,
+            Self::I3bar { colors } => OutputConfig::I3bar {
+                colors: hex_colors(colors)?,
+                color_format,
+            },
+            Self::Stdout => OutputConfig::Stdout,
+            Self::File { path } => OutputConfig::File {
+                path: expand_path(path)?,
+            },
+        })
+    }
+}
+
 impl SegmentColoring {
     fn from(
         c: SegmentColorConfig,
-        mapping: &HashMap<String, u8>,
+        mapping: &HashMap<String, Color>,
     ) -> Result<SegmentColoring, String> {
         let SegmentColorConfig {
             text_color,
             left_separator_color,
             right_separator_color,
             icon_color,
+            text_background,
+            left_separator_background,
+            right_separator_background,
+            icon_background,
         } = c;
 
         let text = Self::color_lookup(text_color, mapping)?;
         let left_separator = Self::color_lookup(left_separator_color, mapping)?;
         let right_separator = Self::color_lookup(right_separator_color, mapping)?;
         let icon = Self::color_lookup(icon_color, mapping)?;
+        let text_background = Self::background_lookup(text_background, mapping)?;
+        let left_separator_background =
+            Self::background_lookup(left_separator_background, mapping)?;
+        let right_separator_background =
+            Self::background_lookup(right_separator_background, mapping)?;
+        let icon_background = Self::background_lookup(icon_background, mapping)?;
 
         Ok(Self {
             text,
             left_separator,
             right_separator,
             icon,
+            text_background,
+            left_separator_background,
+            right_separator_background,
+            icon_background,
         })
     }
 
-    fn color_lookup(c: Option<String>, mapping: &HashMap<String, u8>) -> Result<Color, String> {
+    /// backgrounds are only supported by status2d
+    fn background_lookup(
+        c: Option<String>,
+        mapping: &HashMap<String, Color>,
+    ) -> Result<Color, String> {
+        match Self::color_lookup(c, mapping)? {
+            Color::Colored(_) => Err("background colors need the status2d color format".into()),
+            color => Ok(color),
+        }
+    }
+
+    fn color_lookup(c: Option<String>, mapping: &HashMap<String, Color>) -> Result<Color, String> {
         match c {
             Some(c) => match mapping.get(&c) {
-                Some(&c) => Ok(Color::Colored(c)),
+                Some(&c) => Ok(c),
                 None => Err(format!("undefined color: {c}")),
             },
             None => Ok(Color::Uncolored),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_commands() {
+        let dir = std::env::temp_dir().join(format!("dwmblocksrs-script-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("script");
+        std::fs::write(&script, "#!/bin/sh\necho a").unwrap();
+        let path = script.to_string_lossy().into_owned();
+        let args = || vec!["a b".to_string()];
+
+        assert_eq!(
+            script_command(None, script.clone(), args()).unwrap(),
+            ("/bin/sh".into(), vec![path.clone(), "a b".into()])
+        );
+        assert_eq!(
+            script_command(Some("python3 -u"), script.clone(), args()).unwrap(),
+            (
+                "python3".into(),
+                vec!["-u".into(), path.clone(), "a b".into()]
+            )
+        );
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            script_command(None, script.clone(), args()).unwrap(),
+            (script, args())
+        );
+        assert!(script_command(Some(" "), dir.join("script"), args()).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dependencies() {
+        let segment = |name: &str, depends_on: &[&str]| {
+            let mut segment = Segment::default();
+            segment.name = (!name.is_empty()).then(|| name.into());
+            segment.depends_on = depends_on.iter().map(|s| s.to_string()).collect();
+            segment
+        };
+        let summary = segment("", &["a", "b"]);
+        assert!(check_dependencies(&[segment("a", &[]), segment("b", &["a"]), summary]).is_ok());
+        assert!(check_dependencies(&[segment("a", &["c"])]).is_err());
+        assert!(check_dependencies(&[segment("a", &["a"])]).is_err());
+        assert!(check_dependencies(&[
+            segment("a", &["b"]),
+            segment("b", &["c"]),
+            segment("c", &["a"])
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(ConfigFormat::of(Path::new("a/b.toml")), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::of(Path::new("b.json")), ConfigFormat::Json);
+        assert_eq!(ConfigFormat::of(Path::new("b.yml")), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::of(Path::new("b")), ConfigFormat::Yaml);
+
+        let toml = "left_separator = \" \"\n\n[[segments]]\nconstant = \"a\"\nupdate_interval = 5\n\n  [[segments]]\nprogram = \"date\"\n";
+        let config: ConfigFile = ConfigFormat::Toml.deserialize(toml).unwrap();
+        assert_eq!(config.segments.len(), 2);
+        assert_eq!(config.left_separator.as_deref(), Some(" "));
+        assert_eq!(ConfigFormat::Toml.segment_lines(toml), vec![(3, 0), (7, 2)]);
+
+        let json = r#"{"segments": [{"constant": "a", "signals": [1]}]}"#;
+        let config: ConfigFile = ConfigFormat::Json.deserialize(json).unwrap();
+        assert_eq!(config.segments.len(), 1);
+
+        let error = ConfigFormat::Toml
+            .deserialize::<ConfigFile>("[[segments]]\nupdate_interval = \"a\"\n")
+            .unwrap_err();
+        assert!(error.contains('2'), "{error}");
+    }
+
+    #[test]
+    fn includes() {
+        let dir = std::env::temp_dir().join(format!("dwmblocksrs-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.yaml");
+        std::fs::write(
+            &main,
+            "left_separator: a\nright_separator: b\ninclude: [laptop.toml]\nsegments:\n  - constant: one\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("laptop.toml"),
+            "right_separator = \"c\"\n\n[[segments]]\nconstant = \"two\"\n",
+        )
+        .unwrap();
+
+        let (configuration, segments) = parse_config(main.clone()).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(configuration.left_separator.as_deref(), Some("a"));
+        assert_eq!(configuration.right_separator.as_deref(), Some("c"));
+        assert_eq!(configuration.watched_files, [dir.join("laptop.toml")]);
+
+        // errors point to the included file
+        std::fs::write(
+            dir.join("laptop.toml"),
+            "[[segments]]\nconstant = \"two\"\ntext_color = \"nope\"\n",
+        )
+        .unwrap();
+        let error = parse_config(main.clone()).unwrap_err();
+        assert!(error.contains("segment 1 of"), "{error}");
+
+        std::fs::write(dir.join("laptop.toml"), "include = [\"main.yaml\"]\n").unwrap();
+        assert!(parse_config(main).unwrap_err().contains("includes itself"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[async_std::test]
+    async fn failing_programs_with_notifications() {
+        let dir =
+            std::env::temp_dir().join(format!("dwmblocksrs-notifications-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.yaml");
+        std::fs::write(
+            &config,
+            "failure_notifications:\n  threshold: 2\nsegments:\n  - program: \"false\"\n",
+        )
+        .unwrap();
+
+        let (_, mut segments) = parse_config(config).unwrap();
+        assert_eq!(segments[0].next_value().await, "ERROR");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn templates() {
+        let dir = std::env::temp_dir().join(format!("dwmblocksrs-template-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.yaml");
+        std::fs::write(
+            &config,
+            "templates:\n  pill: { left_separator: '(', right_separator: ')' }\nsegments:\n  - constant: a\n    template: pill\n    right_separator: ']'\n  - constant: b\n",
+        )
+        .unwrap();
+        let (_, mut segments) = parse_config(config.clone()).unwrap();
+        assert_eq!(segments[0].compute_value(), "(a]");
+        assert_eq!(segments[1].compute_value(), "b");
+
+        std::fs::write(
+            &config,
+            "segments:\n  - constant: a\n  - constant: b\n    template: missing\n",
+        )
+        .unwrap();
+        let error = parse_config(config).unwrap_err();
+        assert!(
+            error.contains("segment 2: there is no template named 'missing'"),
+            "{error}"
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let read = |config: &str| {
+            read_config(Path::new("config.yaml"), config.into(), ConfigFormat::Yaml)
+                .map(|_| ())
+                .unwrap_err()
+        };
+
+        let error = read("segments:\n  - script: a\n    name: s\n    update_interal: 3\n");
+        assert!(
+            error.contains(
+                "segment 1 (s): unknown field `update_interal`, did you mean `update_interval`?"
+            ),
+            "{error}"
+        );
+        assert!(error.contains(" 4 |     update_interal"), "{error}");
+
+        let error = read("segments:\n  - battery:\n      devce: BAT0\nleft_separatr: x\n");
+        assert!(
+            error.contains("unknown field `left_separatr`, did you mean `left_separator`?"),
+            "{error}"
+        );
+        assert!(
+            error.contains("segment 1: unknown field `battery.devce`, did you mean `device`?"),
+            "{error}"
+        );
+
+        // the fields of other kinds of segments don't belong to the segment
+        let error = read("segments:\n  - program: a\n    shell: bash\n");
+        assert!(error.contains("unknown field `shell`"), "{error}");
+
+        let error = read("segments:\n  - constant: a\n    name: c\n    update_interval: x\n");
+        assert!(
+            error.contains("segment 1 (c): update_interval: invalid duration 'x'"),
+            "{error}"
+        );
+
+        let error = read("color_format: status2\nsegments: []\n");
+        assert!(error.contains("did you mean `status2d`?"), "{error}");
+    }
+
+    #[test]
+    fn signal_names() {
+        let read = |config: &str| {
+            read_config(Path::new("config.yaml"), config.into(), ConfigFormat::Yaml)
+                .map(|(config, _, _)| config)
+        };
+        let config = read(
+            "signal_names: { volume: 1, brightness: 2 }\nupdate_all_signal: brightness\nsegments:\n  - constant: a\n    signals: [volume, 3]\n    menu: { signal: brightness, items: {} }\n",
+        )
+        .unwrap();
+        assert_eq!(config.update_all_signal, Some(UpdateAllSignal::Offset(2)));
+        assert_eq!(config.segments[0].signals, vec![1, 3]);
+        assert_eq!(config.segments[0].menu.as_ref().unwrap().signal, 2);
+
+        let error = read(
+            "signal_names: { volume: 1 }\nsegments:\n  - constant: a\n    toggle_signal: volum\n",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("segment 1: there is no signal named 'volum', did you mean 'volume'?"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(Condition::default().holds(), Ok(true));
+        let hostname = |hostname: &str| Condition {
+            hostname: Some(hostname.into()),
+            ..Default::default()
+        };
+        assert_eq!(hostname(&local_hostname().unwrap()).holds(), Ok(true));
+        assert_eq!(hostname("not-this-host").holds(), Ok(false));
+
+        std::env::set_var("DWMBLOCKSRS_CONDITION", "a");
+        let env = |env: &str| Condition {
+            env: Some(env.into()),
+            ..Default::default()
+        };
+        assert_eq!(env("DWMBLOCKSRS_CONDITION").holds(), Ok(true));
+        assert_eq!(env("DWMBLOCKSRS_CONDITION=a").holds(), Ok(true));
+        assert_eq!(env("DWMBLOCKSRS_CONDITION=b").holds(), Ok(false));
+        assert_eq!(env("DWMBLOCKSRS_CONDITION_UNSET").holds(), Ok(false));
+
+        let path = |path: &str| Condition {
+            path_exists: Some(path.into()),
+            ..Default::default()
+        };
+        assert_eq!(path("/").holds(), Ok(true));
+        assert_eq!(path("/does/not/exist").holds(), Ok(false));
+        assert!(path("$DWMBLOCKSRS_CONDITION_UNSET").holds().is_err());
+    }
+
+    #[test]
+    fn lines_of_segments() {
+        let config = "left_separator: \" \"\nsegments: # comment\n  - constant: a\n\n    # comment\n    icon: b\n  - constant: c\ncolors: {}\n";
+        assert_eq!(segment_lines(config), vec![(3, 2), (7, 2)]);
+
+        let config = "segments:\n- constant: a\n- constant: b\nscript_dir: c\n";
+        assert_eq!(segment_lines(config), vec![(2, 0), (3, 0)]);
+
+        assert_eq!(segment_lines("segments: []"), vec![]);
+
+        let json = "{\n  \"colors\": {\"segments\": [1]},\n  \"icon\": \"[\\\"\",\n  \"segments\": [\n    {\"constant\": \"a\"},\n    {\"constant\": \"]\", \"signals\": [1, 2]}, {\"constant\": \"c\"}\n  ]\n}\n";
+        assert_eq!(
+            ConfigFormat::Json.segment_lines(json),
+            vec![(5, 4), (6, 4), (6, 42)]
+        );
+        assert_eq!(
+            ConfigFormat::Json.segment_lines("{\"segments\": []}"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn json_segment_errors() {
+        let dir = std::env::temp_dir().join(format!("dwmblocksrs-json-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.json");
+        std::fs::write(
+            &config,
+            "{\n  \"segments\": [\n    {\"constant\": \"a\"},\n    {\"constant\": \"b\", \"text_color\": \"nope\"}\n  ]\n}\n",
+        )
+        .unwrap();
+
+        let error = parse_config(config).unwrap_err();
+        assert!(
+            error.contains("segment 2: undefined color: nope"),
+            "{error}"
+        );
+        assert!(error.contains(" 4 |     {\"constant\""), "{error}");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn json_schema() {
+        let schema: serde_json::Value = serde_json::from_str(&schema()).unwrap();
+        let properties = &schema["properties"];
+        assert!(properties.get("segments").is_some());
+        assert!(properties.get("include").is_some());
+        assert!(schema["definitions"]["SegmentConfig"].is_object());
+        assert_eq!(schema["required"], serde_json::json!(["segments"]));
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_json::Value as Schema;
+use serde_yaml::Value;
+
+lazy_static! {
+    /// the errors of serde for unknown fields and variants, with the expected names
+    static ref UNKNOWN: Regex =
+        Regex::new(r"unknown (field|variant) `([^`]*)`, expected (?:one of )?((?:`[^`]*`(?:, | or )?)+)")
+            .unwrap();
+    static ref NAME: Regex = Regex::new(r"`([^`]*)`").unwrap();
+}
+
+/// A field of the config that its schema doesn't have, probably a typo
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct UnknownField {
+    /// the keys leading to the field, e.g. `battery.devce`
+    pub(super) path: String,
+    /// the field that was probably meant
+    pub(super) suggestion: Option<String>,
+}
+
+impl UnknownField {
+    /// the name of the field, without the keys leading to it
+    pub(super) fn name(&self) -> &str {
+        self.path.rsplit(['.', ']']).next().unwrap_or(&self.path)
+    }
+}
+
+impl std::fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown field `{}`", self.path)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean `{suggestion}`?")?;
+        }
+        Ok(())
+    }
+}
+
+/// The fields of the value that the schema doesn't have, nested ones included.
+/// Unlike `deny_unknown_fields` of serde, this works with flattened fields.
+pub(super) fn unknown_fields(value: &Value, schema: &Schema, root: &Schema) -> Vec<UnknownField> {
+    let mut unknown = Vec::new();
+    check(value, schema, root, String::new(), &mut unknown);
+    unknown
+}
+
+fn check(
+    value: &Value,
+    schema: &Schema,
+    root: &Schema,
+    path: String,
+    unknown: &mut Vec<UnknownField>,
+) {
+    match value {
+        Value::Mapping(mapping) => {
+            let keys = mapping
+                .iter()
+                .filter_map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>();
+            let Some(fields) = fields(schema, root, &keys) else {
+                return;
+            };
+            for (key, value) in mapping {
+                let Some(key) = key.as_str() else {
+                    continue;
+                };
+                let path = match path.is_empty() {
+                    true => key.to_string(),
+                    false => format!("{path}.{key}"),
+                };
+                match fields.get(key) {
+                    Some(schema) => check(value, schema, root, path, unknown),
+                    None => unknown.push(UnknownField {
+                        path,
+                        suggestion: did_you_mean(key, fields.keys().map(String::as_str))
+                            .map(String::from),
+                    }),
+                }
+            }
+        }
+        Value::Sequence(items) => {
+            if let Some(schema) = items_schema(schema, root) {
+                for (index, item) in items.iter().enumerate() {
+                    check(item, schema, root, format!("{path}[{index}]"), unknown);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The schema that a `$ref` points to
+pub(super) fn resolve<'a>(schema: &'a Schema, root: &'a Schema) -> &'a Schema {
+    match schema
+        .get("$ref")
+        .and_then(Schema::as_str)
+        .and_then(|reference| reference.strip_prefix("#/"))
+    {
+        Some(pointer) => root
+            .pointer(&format!("/{pointer}"))
+            .map_or(schema, |schema| resolve(schema, root)),
+        None => schema,
+    }
+}
+
+/// The schemas of the fields that an object with the keys can have, `None` if it can have any
+pub(super) fn fields<'a>(
+    schema: &'a Schema,
+    root: &'a Schema,
+    keys: &[&str],
+) -> Option<BTreeMap<String, &'a Schema>> {
+    let schema = resolve(schema, root);
+    let object = schema.as_object()?;
+    if !matches!(
+        object.get("additionalProperties"),
+        None | Some(Schema::Bool(false))
+    ) {
+        return None;
+    }
+
+    let mut fields = BTreeMap::new();
+    let mut constrained = false;
+    if let Some(properties) = object.get("properties").and_then(Schema::as_object) {
+        fields.extend(
+            properties
+                .iter()
+                .map(|(name, schema)| (name.clone(), schema)),
+        );
+        constrained = true;
+    }
+    for subschema in object
+        .get("allOf")
+        .and_then(Schema::as_array)
+        .into_iter()
+        .flatten()
+    {
+        fields.extend(self::fields(subschema, root, keys)?);
+        constrained = true;
+    }
+
+    // of the alternatives that are objects, the ones whose required fields are there
+    let alternatives = ["anyOf", "oneOf"]
+        .into_iter()
+        .filter_map(|key| object.get(key).and_then(Schema::as_array))
+        .flatten()
+        .map(|alternative| resolve(alternative, root))
+        .filter(|alternative| is_object(alternative, root))
+        .collect::<Vec<_>>();
+    let matching = alternatives
+        .iter()
+        .filter(|alternative| {
+            alternative
+                .get("required")
+                .and_then(Schema::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Schema::as_str)
+                .all(|required| keys.contains(&required))
+        })
+        .collect::<Vec<_>>();
+    // without a matching alternative, all fields are suggested
+    let alternatives = match matching.is_empty() {
+        true => alternatives.iter().collect(),
+        false => matching,
+    };
+    for alternative in alternatives {
+        fields.extend(self::fields(alternative, root, keys)?);
+        constrained = true;
+    }
+
+    constrained.then_some(fields)
+}
+
+/// whether the schema describes an object, or one of its alternatives does
+fn is_object(schema: &Schema, root: &Schema) -> bool {
+    let schema = resolve(schema, root);
+    let is_object_type = match schema.get("type") {
+        Some(Schema::String(kind)) => kind == "object",
+        Some(Schema::Array(kinds)) => kinds.iter().any(|kind| kind == "object"),
+        _ => schema.get("properties").is_some(),
+    };
+    is_object_type
+        || ["allOf", "anyOf", "oneOf"]
+            .into_iter()
+            .filter_map(|key| schema.get(key).and_then(Schema::as_array))
+            .flatten()
+            .any(|alternative| is_object(alternative, root))
+}
+
+/// The schema of the items of an array, or of an alternative that is an array
+pub(super) fn items_schema<'a>(schema: &'a Schema, root: &'a Schema) -> Option<&'a Schema> {
+    let schema = resolve(schema, root);
+    schema.get("items").or_else(|| {
+        ["anyOf", "oneOf"]
+            .into_iter()
+            .filter_map(|key| schema.get(key).and_then(Schema::as_array))
+            .flatten()
+            .find_map(|alternative| items_schema(alternative, root))
+    })
+}
+
+/// The most similar of the names, if it is similar enough
+pub(super) fn did_you_mean<'a>(
+    name: &str,
+    names: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    // a few typos, but not so many that short names are replaced entirely
+    let max_distance = (name.chars().count() / 3).max(1);
+    names
+        .map(|candidate| (strsim::levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Adds a suggestion to the errors of serde for unknown fields and variants
+pub(super) fn suggest(error: &str) -> String {
+    UNKNOWN
+        .replace_all(error, |captures: &Captures| {
+            let names = NAME
+                .captures_iter(&captures[3])
+                .map(|name| name.get(1).unwrap().as_str());
+            match did_you_mean(&captures[2], names) {
+                Some(suggestion) => format!("{}, did you mean `{suggestion}`?", &captures[0]),
+                None => captures[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unknown(config: &str, schema: Schema) -> Vec<String> {
+        let value = serde_yaml::from_str(config).unwrap();
+        unknown_fields(&value, &schema, &schema)
+            .into_iter()
+            .map(|field| field.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn fields() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "update_interval": { "type": "integer" },
+                "battery": { "$ref": "#/definitions/Battery" },
+                "env": { "type": "object", "additionalProperties": { "type": "string" } },
+                "views": { "type": "array", "items": { "$ref": "#/definitions/Battery" } },
+            },
+            "anyOf": [
+                { "type": "object", "required": ["script"], "properties": { "script": {}, "shell": {} } },
+                { "type": "object", "required": ["program"], "properties": { "program": {} } },
+            ],
+            "definitions": {
+                "Battery": { "type": "object", "properties": { "device": {} } },
+            },
+        });
+
+        assert!(unknown("script: a\nshell: b\nenv: { A: b }", schema.clone()).is_empty());
+        assert_eq!(
+            unknown("script: a\nupdate_interal: 1", schema.clone()),
+            vec!["unknown field `update_interal`, did you mean `update_interval`?"]
+        );
+        // the fields of other kinds of segments are unknown
+        assert_eq!(
+            unknown("program: a\nshell: b", schema.clone()),
+            vec!["unknown field `shell`"]
+        );
+        assert_eq!(
+            unknown("scirpt: a", schema.clone()),
+            vec!["unknown field `scirpt`, did you mean `script`?"]
+        );
+        assert_eq!(
+            unknown("script: a\nbattery: { devce: BAT0 }", schema.clone()),
+            vec!["unknown field `battery.devce`, did you mean `device`?"]
+        );
+        assert_eq!(
+            unknown("script: a\nviews: [{ device: a }, { xyz: b }]", schema),
+            vec!["unknown field `views[1].xyz`"]
+        );
+    }
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(
+            suggest("color_format: unknown variant `status2`, expected `statuscolors` or `status2d` at line 1"),
+            "color_format: unknown variant `status2`, expected `statuscolors` or `status2d`, did you mean `status2d`? at line 1"
+        );
+        assert_eq!(
+            suggest("unknown field `xyz`, expected one of `device`, `format`"),
+            "unknown field `xyz`, expected one of `device`, `format`"
+        );
+        assert_eq!(
+            did_you_mean(
+                "left_separatr",
+                ["right_separator", "left_separator"].into_iter()
+            ),
+            Some("left_separator")
+        );
+    }
+}
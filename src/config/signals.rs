@@ -0,0 +1,329 @@
+use std::{collections::BTreeMap, fmt};
+
+use log::warn;
+use serde_json::Value as Schema;
+use serde_yaml::Value;
+
+use super::{fields, MenuConfig, SegmentConfig, SegmentKindConfig};
+use crate::segments;
+
+/// What a signal does for a segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SignalUse {
+    Update,
+    /// the statuscmd patch of dwm identifies the segment by its first signal
+    Click,
+    Menu,
+    Toggle,
+    /// handled by the kind of the segment, e.g. to start a timer
+    Control,
+}
+
+impl fmt::Display for SignalUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Update => "updating",
+            Self::Click => "clicks on",
+            Self::Menu => "the menu of",
+            Self::Toggle => "toggling",
+            Self::Control => "controlling",
+        })
+    }
+}
+
+/// The signals of a segment and what they do, to find conflicts between segments
+#[derive(Debug)]
+pub(super) struct SegmentSignals {
+    /// the segment in messages, e.g. `segment 2 (volume)`
+    label: String,
+    uses: Vec<(u32, SignalUse)>,
+    /// whether updates run a command
+    expensive: bool,
+}
+
+impl SegmentSignals {
+    pub(super) fn of(label: String, config: &SegmentConfig) -> Self {
+        let mut uses = config
+            .signals
+            .iter()
+            .map(|signal| (*signal, SignalUse::Update))
+            .collect::<Vec<_>>();
+        if let (Some(_), Some(signal)) = (&config.click_command, config.signals.first()) {
+            uses.push((*signal, SignalUse::Click));
+        }
+        if let Some(MenuConfig { signal, .. }) = config.menu {
+            uses.push((signal, SignalUse::Menu));
+        }
+        if let Some(signal) = config.toggle_signal {
+            uses.push((signal, SignalUse::Toggle));
+        }
+        let expensive = matches!(
+            config.kind,
+            SegmentKindConfig::Program { .. }
+                | SegmentKindConfig::ShellScript { .. }
+                | SegmentKindConfig::Shell { .. }
+                | SegmentKindConfig::Updates { .. }
+        );
+        Self {
+            label,
+            uses,
+            expensive,
+        }
+    }
+
+    /// adds the signals that the kind of the segment reacts to
+    pub(super) fn add_control_signals(&mut self, signals: Vec<u32>) {
+        self.uses.extend(
+            signals
+                .into_iter()
+                .map(|signal| (signal, SignalUse::Control)),
+        );
+    }
+}
+
+/// Finds signals that do several things, like toggling a segment and updating another one.
+/// Expensive segments that are updated by the same signal are only warned about,
+/// because that can be intended.
+pub(super) fn check_signals(
+    segments: &[SegmentSignals],
+    update_all_signal: Option<u32>,
+) -> Result<(), String> {
+    // the uses of every signal, by the index of the segment, `None` for `update_all_signal`
+    let mut signals = BTreeMap::<u32, Vec<(Option<usize>, SignalUse)>>::new();
+    for (index, segment) in segments.iter().enumerate() {
+        for (signal, signal_use) in &segment.uses {
+            signals
+                .entry(*signal)
+                .or_default()
+                .push((Some(index), *signal_use));
+        }
+    }
+    if let Some(signal) = update_all_signal {
+        signals
+            .entry(signal)
+            .or_default()
+            .push((None, SignalUse::Update));
+    }
+
+    let describe = |(segment, signal_use): &(Option<usize>, SignalUse)| match segment {
+        Some(index) => format!("{signal_use} {}", segments[*index].label),
+        None => "update_all_signal".into(),
+    };
+
+    let mut errors = Vec::new();
+    for (signal, uses) in &signals {
+        let conflicts = |a: &(Option<usize>, SignalUse), b: &(Option<usize>, SignalUse)| {
+            let both_update = a.1 == SignalUse::Update && b.1 == SignalUse::Update;
+            // clicks on a segment always update it too
+            let click_and_update = matches!(
+                (a.1, b.1),
+                (SignalUse::Click, SignalUse::Update) | (SignalUse::Update, SignalUse::Click)
+            );
+            a != b && !both_update && !(a.0 == b.0 && click_and_update)
+        };
+        if uses.iter().any(|a| uses.iter().any(|b| conflicts(a, b))) {
+            errors.push(format!(
+                "signal {signal} is used for {}",
+                uses.iter().map(describe).collect::<Vec<_>>().join(", ")
+            ));
+            continue;
+        }
+
+        if update_all_signal == Some(*signal) {
+            for index in uses.iter().filter_map(|(segment, _)| *segment) {
+                warn!(
+                    "signal {signal} is the update_all_signal, {} doesn't need it",
+                    segments[index].label
+                );
+            }
+            continue;
+        }
+        let expensive = uses
+            .iter()
+            .filter_map(|(segment, _)| *segment)
+            .filter(|index| segments[*index].expensive)
+            .map(|index| segments[index].label.as_str())
+            .collect::<Vec<_>>();
+        if expensive.len() > 1 {
+            warn!(
+                "signal {signal} updates {} together, which all run commands",
+                expensive.join(", ")
+            );
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join("\n")),
+    }
+}
+
+/// The largest signal offset that no segment and no name uses
+pub(super) fn free_signal(
+    segments: &[SegmentSignals],
+    names: &BTreeMap<String, u32>,
+) -> Option<u32> {
+    (0..=segments::max_signal_offset()).rev().find(|signal| {
+        !names.values().any(|name| name == signal)
+            && !segments
+                .iter()
+                .any(|segment| segment.uses.iter().any(|(used, _)| used == signal))
+    })
+}
+
+/// Replaces the names of signals in the value by their offsets of `signal_names`.
+/// The fields with signals are found by their schema.
+pub(super) fn resolve_names(
+    value: &mut Value,
+    schema: &Schema,
+    root: &Schema,
+    names: &BTreeMap<String, u32>,
+) -> Result<(), String> {
+    let mut errors = Vec::new();
+    resolve(value, schema, root, names, &mut errors);
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join("\n")),
+    }
+}
+
+fn resolve(
+    value: &mut Value,
+    schema: &Schema,
+    root: &Schema,
+    names: &BTreeMap<String, u32>,
+    errors: &mut Vec<String>,
+) {
+    match value {
+        Value::String(name) if is_signal(schema, root) => match names.get(name.as_str()) {
+            Some(offset) => *value = Value::Number((*offset).into()),
+            None => {
+                let suggestion = fields::did_you_mean(name, names.keys().map(String::as_str))
+                    .map(|suggestion| format!(", did you mean '{suggestion}'?"))
+                    .unwrap_or_default();
+                errors.push(format!("there is no signal named '{name}'{suggestion}"));
+            }
+        },
+        Value::Mapping(mapping) => {
+            let keys = mapping
+                .iter()
+                .filter_map(|(key, _)| key.as_str().map(String::from))
+                .collect::<Vec<_>>();
+            let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+            let Some(fields) = fields::fields(schema, root, &keys) else {
+                return;
+            };
+            for (key, value) in mapping.iter_mut() {
+                if let Some(schema) = key.as_str().and_then(|key| fields.get(key)) {
+                    resolve(value, schema, root, names, errors);
+                }
+            }
+        }
+        Value::Sequence(items) => {
+            if let Some(schema) = fields::items_schema(schema, root) {
+                for item in items {
+                    resolve(item, schema, root, names, errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// whether the schema is the one of signals, or one of its alternatives is
+fn is_signal(schema: &Schema, root: &Schema) -> bool {
+    if let Some(reference) = schema.get("$ref").and_then(Schema::as_str) {
+        let resolved = fields::resolve(schema, root);
+        return reference == "#/definitions/SignalValue"
+            || (!std::ptr::eq(resolved, schema) && is_signal(resolved, root));
+    }
+    ["allOf", "anyOf", "oneOf"]
+        .into_iter()
+        .filter_map(|key| schema.get(key).and_then(Schema::as_array))
+        .flatten()
+        .any(|alternative| is_signal(alternative, root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(label: &str, uses: &[(u32, SignalUse)]) -> SegmentSignals {
+        SegmentSignals {
+            label: label.into(),
+            uses: uses.to_vec(),
+            expensive: true,
+        }
+    }
+
+    #[test]
+    fn conflicts() {
+        use SignalUse::*;
+
+        // updating several segments with a signal is allowed
+        let segments = [
+            segment("segment 1", &[(1, Update), (1, Click)]),
+            segment("segment 2", &[(2, Update)]),
+            segment("segment 3", &[(2, Update), (3, Toggle)]),
+        ];
+        assert_eq!(check_signals(&segments, Some(4)), Ok(()));
+        assert_eq!(check_signals(&segments, Some(2)), Ok(()));
+
+        assert_eq!(
+            check_signals(&segments, Some(3)),
+            Err("signal 3 is used for toggling segment 3, update_all_signal".into())
+        );
+        let segments = [
+            segment("segment 1", &[(1, Update), (1, Click)]),
+            segment("segment 2 (timer)", &[(1, Control), (2, Menu), (2, Toggle)]),
+        ];
+        assert_eq!(
+            check_signals(&segments, None),
+            Err("signal 1 is used for updating segment 1, clicks on segment 1, controlling segment 2 (timer)\n\
+                 signal 2 is used for the menu of segment 2 (timer), toggling segment 2 (timer)"
+                .into())
+        );
+    }
+
+    #[test]
+    fn names() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "signals": { "type": "array", "items": { "$ref": "#/definitions/SignalValue" } },
+                "toggle_signal": {
+                    "anyOf": [{ "$ref": "#/definitions/SignalValue" }, { "type": "null" }]
+                },
+                "icon": { "type": "string" },
+            },
+            "definitions": {
+                "SignalValue": { "anyOf": [{ "type": "integer" }, { "type": "string" }] },
+            },
+        });
+        let names = BTreeMap::from([("volume".to_string(), 1), ("brightness".to_string(), 2)]);
+        let resolve = |config: &str| {
+            let mut value = serde_yaml::from_str(config).unwrap();
+            resolve_names(&mut value, &schema, &schema, &names).map(|_| value)
+        };
+
+        assert_eq!(
+            resolve("signals: [volume, 3, brightness]\ntoggle_signal: volume\nicon: volume"),
+            Ok(serde_yaml::from_str("signals: [1, 3, 2]\ntoggle_signal: 1\nicon: volume").unwrap())
+        );
+        assert_eq!(
+            resolve("signals: [volum]"),
+            Err("there is no signal named 'volum', did you mean 'volume'?".into())
+        );
+    }
+
+    #[test]
+    fn free_signals() {
+        use SignalUse::*;
+
+        let max = segments::max_signal_offset();
+        let segments = [segment("segment 1", &[(max, Update), (2, Toggle)])];
+        let names = BTreeMap::from([("volume".to_string(), max - 1)]);
+        assert_eq!(free_signal(&segments, &names), Some(max - 2));
+        assert_eq!(free_signal(&[], &BTreeMap::new()), Some(max));
+    }
+}
@@ -0,0 +1,35 @@
+//! Values that segments share with each other.
+//! The value of every named segment is stored under its name,
+//! custom segment kinds can also store and read their own values.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref CONTEXT: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+/// the value stored under the key
+pub fn get(key: &str) -> Option<String> {
+    CONTEXT.lock().unwrap().get(key).cloned()
+}
+
+/// stores the value under the key, replacing the previous one
+pub fn set(key: impl Into<String>, value: impl Into<String>) {
+    CONTEXT.lock().unwrap().insert(key.into(), value.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        assert_eq!(get("context-test"), None);
+        set("context-test", "a");
+        set("context-test", "b");
+        assert_eq!(get("context-test").as_deref(), Some("b"));
+    }
+}
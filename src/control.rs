@@ -0,0 +1,238 @@
+use std::{
+    ffi::CString,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+    thread,
+};
+
+use async_std::channel::Sender;
+use log::warn;
+
+use crate::segments::SegmentCommand;
+
+/// A command for the running status bar from outside
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// passes the command to the segment with the name
+    Segment(String, SegmentCommand),
+    /// hides or shows the segment with the name
+    #[cfg_attr(not(feature = "ipc"), allow(dead_code))]
+    Toggle(String),
+    /// returns the text of the status bar
+    #[cfg_attr(not(feature = "ipc"), allow(dead_code))]
+    Status,
+}
+
+/// A command together with the channel for the answer, if someone waits for it
+pub(crate) struct Request {
+    pub command: Command,
+    pub reply: Option<Sender<Result<String, String>>>,
+}
+
+/// Parses lines like `<segment name> [<button>]`.
+/// With a button, the segment is clicked, otherwise it is updated.
+fn parse_line(line: &str) -> Option<Command> {
+    let mut words = line.split_whitespace();
+    let name = words.next()?.to_string();
+    let command = match words.next() {
+        Some(button) => SegmentCommand::Click(button.parse().ok()?),
+        None => SegmentCommand::Update,
+    };
+    Some(Command::Segment(name, command))
+}
+
+/// Creates the FIFO and sends the commands that are written to it as requests
+pub(crate) fn listen_fifo(path: PathBuf, requests: Sender<Request>) -> Result<(), String> {
+    create_fifo(&path).map_err(|e| format!("error creating fifo '{}': {}", path.display(), e))?;
+
+    thread::spawn(move || loop {
+        let read = read_fifo(&path, &requests);
+        // stopped, e.g. because the configuration was reloaded
+        if requests.is_closed() {
+            return;
+        }
+        if let Err(e) = read {
+            warn!("error reading fifo '{}': {}", path.display(), e);
+            return;
+        }
+    });
+    Ok(())
+}
+
+/// Stops listening on the FIFO after the receiver of the requests was dropped, and removes it
+pub(crate) fn stop_fifo(path: &Path) {
+    // the reading thread waits for a writer and stops when this one closes the fifo
+    let _ = OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path);
+    if fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo()) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn create_fifo(path: &Path) -> io::Result<()> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.file_type().is_fifo() => return Ok(()),
+        Ok(_) => return Err(io::Error::other("the file exists, but is no fifo")),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// reads the commands until all writers closed the fifo
+fn read_fifo(path: &Path, requests: &Sender<Request>) -> io::Result<()> {
+    // blocks until the fifo is opened for writing
+    let fifo = File::open(path)?;
+    for line in BufReader::new(fifo).lines() {
+        let line = line?;
+        match parse_line(&line) {
+            // the channel is unbounded, so it can't be full
+            Some(command) => {
+                let _ = requests.try_send(Request {
+                    command,
+                    reply: None,
+                });
+            }
+            None if line.trim().is_empty() => {}
+            None => warn!("invalid command in fifo: '{line}'"),
+        }
+    }
+    Ok(())
+}
+
+/// Writes a line to the FIFO of a running instance
+pub(crate) fn send(path: &Path, line: &str) -> Result<(), String> {
+    // opening a fifo without a reader fails instead of blocking with O_NONBLOCK
+    let mut fifo = OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .map_err(|e| match e.raw_os_error() {
+            Some(libc::ENXIO) => "dwmblocksrs is not running".into(),
+            _ => format!("error opening fifo '{}': {}", path.display(), e),
+        })?;
+    writeln!(fifo, "{line}")
+        .map_err(|e| format!("error writing to fifo '{}': {}", path.display(), e))
+}
+
+/// Sends the signal to the processes with the name, like `pkill` does
+pub(crate) fn signal_processes(name: &str, signal: i32) -> Result<(), String> {
+    let own = std::process::id();
+    let signalled = fs::read_dir("/proc")
+        .map_err(|e| format!("error listing the processes: {e}"))?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != own)
+        .filter(|pid| {
+            fs::read_to_string(format!("/proc/{pid}/comm"))
+                .is_ok_and(|comm| comm.trim_end() == name)
+        })
+        // processes of other users can't be signalled
+        .filter(|pid| unsafe { libc::kill(*pid as i32, signal) } == 0)
+        .count();
+    match signalled {
+        0 => Err(format!("{name} is not running")),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, time::Duration};
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_line("volume 3"),
+            Some(Command::Segment("volume".into(), SegmentCommand::Click(3)))
+        );
+        assert_eq!(
+            parse_line("mail"),
+            Some(Command::Segment("mail".into(), SegmentCommand::Update))
+        );
+        assert_eq!(parse_line("volume left"), None);
+        assert_eq!(parse_line(""), None);
+    }
+
+    #[test]
+    fn not_running() {
+        let path = env::temp_dir().join(format!("dwmblocksrs-no-reader-{}", std::process::id()));
+        create_fifo(&path).unwrap();
+        assert_eq!(
+            send(&path, "volume"),
+            Err("dwmblocksrs is not running".into())
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn signals() {
+        // a process with a name of its own, taken from the link
+        let name = format!("dwmbt{}", std::process::id());
+        let link = env::temp_dir().join(&name);
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink("/bin/sleep", &link).unwrap();
+        let mut child = std::process::Command::new(&link).arg("10").spawn().unwrap();
+        // the name is only set once the process runs the program
+        for _ in 0..100 {
+            if fs::read_to_string(format!("/proc/{}/comm", child.id()))
+                .is_ok_and(|comm| comm.trim_end() == name)
+            {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(signal_processes(&name, libc::SIGTERM), Ok(()));
+        let status = child.wait().unwrap();
+        assert_eq!(
+            std::os::unix::process::ExitStatusExt::signal(&status),
+            Some(libc::SIGTERM)
+        );
+        assert_eq!(
+            signal_processes(&name, libc::SIGTERM),
+            Err(format!("{name} is not running"))
+        );
+        fs::remove_file(link).unwrap();
+    }
+
+    #[async_std::test]
+    async fn fifo() {
+        let path = env::temp_dir().join(format!("dwmblocksrs-fifo-{}", std::process::id()));
+        let (tx, requests) = async_std::channel::unbounded();
+        listen_fifo(path.clone(), tx).unwrap();
+
+        // the reading thread might not have opened the fifo yet
+        let mut sent = send(&path, "volume 1");
+        for _ in 0..100 {
+            if sent.is_ok() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+            sent = send(&path, "volume 1");
+        }
+        sent.unwrap();
+
+        let request = async_std::future::timeout(Duration::from_secs(5), requests.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            request.command,
+            Command::Segment("volume".into(), SegmentCommand::Click(1))
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
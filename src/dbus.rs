@@ -0,0 +1,56 @@
+use std::{thread, time::Duration};
+
+use async_std::channel::{self, Receiver, Sender, TrySendError};
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use zbus::{
+    blocking::{Connection, MessageIterator},
+    MatchRule,
+};
+
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Bus {
+    #[default]
+    Session,
+    System,
+}
+
+impl Bus {
+    pub fn connect(self) -> zbus::Result<Connection> {
+        match self {
+            Self::Session => Connection::session(),
+            Self::System => Connection::system(),
+        }
+    }
+}
+
+/// Sends a request through the returned channel whenever a message that matches the rule arrives.
+/// If the connection to the bus is lost, it is reestablished.
+pub(crate) fn watch_signals(bus: Bus, rule: MatchRule<'static>) -> Receiver<()> {
+    let (tx, rx) = channel::bounded(1);
+    thread::spawn(move || loop {
+        if let Err(e) = watch(bus, &rule, &tx) {
+            warn!("error watching d-bus signals '{rule}': {e}");
+        }
+        if tx.is_closed() {
+            return;
+        }
+        thread::sleep(Duration::from_secs(5));
+    });
+    rx
+}
+
+fn watch(bus: Bus, rule: &MatchRule<'static>, requests: &Sender<()>) -> zbus::Result<()> {
+    let connection = bus.connect()?;
+    let messages = MessageIterator::for_match_rule(rule.clone(), &connection, Some(16))?;
+    for message in messages {
+        message?;
+        if let Err(TrySendError::Closed(_)) = requests.try_send(()) {
+            // the segment does not exist anymore
+            break;
+        }
+    }
+    Ok(())
+}
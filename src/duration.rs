@@ -0,0 +1,137 @@
+use std::{fmt, time::Duration};
+
+use schemars::JsonSchema;
+use serde::{de::Visitor, Deserializer, Serializer};
+
+/// How durations are written in the config: a number or a text like `1h30m`
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+pub(crate) enum DurationValue {
+    Number(f64),
+    /// e.g. `90s`, `5m` or `1h30m`
+    Text(String),
+}
+
+/// Numbers are a count of the unit, texts are parsed by humantime, e.g. `1h30m` or `500ms`
+struct DurationVisitor {
+    unit: Duration,
+}
+
+impl<'de> Visitor<'de> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number or a duration like `1h30m`")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        self.visit_f64(value as f64)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        self.visit_f64(value as f64)
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        Duration::try_from_secs_f64(self.unit.as_secs_f64() * value)
+            .map_err(|_| E::custom(format!("invalid duration {value}")))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        humantime::parse_duration(value)
+            .map_err(|e| E::custom(format!("invalid duration '{value}': {e}")))
+    }
+}
+
+/// Like `DurationVisitor`, with null being `None` as for an `Option<Duration>`
+struct OptionalDurationVisitor(DurationVisitor);
+
+impl<'de> Visitor<'de> for OptionalDurationVisitor {
+    type Value = Option<Duration>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(formatter)?;
+        formatter.write_str(" or null")
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self.0).map(Some)
+    }
+}
+
+/// Deserializes a number of seconds or a duration like `1h30m`
+pub(crate) fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    deserializer.deserialize_any(DurationVisitor {
+        unit: Duration::from_secs(1),
+    })
+}
+
+/// Deserializes a number of milliseconds or a duration like `1s`
+pub(crate) fn milliseconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    deserializer.deserialize_any(DurationVisitor {
+        unit: Duration::from_millis(1),
+    })
+}
+
+/// Like `seconds`, for optional durations, which also need `#[serde(default)]`
+pub(crate) fn optional_seconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    deserializer.deserialize_option(OptionalDurationVisitor(DurationVisitor {
+        unit: Duration::from_secs(1),
+    }))
+}
+
+/// Serializes the duration as text, for the defaults in the schema
+pub(crate) fn text<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&humantime::format_duration(*duration).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> Result<Duration, String> {
+        seconds(serde_yaml::Deserializer::from_str(value)).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse("5"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse("0.5"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse("1h 30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse("250ms"), Ok(Duration::from_millis(250)));
+        assert!(parse("-1").is_err());
+        assert!(parse("5 parsecs").is_err());
+        assert!(parse("[]").is_err());
+
+        let milliseconds =
+            milliseconds(serde_yaml::Deserializer::from_str("20")).map_err(|e| e.to_string());
+        assert_eq!(milliseconds, Ok(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn optional_durations() {
+        let parse = |value| {
+            optional_seconds(serde_yaml::Deserializer::from_str(value)).map_err(|e| e.to_string())
+        };
+        assert_eq!(parse("~"), Ok(None));
+        assert_eq!(parse("null"), Ok(None));
+        assert_eq!(parse("5m"), Ok(Some(Duration::from_secs(300))));
+        assert_eq!(parse("10"), Ok(Some(Duration::from_secs(10))));
+        assert!(parse("5 parsecs").is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use std::{io, process::Command, thread};
+
+use log::warn;
+
+/// Runs the commands one after another in the background
+pub(crate) fn spawn_hooks(commands: Vec<String>) {
+    if commands.is_empty() {
+        return;
+    }
+    thread::spawn(move || run_hooks(&commands));
+}
+
+/// Runs the commands one after another and waits for them to finish
+pub(crate) fn run_hooks(commands: &[String]) {
+    for command in commands {
+        // stdout can be the output of the status bar
+        match Command::new("/bin/sh")
+            .arg("-c")
+            .arg(command)
+            .stdout(io::stderr())
+            .status()
+        {
+            Ok(status) if !status.success() => {
+                warn!("hook '{command}' exited with non-zero error code ({status})")
+            }
+            Ok(_) => {}
+            Err(e) => warn!("error running hook '{command}': {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn run_in_order() {
+        let file = env::temp_dir().join(format!("dwmblocksrs-hooks-{}", std::process::id()));
+        let path = file.to_str().unwrap();
+        run_hooks(&[
+            format!("echo first > {path}"),
+            format!("echo second >> {path}"),
+        ]);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "first\nsecond\n");
+        fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr, OsString},
+    io,
+    os::unix::{ffi::OsStrExt, io::RawFd},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use async_std::channel::{self, Receiver, Sender, TrySendError};
+use log::warn;
+
+const MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO;
+
+/// A minimal wrapper around an inotify instance
+pub(crate) struct Inotify {
+    fd: RawFd,
+}
+
+impl Inotify {
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd })
+    }
+
+    /// returns the watch descriptor
+    pub fn add_watch(&self, path: &Path, mask: u32) -> io::Result<i32> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), mask) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(wd)
+    }
+
+    /// blocks until events arrive and returns the watch descriptors and file names of the events
+    pub fn read_events(&self) -> io::Result<Vec<(i32, Option<OsString>)>> {
+        let mut buffer = [0u8; 4096];
+        let read = unsafe { libc::read(self.fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if read < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let header = std::mem::size_of::<libc::inotify_event>();
+        let mut events = Vec::new();
+        let mut offset = 0;
+        while offset + header <= read as usize {
+            let event: libc::inotify_event =
+                unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr().cast()) };
+            let name = &buffer[offset + header..offset + header + event.len as usize];
+            let name = name.split(|b| *b == 0).next().unwrap_or_default();
+            let name = (!name.is_empty()).then(|| OsStr::from_bytes(name).to_os_string());
+            events.push((event.wd, name));
+            offset += header + event.len as usize;
+        }
+        Ok(events)
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+/// Watches the paths and sends a request through the returned channel whenever one of them changes.
+/// Directories are watched for changes of their entries.
+/// Files are watched through their parent directory, so they can also be replaced or created later.
+pub(crate) fn watch_for_changes(paths: Vec<PathBuf>) -> Receiver<()> {
+    let (tx, rx) = channel::bounded(1);
+    thread::spawn(move || {
+        if let Err(e) = watch(&paths, tx) {
+            warn!("error watching {paths:?} for changes: {e}");
+        }
+    });
+    rx
+}
+
+fn watch(paths: &[PathBuf], requests: Sender<()>) -> io::Result<()> {
+    let inotify = Inotify::new()?;
+
+    // the file names we are interested in for each watch descriptor, None means all files
+    let mut watches: HashMap<i32, Option<Vec<OsString>>> = HashMap::new();
+    for path in paths {
+        if path.is_dir() {
+            let wd = inotify.add_watch(path, MASK)?;
+            watches.insert(wd, None);
+        } else {
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            let name = path.file_name().unwrap_or_default().to_os_string();
+            let wd = inotify.add_watch(parent, MASK)?;
+            if let Some(names) = watches.entry(wd).or_insert_with(|| Some(Vec::new())) {
+                names.push(name);
+            }
+        }
+    }
+
+    loop {
+        let changed = inotify
+            .read_events()?
+            .into_iter()
+            .any(|(wd, name)| match watches.get(&wd) {
+                Some(None) => true,
+                Some(Some(names)) => name.is_some_and(|name| names.contains(&name)),
+                None => false,
+            });
+
+        if changed {
+            if let Err(TrySendError::Closed(_)) = requests.try_send(()) {
+                // nobody is interested in the changes anymore
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, time::Duration};
+
+    #[async_std::test]
+    async fn file_changes() {
+        let dir = env::temp_dir().join(format!("dwmblocksrs-inotify-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("watched");
+
+        let requests = watch_for_changes(vec![file.clone()]);
+        // give the watcher some time to start
+        async_std::task::sleep(Duration::from_millis(100)).await;
+
+        // other files in the directory are ignored
+        fs::write(dir.join("other"), "").unwrap();
+        fs::write(&file, "content").unwrap();
+        async_std::future::timeout(Duration::from_secs(5), requests.recv())
+            .await
+            .expect("change should be noticed")
+            .unwrap();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
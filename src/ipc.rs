@@ -0,0 +1,216 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    os::unix::{fs::FileTypeExt, net},
+    path::{Path, PathBuf},
+};
+
+use async_std::{
+    channel::{self, Sender},
+    io::{prelude::BufReadExt, BufReader, WriteExt},
+    os::unix::net::{UnixListener, UnixStream},
+    prelude::*,
+    task,
+};
+use log::warn;
+
+use crate::control::{Command, Request};
+use crate::segments::SegmentCommand;
+
+/// Parses the commands of the socket:
+/// `refresh <name>`, `set <name> <text>`, `view <name>`, `toggle <name>` and `status`
+fn parse_line(line: &str) -> Result<Command, String> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+    let name = || match arguments.split_whitespace().next() {
+        Some(name) => Ok(name.to_string()),
+        None => Err(format!("'{command}' needs the name of a segment")),
+    };
+
+    match command {
+        "refresh" => Ok(Command::Segment(name()?, SegmentCommand::Update)),
+        "set" => {
+            let name = name()?;
+            let text = arguments.trim_start()[name.len()..]
+                .strip_prefix(' ')
+                .unwrap_or_default();
+            Ok(Command::Segment(name, SegmentCommand::Set(text.into())))
+        }
+        "view" => Ok(Command::Segment(name()?, SegmentCommand::NextView)),
+        "toggle" => Ok(Command::Toggle(name()?)),
+        "status" => Ok(Command::Status),
+        _ => Err(format!("unknown command '{command}'")),
+    }
+}
+
+/// Listens on the unix socket and sends the commands of the clients as requests,
+/// until the returned task is cancelled.
+/// Every command is answered by a line with `ok`, the text of the status bar or `error: <message>`.
+pub(crate) fn listen_socket(
+    path: PathBuf,
+    requests: Sender<Request>,
+) -> Result<task::JoinHandle<()>, String> {
+    remove_stale_socket(&path)
+        .map_err(|e| format!("error removing socket '{}': {}", path.display(), e))?;
+    let listener = task::block_on(UnixListener::bind(&path))
+        .map_err(|e| format!("error creating socket '{}': {}", path.display(), e))?;
+
+    Ok(task::spawn(async move {
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            match stream {
+                Ok(stream) => {
+                    task::spawn(handle_client(stream, requests.clone()));
+                }
+                Err(e) => warn!("error accepting connection: {e}"),
+            }
+        }
+    }))
+}
+
+/// Stops listening on the socket and removes it
+pub(crate) async fn stop_socket(path: &Path, task: task::JoinHandle<()>) {
+    task.cancel().await;
+    let _ = remove_stale_socket(path);
+}
+
+/// a socket left over by an instance that was killed would prevent binding
+fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+async fn handle_client(stream: UnixStream, requests: Sender<Request>) {
+    let mut lines = BufReader::new(&stream).lines();
+    let mut writer = &stream;
+
+    while let Some(Ok(line)) = lines.next().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = match parse_line(&line) {
+            Ok(command) => request(command, &requests).await,
+            Err(e) => Err(e),
+        };
+        let answer = match answer {
+            Ok(text) => text,
+            Err(e) => format!("error: {e}"),
+        };
+        if writer
+            .write_all(format!("{answer}\n").as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// passes the command to the status bar and waits for the answer
+async fn request(command: Command, requests: &Sender<Request>) -> Result<String, String> {
+    let (tx, rx) = channel::bounded(1);
+    let request = Request {
+        command,
+        reply: Some(tx),
+    };
+    if requests.send(request).await.is_err() {
+        return Err("the status bar is shutting down".into());
+    }
+    rx.recv()
+        .await
+        .unwrap_or_else(|_| Err("the status bar is shutting down".into()))
+}
+
+/// Sends a command to the socket of a running instance and returns the answer
+pub(crate) fn send(path: &Path, line: &str) -> Result<String, String> {
+    let mut stream = net::UnixStream::connect(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => {
+            "dwmblocksrs is not running".to_string()
+        }
+        _ => format!("error connecting to socket '{}': {}", path.display(), e),
+    })?;
+    writeln!(stream, "{line}").map_err(|e| format!("error sending command: {e}"))?;
+
+    let mut answer = String::new();
+    io::BufReader::new(stream)
+        .read_line(&mut answer)
+        .map_err(|e| format!("error reading answer: {e}"))?;
+    let answer = answer.trim_end_matches('\n');
+    match answer.strip_prefix("error: ") {
+        Some(e) => Err(e.into()),
+        None => Ok(answer.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_line("refresh mail"),
+            Ok(Command::Segment("mail".into(), SegmentCommand::Update))
+        );
+        assert_eq!(
+            parse_line("set volume muted  now"),
+            Ok(Command::Segment(
+                "volume".into(),
+                SegmentCommand::Set("muted  now".into())
+            ))
+        );
+        assert_eq!(
+            parse_line("set volume"),
+            Ok(Command::Segment(
+                "volume".into(),
+                SegmentCommand::Set("".into())
+            ))
+        );
+        assert_eq!(
+            parse_line("view clock"),
+            Ok(Command::Segment("clock".into(), SegmentCommand::NextView))
+        );
+        assert_eq!(
+            parse_line("toggle weather"),
+            Ok(Command::Toggle("weather".into()))
+        );
+        assert_eq!(parse_line("status"), Ok(Command::Status));
+        assert!(parse_line("toggle").is_err());
+        assert!(parse_line("explode").is_err());
+    }
+
+    #[async_std::test]
+    async fn answers() {
+        let path = std::env::temp_dir().join(format!("dwmblocksrs-ipc-{}", std::process::id()));
+        let (tx, requests) = channel::unbounded();
+        listen_socket(path.clone(), tx).unwrap();
+
+        task::spawn(async move {
+            while let Ok(request) = requests.recv().await {
+                let answer = match request.command {
+                    Command::Status => Ok("bar".into()),
+                    _ => Err("unknown segment".into()),
+                };
+                request.reply.unwrap().send(answer).await.unwrap();
+            }
+        });
+
+        assert_eq!(send(&path, "status"), Ok("bar".into()));
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        stream.write_all(b"status\ntoggle x\nfoo\n").await.unwrap();
+        let mut lines = BufReader::new(&stream).lines();
+        assert_eq!(lines.next().await.unwrap().unwrap(), "bar");
+        assert_eq!(
+            lines.next().await.unwrap().unwrap(),
+            "error: unknown segment"
+        );
+        assert_eq!(
+            lines.next().await.unwrap().unwrap(),
+            "error: unknown command 'foo'"
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
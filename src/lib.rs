@@ -1,47 +1,670 @@
+pub(crate) mod click;
 pub(crate) mod color;
+mod command_cache;
+mod command_limit;
 mod config;
+pub mod context;
+mod control;
+mod dbus;
+mod duration;
+mod hooks;
+mod inotify;
+#[cfg(feature = "ipc")]
+mod ipc;
+mod local_time;
+mod menu;
+mod migrate;
+mod notification;
+mod number_format;
+mod output;
+mod registry;
+mod schedule;
 pub mod segments;
 mod status_bar;
+mod template;
+mod theme;
+mod transform;
 
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use async_std::channel;
 use async_std::prelude::*;
 use async_std::task;
-use config::parse_config;
+use config::{parse_config, parse_config_with_registry, Configuration};
+use control::{Command, Request};
+use futures_lite::future;
+use inotify::watch_for_changes;
+use log::{error, info, warn};
 use segments::Segment;
+use signal_hook_async_std::Signals;
 use status_bar::StatusBar;
 
+pub use color::{Color, ColorFormat, SegmentColoring};
+pub use output::OutputConfig;
+pub use registry::Registry;
+pub use status_bar::StatusBarConfig;
+
 pub(crate) type SegmentId = usize;
 
-/// Run the statusbar with the given segments
-pub async fn run(segments: Vec<Segment>) -> Result<(), String> {
+enum Event {
+    Update(SegmentId, String),
+    Control(Request),
+    Toggle(i32),
+    Reload,
+    Shutdown,
+}
+
+/// A status bar that runs in the background
+pub struct Handle {
+    shutdown: channel::Sender<()>,
+    task: task::JoinHandle<()>,
+}
+
+impl Handle {
+    /// Stops the status bar, like SIGTERM or SIGINT do
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.try_send(());
+    }
+
+    /// Waits until the status bar stopped
+    pub async fn wait(self) {
+        self.task.await
+    }
+}
+
+/// Runs the statusbar with the given segments in the background,
+/// until SIGTERM or SIGINT arrives or it is shut down with the handle
+pub fn run(segments: Vec<Segment>) -> Result<Handle, String> {
+    run_with_status_bar_config(segments, Default::default())
+}
+
+/// Like `run`, but with custom settings for the status bar
+pub fn run_with_status_bar_config(
+    segments: Vec<Segment>,
+    config: StatusBarConfig,
+) -> Result<Handle, String> {
+    let status_bar = StatusBar::new(segments.iter().map(Segment::info).collect(), config)?;
+    spawn_status_bar(segments, status_bar)
+}
+
+/// Like `run`, but instead of displaying the status, the observer is called with every new one
+pub fn run_with_observer(
+    segments: Vec<Segment>,
+    observer: impl Fn(&str) + Send + 'static,
+) -> Result<Handle, String> {
+    let config = StatusBarConfig {
+        outputs: Vec::new(),
+        ..Default::default()
+    };
+    let mut status_bar = StatusBar::new(segments.iter().map(Segment::info).collect(), config)?;
+    status_bar.add_output(Box::new(output::observer::Observer(Box::new(observer))));
+    spawn_status_bar(segments, status_bar)
+}
+
+fn spawn_status_bar(segments: Vec<Segment>, mut status_bar: StatusBar) -> Result<Handle, String> {
+    let shutdown = Shutdown::new()?;
+    let handle = Handle {
+        shutdown: shutdown.sender.clone(),
+        task: task::spawn(async move {
+            run_segments(segments, &mut status_bar, &shutdown, None, None).await;
+            segments::program_output::kill_running();
+            status_bar.close();
+        }),
+    };
+    Ok(handle)
+}
+
+/// Requests to shut down, from SIGTERM, SIGINT or a handle
+struct Shutdown {
+    sender: channel::Sender<()>,
+    requests: channel::Receiver<()>,
+    signals: signal_hook_async_std::Handle,
+}
+
+impl Shutdown {
+    fn new() -> Result<Self, String> {
+        let mut signals = Signals::new([libc::SIGTERM, libc::SIGINT])
+            .map_err(|e| format!("error registering shutdown signals: {e}"))?;
+        let (sender, requests) = channel::unbounded();
+        let handle = signals.handle();
+        let signal_sender = sender.clone();
+        task::spawn(async move {
+            if signals.next().await.is_some() {
+                let _ = signal_sender.send(()).await;
+            }
+        });
+
+        Ok(Self {
+            sender,
+            requests,
+            signals: handle,
+        })
+    }
+
+    async fn requested(&self) {
+        let _ = self.requests.recv().await;
+    }
+}
+
+impl Drop for Shutdown {
+    /// stops waiting for the signals
+    fn drop(&mut self) {
+        self.signals.close();
+    }
+}
+
+/// Where the configuration is reloaded from and what requests the reloads
+struct Reload {
+    config_path: PathBuf,
+    file_changes: channel::Receiver<()>,
+    signals: Signals,
+    /// the custom segment kinds of the configuration
+    registry: Registry,
+}
+
+impl Reload {
+    /// waits until the configuration file changed or SIGHUP arrived
+    async fn requested(&mut self) {
+        future::or(
+            async {
+                if self.file_changes.recv().await.is_err() {
+                    future::pending::<()>().await;
+                }
+            },
+            async {
+                self.signals.next().await;
+            },
+        )
+        .await;
+
+        // editors often write the file in several steps
+        task::sleep(Duration::from_millis(100)).await;
+        while self.file_changes.try_recv().is_ok() {}
+    }
+}
+
+/// Runs the segments until the shutdown.
+/// If the configuration is reloaded successfully, the segments are stopped
+/// and the new configuration is returned.
+async fn run_segments(
+    segments: Vec<Segment>,
+    status_bar: &mut StatusBar,
+    shutdown: &Shutdown,
+    mut reload: Option<&mut Reload>,
+    control: Option<&channel::Receiver<Request>>,
+) -> Option<(Configuration, Vec<Segment>)> {
     // when a segment should get updated, it's id is send through this channel
     let (tx, mut rx) = channel::unbounded::<(SegmentId, String)>();
+    // the named segments and the channels for their commands
+    let mut names = HashMap::new();
+    // the segments that are hidden or shown by a signal
+    let toggle_signals = segments
+        .iter()
+        .enumerate()
+        .filter_map(|(id, segment)| segment.toggle_signal().map(|signal| (signal, id)))
+        .collect::<Vec<_>>();
+    let mut toggles = match Signals::new(toggle_signals.iter().map(|(signal, _)| *signal)) {
+        Ok(signals) => Some(signals),
+        Err(e) => {
+            error!("error registering toggle signals: {e}");
+            None
+        }
+    };
 
-    let num_segments = segments.len();
+    let (command_txs, command_rxs): (Vec<_>, Vec<_>) =
+        segments.iter().map(|_| channel::unbounded()).unzip();
+    // the command channels of the segments that are updated after each segment
+    let mut dependents = vec![Vec::new(); segments.len()];
+    for (id, segment) in segments.iter().enumerate() {
+        for name in &segment.depends_on {
+            if let Some(dependency) = segments.iter().position(|s| s.name.as_ref() == Some(name)) {
+                dependents[dependency].push(command_txs[id].clone());
+            }
+        }
+    }
 
     // for each segment we spawn a task that requests updates according to the update period
-    for (id, segment) in segments.into_iter().enumerate() {
-        let sender = tx.clone();
-        task::spawn(async move { segment.run_update_loop(id, sender).await });
+    let tasks = segments
+        .into_iter()
+        .zip(command_txs.into_iter().zip(command_rxs))
+        .enumerate()
+        .map(|(id, (segment, (command_tx, command_rx)))| {
+            let sender = tx.clone();
+            if let Some(name) = &segment.name {
+                names.insert(name.clone(), (id, command_tx));
+            }
+            task::spawn(async move { segment.run_update_loop(id, sender, command_rx).await })
+        })
+        .collect::<Vec<_>>();
+
+    let new_config = loop {
+        // wait for a new update, a reload or for the shutdown
+        let event = future::or(
+            async { rx.next().await.map(|(id, text)| Event::Update(id, text)) },
+            future::or(
+                async {
+                    shutdown.requested().await;
+                    Some(Event::Shutdown)
+                },
+                future::or(
+                    async {
+                        match &mut reload {
+                            Some(reload) => {
+                                reload.requested().await;
+                                Some(Event::Reload)
+                            }
+                            None => future::pending().await,
+                        }
+                    },
+                    future::or(
+                        async {
+                            match control.map(|control| control.recv()) {
+                                Some(command) => match command.await {
+                                    Ok(command) => Some(Event::Control(command)),
+                                    Err(_) => future::pending().await,
+                                },
+                                None => future::pending().await,
+                            }
+                        },
+                        async {
+                            match &mut toggles {
+                                Some(toggles) => toggles.next().await.map(Event::Toggle),
+                                None => future::pending().await,
+                            }
+                        },
+                    ),
+                ),
+            ),
+        )
+        .await;
+
+        match event {
+            // and update that segment in the status bar
+            Some(Event::Update(id, text)) if status_bar.debounce().is_zero() => {
+                status_bar.update_segment(id, text);
+                update_dependents(&dependents[id]);
+            }
+            Some(Event::Update(id, text)) => {
+                status_bar.set_segment_text(id, text);
+                update_dependents(&dependents[id]);
+                for id in collect_updates(&mut rx, status_bar).await {
+                    update_dependents(&dependents[id]);
+                }
+                status_bar.refresh();
+            }
+            Some(Event::Control(request)) => {
+                let answer = handle_command(request.command, &names, status_bar);
+                match request.reply {
+                    Some(reply) => {
+                        let _ = reply.try_send(answer);
+                    }
+                    None => {
+                        if let Err(e) = answer {
+                            warn!("{e}");
+                        }
+                    }
+                }
+            }
+            Some(Event::Toggle(signal)) => toggle_signals
+                .iter()
+                .filter(|(s, _)| *s == signal)
+                .for_each(|(_, id)| {
+                    status_bar.toggle_segment(*id);
+                }),
+            Some(Event::Reload) => {
+                let reload = reload.as_ref().unwrap();
+                info!("reloading config file '{}'", reload.config_path.display());
+                match parse_config_with_registry(reload.config_path.clone(), &reload.registry) {
+                    Ok(new_config) => break Some(new_config),
+                    // keep the running segments
+                    Err(e) => error!("error reloading config: {e}"),
+                }
+            }
+            Some(Event::Shutdown) | None => break None,
+        }
+    };
+
+    for task in tasks {
+        task.cancel().await;
+    }
+    if let Some(toggles) = toggles {
+        toggles.handle().close();
     }
 
-    let mut status_bar = StatusBar::new(num_segments);
+    new_config
+}
 
-    // wait for a new update to arrive
-    while let Some((id, text)) = rx.next().await {
-        // and update that segment in the status bar
-        status_bar.update_segment(id, text);
+/// Sets the texts of the updates that arrive within the debounce time,
+/// so that they are displayed together, and returns the updated segments
+async fn collect_updates(
+    rx: &mut channel::Receiver<(SegmentId, String)>,
+    status_bar: &mut StatusBar,
+) -> Vec<SegmentId> {
+    let mut updated = Vec::new();
+    let deadline = Instant::now() + status_bar.debounce();
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match async_std::future::timeout(remaining, rx.next()).await {
+            Ok(Some((id, text))) => {
+                status_bar.set_segment_text(id, text);
+                updated.push(id);
+            }
+            _ => break,
+        }
     }
+    updated
+}
 
-    Ok(())
+/// requests an update of the segments that depend on an updated segment
+fn update_dependents(dependents: &[channel::Sender<segments::SegmentCommand>]) {
+    for command_tx in dependents {
+        let _ = command_tx.try_send(segments::SegmentCommand::Update);
+    }
+}
+
+/// executes a command from outside and returns the answer
+fn handle_command(
+    command: Command,
+    names: &HashMap<String, (SegmentId, channel::Sender<segments::SegmentCommand>)>,
+    status_bar: &mut StatusBar,
+) -> Result<String, String> {
+    let segment = |name: &String| {
+        names
+            .get(name)
+            .ok_or_else(|| format!("there is no segment named '{name}'"))
+    };
+
+    match command {
+        Command::Segment(name, command) => {
+            let _ = segment(&name)?.1.try_send(command);
+            Ok("ok".into())
+        }
+        Command::Toggle(name) => {
+            status_bar.toggle_segment(segment(&name)?.0);
+            Ok("ok".into())
+        }
+        Command::Status => Ok(status_bar.text().into()),
+    }
+}
+
+/// The FIFO and the socket of the configuration, the commands that arrive on them are requests
+#[derive(Default)]
+struct Listeners {
+    fifo: Option<PathBuf>,
+    socket: Option<PathBuf>,
+    #[cfg(feature = "ipc")]
+    socket_task: Option<task::JoinHandle<()>>,
+    /// None if there is neither a FIFO nor a socket
+    requests: Option<channel::Receiver<Request>>,
+}
+
+impl Listeners {
+    /// Listens for commands on the FIFO and the socket of the configuration
+    async fn start(configuration: &Configuration) -> Result<Self, String> {
+        let (tx, rx) = channel::unbounded();
+        let mut listeners = Self {
+            requests: Some(rx),
+            ..Default::default()
+        };
+
+        if let Some(fifo) = &configuration.control_fifo {
+            control::listen_fifo(fifo.clone(), tx.clone())?;
+            listeners.fifo = Some(fifo.clone());
+        }
+
+        if let Some(socket) = &configuration.control_socket {
+            #[cfg(feature = "ipc")]
+            match ipc::listen_socket(socket.clone(), tx) {
+                Ok(task) => {
+                    listeners.socket = Some(socket.clone());
+                    listeners.socket_task = Some(task);
+                }
+                Err(e) => {
+                    listeners.stop().await;
+                    return Err(e);
+                }
+            }
+            #[cfg(not(feature = "ipc"))]
+            {
+                let _ = socket;
+                listeners.stop().await;
+                return Err("control_socket needs dwmblocksrs built with the ipc feature".into());
+            }
+        }
+
+        if listeners.fifo.is_none() && listeners.socket.is_none() {
+            listeners.requests = None;
+        }
+        Ok(listeners)
+    }
+
+    /// whether the configuration has another FIFO or socket
+    fn changed(&self, configuration: &Configuration) -> bool {
+        self.fifo != configuration.control_fifo || self.socket != configuration.control_socket
+    }
+
+    /// Stops listening and removes the FIFO and the socket
+    async fn stop(mut self) {
+        // the listeners stop when nobody receives their requests anymore
+        drop(self.requests.take());
+        if let Some(fifo) = &self.fifo {
+            control::stop_fifo(fifo);
+        }
+        #[cfg(feature = "ipc")]
+        if let (Some(socket), Some(task)) = (&self.socket, self.socket_task.take()) {
+            ipc::stop_socket(socket, task).await;
+        }
+    }
 }
 
-/// Run the statusbar with the given configuration file
+/// the config file and the files it imports
+fn watched_files(config_path: &Path, configuration: &Configuration) -> Vec<PathBuf> {
+    let mut files = vec![config_path.to_path_buf()];
+    files.extend(configuration.watched_files.iter().cloned());
+    files
+}
+
+/// Run the statusbar with the given configuration file.
+/// The configuration is reloaded when the file changes or SIGHUP arrives.
 pub async fn run_with_config(config_path: PathBuf) -> Result<(), String> {
-    let segments = parse_config(config_path)?;
-    run(segments).await
+    run_with_config_and_registry(config_path, Registry::new()).await
+}
+
+/// Like `run_with_config`, but the configuration can use the custom segment kinds of the registry
+pub async fn run_with_config_and_registry(
+    config_path: PathBuf,
+    registry: Registry,
+) -> Result<(), String> {
+    let (mut configuration, mut segments) =
+        parse_config_with_registry(config_path.clone(), &registry)?;
+
+    let shutdown = Shutdown::new()?;
+    let mut reload = Reload {
+        file_changes: watch_for_changes(watched_files(&config_path, &configuration)),
+        config_path,
+        signals: Signals::new([libc::SIGHUP])
+            .map_err(|e| format!("error registering reload signal: {e}"))?,
+        registry,
+    };
+
+    let mut listeners = Listeners::start(&configuration).await?;
+    command_limit::set_max_concurrent(configuration.max_concurrent_commands);
+
+    hooks::spawn_hooks(std::mem::take(&mut configuration.on_start));
+
+    let mut status_bar = StatusBar::new(
+        segments.iter().map(Segment::info).collect(),
+        configuration.status_bar.clone(),
+    )?;
+
+    while let Some((new_configuration, new_segments)) = run_segments(
+        segments,
+        &mut status_bar,
+        &shutdown,
+        Some(&mut reload),
+        listeners.requests.as_ref(),
+    )
+    .await
+    {
+        if new_configuration.watched_files != configuration.watched_files {
+            reload.file_changes =
+                watch_for_changes(watched_files(&reload.config_path, &new_configuration));
+        }
+        if listeners.changed(&new_configuration) {
+            listeners.stop().await;
+            listeners = Listeners::start(&new_configuration)
+                .await
+                .unwrap_or_else(|e| {
+                    error!("{e}");
+                    Listeners::default()
+                });
+        }
+        command_limit::set_max_concurrent(new_configuration.max_concurrent_commands);
+        configuration = new_configuration;
+        segments = new_segments;
+        status_bar.reset(
+            segments.iter().map(Segment::info).collect(),
+            configuration.status_bar.clone(),
+        );
+    }
+
+    // programs that are still running would outlive the status bar
+    segments::program_output::kill_running();
+    listeners.stop().await;
+    status_bar.close();
+    hooks::run_hooks(&configuration.on_exit);
+
+    Ok(())
+}
+
+/// Computes every segment of the configuration once and returns the text of the status bar
+pub async fn print_once(config_path: PathBuf) -> Result<String, String> {
+    let (configuration, segments) = parse_config(config_path)?;
+    let areas = segments.iter().map(|s| s.area).collect::<Vec<_>>();
+
+    // the segments are computed in parallel, so that slow ones don't add up
+    let tasks = segments
+        .into_iter()
+        .map(|mut segment| task::spawn(async move { segment.next_value().await }))
+        .collect::<Vec<_>>();
+    let mut texts = Vec::new();
+    for task in tasks {
+        texts.push(task.await);
+    }
+
+    Ok(status_bar::join_segments(
+        &texts,
+        &areas,
+        &configuration.status_bar,
+    ))
+}
+
+/// Writes a commented config file to start from, unless the file exists
+pub fn init_config(config_path: &Path) -> Result<(), String> {
+    if config_path.exists() {
+        return Err(format!("'{}' already exists", config_path.display()));
+    }
+    if let Some(dir) = config_path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("error creating '{}': {}", dir.display(), e))?;
+    }
+    std::fs::write(config_path, DEFAULT_CONFIG)
+        .map_err(|e| format!("error writing '{}': {}", config_path.display(), e))
+}
+
+const DEFAULT_CONFIG: &str = include_str!("../default_config.yaml");
+
+/// Converts the blocks of a `blocks.h` of dwmblocks into a config file
+pub fn migrate_blocks(blocks_path: &Path) -> Result<String, String> {
+    let source = std::fs::read_to_string(blocks_path)
+        .map_err(|e| format!("error reading '{}': {}", blocks_path.display(), e))?;
+    migrate::migrate_blocks(&source)
+}
+
+/// The JSON Schema of the configuration file
+pub fn config_schema() -> String {
+    config::schema()
+}
+
+/// Parses the configuration file and checks that its scripts and programs exist,
+/// without starting the status bar
+pub fn check_config(config_path: PathBuf) -> Result<(), String> {
+    config::check_config(config_path)
+}
+
+/// The names of the signals in the configuration file and their offsets, one per line
+pub fn list_signals(config_path: PathBuf) -> Result<String, String> {
+    let (configuration, _) = parse_config(config_path)?;
+    Ok(format_signals(&configuration))
+}
+
+fn format_signals(configuration: &Configuration) -> String {
+    let mut signals = configuration
+        .signal_names
+        .iter()
+        .map(|(name, offset)| (name.as_str(), *offset))
+        .chain(
+            configuration
+                .update_all_signal
+                .map(|offset| ("update_all_signal", offset)),
+        )
+        .collect::<Vec<_>>();
+    signals.sort_by_key(|(_, offset)| *offset);
+    let width = signals
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    signals
+        .into_iter()
+        .map(|(name, offset)| {
+            format!("{name:width$}  {offset:>2}  pkill -RTMIN+{offset} dwmblocksrs\n")
+        })
+        .collect()
+}
+
+/// Asks the instance running with the given configuration file to update the segment with the name.
+/// The segment is updated by its signal, or through the `control_fifo` if it has none.
+pub fn update_segment(config_path: PathBuf, name: &str) -> Result<(), String> {
+    let (configuration, segments) = parse_config(config_path)?;
+    let segment = segments
+        .iter()
+        .find(|s| s.name.as_deref() == Some(name))
+        .ok_or_else(|| format!("there is no segment named '{name}'"))?;
+    match (segment.update_signal, configuration.control_fifo) {
+        (Some(offset), _) => {
+            control::signal_processes("dwmblocksrs", libc::SIGRTMIN() + offset as i32)
+        }
+        (None, Some(fifo)) => control::send(&fifo, name),
+        (None, None) => Err(format!(
+            "the segment '{name}' has no signals, updating it requires a `control_fifo` in the config"
+        )),
+    }
+}
+
+/// Displays the text in the segment with the name of the instance running with the given configuration file
+#[cfg(feature = "ipc")]
+pub fn set_segment(config_path: PathBuf, name: &str, text: &str) -> Result<(), String> {
+    let (configuration, _) = parse_config(config_path)?;
+    let socket = configuration
+        .control_socket
+        .ok_or("setting segments requires a `control_socket` in the config")?;
+    ipc::send(&socket, &set_command(name, text)?).map(|_| ())
+}
+
+/// The `set` command of the socket, whose commands are lines
+#[cfg(feature = "ipc")]
+fn set_command(name: &str, text: &str) -> Result<String, String> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("invalid segment name '{name}'"));
+    }
+    if text.contains(['\n', '\r']) {
+        return Err("the text can't contain line breaks".into());
+    }
+    Ok(format!("set {name} {text}"))
 }
 
 #[cfg(test)]
@@ -50,7 +673,8 @@ mod tests {
 
     #[test]
     fn test_sample_config() {
-        let mut segments = parse_config("test_config.yaml".into()).expect("config should parse");
+        let (_, mut segments) =
+            parse_config("test_config.yaml".into()).expect("config should parse");
         let status_text = segments
             .iter_mut()
             .map(|s| s.compute_value())
@@ -61,9 +685,158 @@ mod tests {
         );
     }
 
+    #[async_std::test]
+    async fn listeners_on_reload() {
+        let dir =
+            std::env::temp_dir().join(format!("dwmblocksrs-listeners-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let configuration = |fifo: &str| Configuration {
+            control_fifo: Some(dir.join(fifo)),
+            ..Default::default()
+        };
+
+        let listeners = Listeners::start(&configuration("old")).await.unwrap();
+        assert!(!listeners.changed(&configuration("old")));
+        assert!(listeners.changed(&configuration("new")));
+        listeners.stop().await;
+        assert!(!dir.join("old").exists());
+
+        let listeners = Listeners::start(&configuration("new")).await.unwrap();
+        // the reading thread might not have opened the fifo yet
+        let mut sent = control::send(&dir.join("new"), "volume");
+        for _ in 0..100 {
+            if sent.is_ok() {
+                break;
+            }
+            task::sleep(Duration::from_millis(10)).await;
+            sent = control::send(&dir.join("new"), "volume");
+        }
+        sent.unwrap();
+        let requests = listeners.requests.as_ref().unwrap();
+        let request = async_std::future::timeout(Duration::from_secs(5), requests.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            request.command,
+            Command::Segment("volume".into(), segments::SegmentCommand::Update)
+        );
+        listeners.stop().await;
+        std::fs::remove_dir(dir).unwrap();
+    }
+
+    #[cfg(feature = "ipc")]
+    #[test]
+    fn set_commands() {
+        assert_eq!(
+            set_command("mail", "3 new"),
+            Ok("set mail 3 new".to_string())
+        );
+        assert!(set_command("mail", "3 new\nrefresh volume").is_err());
+        assert!(set_command("mail", "3\r").is_err());
+        assert!(set_command("mail\nstatus", "3").is_err());
+    }
+
+    #[test]
+    fn signal_names() {
+        let configuration = Configuration {
+            signal_names: [("volume".into(), 10), ("brightness".into(), 2)].into(),
+            update_all_signal: Some(30),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_signals(&configuration),
+            "brightness          2  pkill -RTMIN+2 dwmblocksrs\n\
+             volume             10  pkill -RTMIN+10 dwmblocksrs\n\
+             update_all_signal  30  pkill -RTMIN+30 dwmblocksrs\n"
+        );
+    }
+
+    #[test]
+    fn init() {
+        let dir = std::env::temp_dir().join(format!("dwmblocksrs-init-{}", std::process::id()));
+        let config_path = dir.join("dwmblocksrs").join("dwmblocksrs.yaml");
+        init_config(&config_path).unwrap();
+        parse_config(config_path.clone()).expect("the default config should parse");
+        assert!(init_config(&config_path).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[async_std::test]
+    async fn once() {
+        assert_eq!(
+            print_once("test_config.yaml".into()).await.unwrap(),
+            "Segment1 | Segment2 | hello world |  | %%% | $>>><<<"
+        );
+    }
+
+    #[async_std::test]
+    async fn reload_on_file_change() {
+        let dir = std::env::temp_dir().join(format!("dwmblocksrs-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.yaml");
+        std::fs::write(&config_path, "segments: []").unwrap();
+
+        let mut reload = Reload {
+            file_changes: watch_for_changes(vec![config_path.clone()]),
+            config_path: config_path.clone(),
+            signals: Signals::new([libc::SIGHUP]).unwrap(),
+            registry: Registry::new(),
+        };
+        // give the watcher some time to start
+        task::sleep(Duration::from_millis(100)).await;
+
+        std::fs::write(&config_path, "segments: []\n").unwrap();
+        async_std::future::timeout(Duration::from_secs(5), reload.requested())
+            .await
+            .expect("reload should be requested");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[async_std::test]
+    async fn debounce() {
+        let config = StatusBarConfig {
+            outputs: Vec::new(),
+            ..Default::default()
+        };
+        let mut status_bar = StatusBar::new(vec![Default::default(); 3], config).unwrap();
+        let (tx, mut rx) = channel::unbounded();
+        tx.send((1, "b".into())).await.unwrap();
+        tx.send((2, "c".into())).await.unwrap();
+
+        status_bar.set_segment_text(0, "a".into());
+        collect_updates(&mut rx, &mut status_bar).await;
+        assert_eq!(status_bar.text(), "");
+        status_bar.refresh();
+        assert_eq!(status_bar.text(), "abc");
+    }
+
+    #[async_std::test]
+    async fn observer() {
+        let segment = Segment::new(
+            Box::new(segments::constant::Constant::new("a".into())),
+            None,
+            vec![],
+        )
+        .unwrap();
+        let (tx, rx) = channel::unbounded();
+        let handle = run_with_observer(vec![segment], move |status| {
+            let _ = tx.try_send(status.to_string());
+        })
+        .unwrap();
+
+        assert_eq!(rx.recv().await.unwrap(), "");
+        assert_eq!(rx.recv().await.unwrap(), "a");
+        handle.shutdown();
+        async_std::future::timeout(Duration::from_secs(5), handle.wait())
+            .await
+            .expect("the status bar should stop");
+    }
+
     #[test]
     fn test_sample_config_color() {
-        let mut segments = parse_config("test_config_color.yaml".into()).unwrap();
+        let (_, mut segments) = parse_config("test_config_color.yaml".into()).unwrap();
         let status_text = segments
             .iter_mut()
             .map(|s| s.compute_value())
@@ -0,0 +1,16 @@
+use std::time::SystemTime;
+
+/// the current unix timestamp in seconds
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// converts the unix timestamp into the broken down local time
+pub(crate) fn local_time(timestamp: i64) -> libc::tm {
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&timestamp, &mut tm) };
+    tm
+}
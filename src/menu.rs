@@ -0,0 +1,109 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use log::warn;
+
+/// A menu (dmenu, rofi, ...) that lets the user choose an action for a segment
+#[derive(Debug, Clone)]
+pub(crate) struct Menu {
+    /// the command that displays the menu, it gets the labels on stdin
+    /// and is expected to print the selected label
+    program: String,
+    /// labels and the commands that are run when the label is chosen
+    items: Vec<(String, String)>,
+}
+
+impl Menu {
+    pub fn new(program: String, items: Vec<(String, String)>) -> Self {
+        Self { program, items }
+    }
+
+    /// Displays the menu and runs the command of the selected item
+    pub fn show(&self) {
+        let selection = match self.select() {
+            Ok(selection) => selection,
+            Err(e) => {
+                warn!("error running menu '{}': {}", self.program, e);
+                return;
+            }
+        };
+
+        // nothing was selected
+        let command = match self.command_for(&selection) {
+            Some(command) => command,
+            None => return,
+        };
+
+        // stdout can be the output of the status bar
+        match Command::new("/bin/sh")
+            .arg("-c")
+            .arg(command)
+            .stdout(io::stderr())
+            .status()
+        {
+            Ok(status) if !status.success() => {
+                warn!("menu command '{command}' exited with non-zero error code ({status})")
+            }
+            Ok(_) => {}
+            Err(e) => warn!("error running menu command '{command}': {e}"),
+        }
+    }
+
+    fn select(&self) -> std::io::Result<String> {
+        let mut child = Command::new("/bin/sh")
+            .arg("-c")
+            .arg(&self.program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let labels = self
+            .items
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        // stdin is closed when it goes out of scope, so the menu knows that all labels arrived
+        child.stdin.take().unwrap().write_all(labels.as_bytes())?;
+
+        let output = child.wait_with_output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().into())
+    }
+
+    fn command_for(&self, selection: &str) -> Option<&str> {
+        self.items
+            .iter()
+            .find(|(label, _)| label == selection)
+            .map(|(_, command)| command.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menu(program: &str) -> Menu {
+        Menu::new(
+            program.into(),
+            vec![
+                ("first".into(), "echo 1".into()),
+                ("second".into(), "echo 2".into()),
+            ],
+        )
+    }
+
+    #[test]
+    fn select() {
+        // simulates a menu where the user picks the last entry
+        assert_eq!(menu("tail -n 1").select().unwrap(), "second");
+    }
+
+    #[test]
+    fn command_for() {
+        let menu = menu("dmenu");
+        assert_eq!(menu.command_for("first"), Some("echo 1"));
+        assert_eq!(menu.command_for(""), None);
+    }
+}
@@ -0,0 +1,267 @@
+//! Converts the blocks of the original dwmblocks into a config of dwmblocksrs
+
+/// Reads the `blocks` array (and the delimiter) of a `blocks.h` of dwmblocks and returns the equivalent YAML config
+pub(crate) fn migrate_blocks(source: &str) -> Result<String, String> {
+    let tokens = tokenize(source)?;
+
+    let blocks = parse_array(&tokens, "blocks")?;
+    let mut segments = Vec::new();
+    for block in blocks {
+        let [Token::Str(icon), Token::Str(command), Token::Num(interval), Token::Num(signal)] =
+            &block[..]
+        else {
+            return Err(format!(
+                "the block {} is not like {{icon, command, interval, signal}}",
+                describe(&block)
+            ));
+        };
+
+        let mut segment = serde_yaml::Mapping::new();
+        segment.insert("sh".into(), command.as_str().into());
+        if !icon.is_empty() {
+            segment.insert("icon".into(), icon.as_str().into());
+        }
+        // dwmblocks only updates blocks without an interval on their signal
+        if *interval > 0 {
+            segment.insert("update_interval".into(), (*interval).into());
+        }
+        if *signal > 0 {
+            segment.insert("signals".into(), vec![*signal].into());
+        }
+        segments.push(serde_yaml::Value::Mapping(segment));
+    }
+
+    let mut config = serde_yaml::Mapping::new();
+    if let Some(delim) = find_delimiter(&tokens) {
+        if !delim.is_empty() {
+            config.insert("separator".into(), delim.into());
+        }
+    }
+    config.insert("segments".into(), segments.into());
+
+    let yaml = serde_yaml::to_string(&config).map_err(|e| e.to_string())?;
+    Ok(yaml.trim_start_matches("---\n").to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(i64),
+    Punct(char),
+}
+
+/// Splits the C source into tokens, leaving out comments and preprocessor lines.
+/// Adjacent string literals are joined like the compiler does.
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line_start = true,
+            c if c.is_whitespace() => {}
+            '#' if line_start => {
+                // the rest of the line, including continued lines
+                let mut previous = '#';
+                for c in chars.by_ref() {
+                    if c == '\n' && previous != '\\' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line_start = true;
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => previous = c,
+                        None => return Err("a comment is not closed".into()),
+                    }
+                }
+            }
+            '"' | '\'' => {
+                let text = literal(&mut chars, c)?;
+                match tokens.last_mut() {
+                    Some(Token::Str(previous)) if c == '"' => previous.push_str(&text),
+                    _ => tokens.push(Token::Str(text)),
+                }
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.peek().is_some_and(char::is_ascii_digit)) =>
+            {
+                let mut number = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                    number.push(c);
+                    chars.next();
+                }
+                let number = number.trim_end_matches(['u', 'U', 'l', 'L']);
+                let value = number
+                    .parse()
+                    .map_err(|_| format!("'{number}' is no number"))?;
+                tokens.push(Token::Num(value));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c => tokens.push(Token::Punct(c)),
+        }
+        if !c.is_whitespace() {
+            line_start = false;
+        }
+    }
+    Ok(tokens)
+}
+
+/// the text of a string or character literal, after the opening quote
+fn literal(chars: &mut impl Iterator<Item = char>, quote: char) -> Result<String, String> {
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some(c) if c == quote => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('0') => text.push('\0'),
+                Some(c) => text.push(c),
+                None => break,
+            },
+            Some(c) => text.push(c),
+            None => break,
+        }
+    }
+    Err("a string is not closed".into())
+}
+
+/// the entries of the array `name[] = { {...}, ... }`, each as its values
+fn parse_array(tokens: &[Token], name: &str) -> Result<Vec<Vec<Token>>, String> {
+    let start = tokens
+        .windows(2)
+        .position(|w| w[0] == Token::Ident(name.into()) && w[1] == Token::Punct('['))
+        .ok_or(format!("there is no array named '{name}'"))?;
+    let mut tokens = tokens[start..]
+        .iter()
+        .skip_while(|token| **token != Token::Punct('{'))
+        .skip(1);
+
+    let mut entries = Vec::new();
+    loop {
+        match tokens.next() {
+            Some(Token::Punct('{')) => {
+                let mut entry = Vec::new();
+                for token in tokens.by_ref() {
+                    match token {
+                        Token::Punct('}') => break,
+                        Token::Punct(',') => {}
+                        token => entry.push(token.clone()),
+                    }
+                }
+                entries.push(entry);
+            }
+            Some(Token::Punct(',')) => {}
+            Some(Token::Punct('}')) => return Ok(entries),
+            Some(token) => {
+                return Err(format!(
+                    "unexpected {} in the array '{name}'",
+                    describe(std::slice::from_ref(token))
+                ))
+            }
+            None => return Err(format!("the array '{name}' is not closed")),
+        }
+    }
+}
+
+/// the string that `delim` is set to
+fn find_delimiter(tokens: &[Token]) -> Option<String> {
+    let start = tokens
+        .iter()
+        .position(|token| *token == Token::Ident("delim".into()))?;
+    tokens[start..]
+        .iter()
+        .take_while(|token| **token != Token::Punct(';'))
+        .find_map(|token| match token {
+            Token::Str(text) => Some(text.trim_end_matches('\0').to_string()),
+            _ => None,
+        })
+}
+
+fn describe(tokens: &[Token]) -> String {
+    let parts = tokens
+        .iter()
+        .map(|token| match token {
+            Token::Ident(ident) => ident.clone(),
+            Token::Str(text) => format!("{text:?}"),
+            Token::Num(number) => number.to_string(),
+            Token::Punct(c) => c.to_string(),
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", parts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCKS: &str = r#"//Modify this file to change what commands output to your statusbar, and recompile using the make command.
+static const Block blocks[] = {
+	/*Icon*/	/*Command*/		/*Update Interval*/	/*Update Signal*/
+	{"Mem:", "free -h | awk '/^Mem/ { print $3\"/\"$2 }' | sed s/i//g",	30,		0},
+
+	{"", "date '+%b %d (%a) %I:%M%p'"
+	     " | tr -d '\\n'",					5,		0},
+	{"", "volume",					0,		10},
+};
+
+//sets delimeter between status commands. NULL character ('\0') means no delimeter.
+static char delim[] = " | ";
+static unsigned int delimLen = 5;
+"#;
+
+    #[test]
+    fn migrate() {
+        let yaml = migrate_blocks(BLOCKS).unwrap();
+        let config: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        let text = |value: &serde_yaml::Value| value.as_str().map(String::from);
+        assert_eq!(text(&config["separator"]).as_deref(), Some(" | "));
+
+        let segments = config["segments"].as_sequence().unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(
+            text(&segments[0]["sh"]).as_deref(),
+            Some("free -h | awk '/^Mem/ { print $3\"/\"$2 }' | sed s/i//g")
+        );
+        assert_eq!(text(&segments[0]["icon"]).as_deref(), Some("Mem:"));
+        assert_eq!(segments[0]["update_interval"].as_u64(), Some(30));
+        assert_eq!(segments[0].get("signals"), None);
+        assert_eq!(
+            text(&segments[1]["sh"]).as_deref(),
+            Some("date '+%b %d (%a) %I:%M%p' | tr -d '\\n'")
+        );
+        assert_eq!(segments[1].get("icon"), None);
+        assert_eq!(segments[2].get("update_interval"), None);
+        assert_eq!(segments[2]["signals"][0].as_u64(), Some(10));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(migrate_blocks("int x;").is_err());
+        assert!(migrate_blocks("static const Block blocks[] = { {\"\", \"date\", 5} };").is_err());
+        assert!(migrate_blocks("static const Block blocks[] = { {\"\", \"date\", 5, 0},").is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use zbus::{blocking::Connection, zvariant::Value};
+
+/// Sends a desktop notification when a segment starts failing
+#[derive(Debug, Clone)]
+pub(crate) struct FailureNotifier {
+    /// number of consecutive failures until a notification is sent
+    threshold: u32,
+    /// minimal time between two notifications
+    interval: Duration,
+
+    failures: u32,
+    /// whether the current failures were reported
+    notified: bool,
+    last_notification: Option<Instant>,
+}
+
+impl FailureNotifier {
+    pub fn new(threshold: u32, interval: Duration) -> Self {
+        Self {
+            threshold,
+            interval,
+            failures: 0,
+            notified: false,
+            last_notification: None,
+        }
+    }
+
+    pub fn success(&mut self) {
+        self.failures = 0;
+        self.notified = false;
+    }
+
+    pub fn failure(&mut self, message: &str) {
+        self.failures += 1;
+        if self.should_notify() {
+            self.notified = true;
+            self.last_notification = Some(Instant::now());
+            // sending waits for the notification daemon
+            let message = message.to_string();
+            blocking::unblock(move || {
+                if let Err(e) = send_notification("dwmblocksrs: segment failed", &message) {
+                    warn!("error sending notification: {e}");
+                }
+            })
+            .detach();
+        }
+    }
+
+    fn should_notify(&self) -> bool {
+        // only notify once while the segment is failing, later if it was too early
+        !self.notified
+            && self.failures >= self.threshold.max(1)
+            && self
+                .last_notification
+                .is_none_or(|last| last.elapsed() >= self.interval)
+    }
+}
+
+fn send_notification(summary: &str, body: &str) -> zbus::Result<()> {
+    let connection = Connection::session()?;
+    connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            "dwmblocksrs",
+            0u32,
+            "",
+            summary,
+            body,
+            Vec::<&str>::new(),
+            HashMap::<&str, Value>::new(),
+            -1i32,
+        ),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold() {
+        let mut notifier = FailureNotifier::new(2, Duration::ZERO);
+        notifier.failures = 1;
+        assert!(!notifier.should_notify());
+        notifier.failures = 2;
+        assert!(notifier.should_notify());
+        // still failing, but already notified
+        notifier.notified = true;
+        notifier.failures = 3;
+        assert!(!notifier.should_notify());
+        notifier.success();
+        notifier.failures = 2;
+        assert!(notifier.should_notify());
+    }
+
+    #[test]
+    fn rate_limit() {
+        let mut notifier = FailureNotifier::new(1, Duration::from_secs(3600));
+        notifier.failures = 1;
+        notifier.last_notification = Some(Instant::now());
+        assert!(!notifier.should_notify());
+        // the failures are reported once the interval has passed
+        notifier.failures = 5;
+        notifier.last_notification = Some(Instant::now() - Duration::from_secs(3600));
+        assert!(notifier.should_notify());
+    }
+}
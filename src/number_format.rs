@@ -0,0 +1,166 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+lazy_static! {
+    static ref NUMBER: Regex = Regex::new(r"-?\d+(\.\d+)?").unwrap();
+}
+
+/// the first number in the text, e.g. 42 in `vol 42%`
+pub(crate) fn first_number(text: &str) -> Option<f64> {
+    NUMBER.find(text)?.as_str().parse().ok()
+}
+
+/// How numbers are displayed
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NumberFormat {
+    /// the number of decimal places, by default one for small scaled numbers and none otherwise
+    pub precision: Option<usize>,
+    /// put between groups of three digits, e.g. `,`
+    pub thousands_separator: String,
+    /// scales numbers of bytes to a unit
+    pub scale: Scale,
+    /// only the first letter of the unit, e.g. `K` instead of `KiB`
+    pub short: bool,
+}
+
+/// The units that numbers of bytes are scaled to
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scale {
+    /// the number is not scaled
+    #[default]
+    None,
+    /// powers of 1000: kB, MB, GB, ...
+    Si,
+    /// powers of 1024: KiB, MiB, GiB, ...
+    Iec,
+}
+
+impl Scale {
+    fn base(self) -> f64 {
+        match self {
+            Scale::None => f64::INFINITY,
+            Scale::Si => 1000.0,
+            Scale::Iec => 1024.0,
+        }
+    }
+
+    fn units(self, short: bool) -> &'static [&'static str] {
+        match (self, short) {
+            (Scale::None, _) => &[""],
+            (Scale::Si, false) => &["B", "kB", "MB", "GB", "TB", "PB"],
+            (Scale::Si, true) => &["B", "k", "M", "G", "T", "P"],
+            (Scale::Iec, false) => &["B", "KiB", "MiB", "GiB", "TiB", "PiB"],
+            (Scale::Iec, true) => &["B", "K", "M", "G", "T", "P"],
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Formats the text if it is a number, other texts are returned unchanged
+    pub fn apply(&self, text: String) -> String {
+        match text.trim().parse::<f64>() {
+            Ok(number) if number.is_finite() => self.format(number),
+            _ => text,
+        }
+    }
+
+    pub fn format(&self, number: f64) -> String {
+        let units = self.scale.units(self.short);
+        let mut value = number;
+        let mut unit = 0;
+        while value.abs() >= self.scale.base() && unit < units.len() - 1 {
+            value /= self.scale.base();
+            unit += 1;
+        }
+
+        let text = match self.precision {
+            Some(precision) => format!("{value:.precision$}"),
+            None if self.scale == Scale::None => value.to_string(),
+            None if unit == 0 || value.abs() >= 10.0 => format!("{value:.0}"),
+            None => format!("{value:.1}"),
+        };
+        format!(
+            "{}{}",
+            group_thousands(&text, &self.thousands_separator),
+            units[unit]
+        )
+    }
+}
+
+/// puts the separator between groups of three digits of the integer part
+fn group_thousands(number: &str, separator: &str) -> String {
+    if separator.is_empty() {
+        return number.into();
+    }
+
+    let (sign, number) = match number.strip_prefix('-') {
+        Some(number) => ("-", number),
+        None => ("", number),
+    };
+    let (integer, fraction) = match number.find('.') {
+        Some(dot) => number.split_at(dot),
+        None => (number, ""),
+    };
+
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    format!("{sign}{grouped}{fraction}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unscaled() {
+        let format = NumberFormat::default();
+        assert_eq!(format.apply("42".into()), "42");
+        assert_eq!(format.apply("3.25\n".into()), "3.25");
+        assert_eq!(format.apply("on".into()), "on");
+
+        let format = NumberFormat {
+            precision: Some(1),
+            thousands_separator: ",".into(),
+            ..Default::default()
+        };
+        assert_eq!(format.apply("1234567.89".into()), "1,234,567.9");
+        assert_eq!(format.apply("-123456".into()), "-123,456.0");
+        assert_eq!(format.apply("123".into()), "123.0");
+    }
+
+    #[test]
+    fn first() {
+        assert_eq!(first_number("vol -4.5% 3"), Some(-4.5));
+        assert_eq!(first_number("off"), None);
+    }
+
+    #[test]
+    fn scaled() {
+        let iec = NumberFormat {
+            scale: Scale::Iec,
+            ..Default::default()
+        };
+        assert_eq!(iec.format(512.0), "512B");
+        assert_eq!(iec.format(1536.0), "1.5KiB");
+        assert_eq!(iec.format(50.0 * 1024.0 * 1024.0), "50MiB");
+
+        let si = NumberFormat {
+            scale: Scale::Si,
+            short: true,
+            precision: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(si.format(1234.0), "1.23k");
+        assert_eq!(si.format(2e15), "2.00P");
+        assert_eq!(si.format(3e18), "3000.00P");
+    }
+}
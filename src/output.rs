@@ -0,0 +1,81 @@
+pub mod file;
+pub mod i3bar;
+pub mod observer;
+pub mod stdout;
+pub mod xroot;
+
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::color::{Color, ColorFormat};
+
+/// Where the status is displayed
+#[derive(Debug, Clone, Default)]
+pub enum OutputConfig {
+    /// the name of the X root window, which dwm displays
+    #[default]
+    XRoot,
+    /// the JSON protocol of i3bar and swaybar on stdout
+    I3bar {
+        /// the hex colors (`#rrggbb`) of the color numbers
+        colors: HashMap<u8, String>,
+        /// the colors in the texts of the segments
+        color_format: ColorFormat,
+    },
+    /// one line of text per update on stdout, e.g. for the bars of dwl
+    Stdout,
+    /// the text in a file or a FIFO
+    File { path: PathBuf },
+}
+
+/// A segment as displayed by the outputs
+#[derive(Debug)]
+pub(crate) struct Block<'a> {
+    pub name: Option<&'a str>,
+    pub text: &'a str,
+    pub color: Color,
+}
+
+/// The status in the forms the different outputs need
+#[derive(Debug, Default)]
+pub(crate) struct Status<'a> {
+    /// the joined texts of all visible segments
+    pub text: &'a str,
+    /// the visible segments
+    pub blocks: Vec<Block<'a>>,
+    /// the text for each monitor, if segments are only displayed on some monitors
+    pub monitors: Option<Vec<String>>,
+}
+
+/// Outputs are moved into the task that runs the status bar
+pub(crate) trait Output: Send {
+    /// Displays the status, called whenever it changed
+    fn set_status(&mut self, status: &Status);
+
+    /// Displays again what was displayed before the status bar started, called on exit
+    fn restore(&mut self) {}
+}
+
+impl OutputConfig {
+    /// Uses the JSON protocol of swaybar in Wayland sessions and the X root window otherwise
+    pub fn detect(colors: HashMap<u8, String>, color_format: ColorFormat) -> Self {
+        match std::env::var_os("WAYLAND_DISPLAY") {
+            Some(_) => Self::I3bar {
+                colors,
+                color_format,
+            },
+            None => Self::XRoot,
+        }
+    }
+
+    pub(crate) fn create(&self) -> Result<Box<dyn Output>, String> {
+        Ok(match self {
+            Self::XRoot => Box::new(xroot::XRoot::new()?),
+            Self::I3bar {
+                colors,
+                color_format,
+            } => Box::new(i3bar::I3bar::new(colors.clone(), *color_format)),
+            Self::Stdout => Box::new(stdout::Stdout),
+            Self::File { path } => Box::new(file::File::new(path.clone())),
+        })
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::{FileTypeExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+
+use super::{Output, Status};
+
+/// Writes the status as a line to a file or a FIFO.
+/// Files are replaced atomically, so that readers never see a partial status.
+pub(crate) struct File {
+    path: PathBuf,
+}
+
+impl File {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+fn is_fifo(path: &Path) -> bool {
+    fs::metadata(path)
+        .map(|metadata| metadata.file_type().is_fifo())
+        .unwrap_or(false)
+}
+
+fn write_fifo(path: &Path, text: &str) -> io::Result<()> {
+    // without a reader, opening fails instead of blocking
+    let mut fifo = match OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+    {
+        Err(e) if e.raw_os_error() == Some(libc::ENXIO) => {
+            debug!("fifo '{}' has no reader", path.display());
+            return Ok(());
+        }
+        fifo => fifo?,
+    };
+    writeln!(fifo, "{text}")
+}
+
+fn write_file(path: &Path, text: &str) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, format!("{text}\n"))?;
+    fs::rename(&temporary, path)
+}
+
+impl Output for File {
+    fn set_status(&mut self, status: &Status) {
+        let result = match is_fifo(&self.path) {
+            true => write_fifo(&self.path, status.text),
+            false => write_file(&self.path, status.text),
+        };
+        if let Err(e) = result {
+            warn!(
+                "error writing the status to '{}': {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file() {
+        let path = std::env::temp_dir().join(format!("dwmblocksrs-output-{}", std::process::id()));
+        let mut file = File::new(path.clone());
+        for text in ["first", "second"] {
+            file.set_status(&Status {
+                text,
+                ..Default::default()
+            });
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use log::warn;
+use serde_json::{json, Value};
+
+use super::{Block, Output, Status};
+use crate::color::{strip_colors, Color, ColorFormat};
+
+/// Writes the status to stdout in the JSON protocol of i3bar and swaybar.
+/// Every segment becomes a block, the bar draws no separators between them.
+pub(crate) struct I3bar {
+    colors: HashMap<u8, String>,
+    color_format: ColorFormat,
+    header_written: bool,
+}
+
+impl I3bar {
+    pub fn new(colors: HashMap<u8, String>, color_format: ColorFormat) -> Self {
+        Self {
+            colors,
+            color_format,
+            header_written: false,
+        }
+    }
+
+    fn block(&self, block: &Block) -> Value {
+        let mut value = json!({
+            // the colors for dwm are no text
+            "full_text": strip_colors(block.text, self.color_format),
+            "separator": false,
+            "separator_block_width": 0,
+        });
+        if let Some(name) = block.name {
+            value["name"] = name.into();
+        }
+        match block.color {
+            Color::Colored(c) => match self.colors.get(&c) {
+                Some(color) => value["color"] = color.as_str().into(),
+                None => warn!("color {c} has no hex value for i3bar"),
+            },
+            Color::Rgb(..) => value["color"] = json!(block.color.hex()),
+            Color::Uncolored => {}
+        }
+        value
+    }
+
+    fn write(&mut self, blocks: &[Block]) -> io::Result<()> {
+        let mut stdout = io::stdout().lock();
+        if !self.header_written {
+            // the status is an infinite array of arrays of blocks
+            writeln!(stdout, "{}", json!({ "version": 1 }))?;
+            writeln!(stdout, "[")?;
+            self.header_written = true;
+        }
+
+        let blocks = blocks
+            .iter()
+            .filter(|block| !block.text.is_empty())
+            .map(|block| self.block(block))
+            .collect::<Vec<_>>();
+        writeln!(stdout, "{},", Value::Array(blocks))?;
+        stdout.flush()
+    }
+}
+
+impl Output for I3bar {
+    fn set_status(&mut self, status: &Status) {
+        if let Err(e) = self.write(&status.blocks) {
+            warn!("error writing the status to stdout: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks() {
+        let i3bar = I3bar::new(
+            HashMap::from([(2, "#00ff00".into())]),
+            ColorFormat::Statuscolors,
+        );
+        let block = Block {
+            name: Some("volume"),
+            text: "\x02 50%\x01",
+            color: Color::Colored(2),
+        };
+        assert_eq!(
+            i3bar.block(&block),
+            json!({
+                "name": "volume",
+                "full_text": " 50%",
+                "color": "#00ff00",
+                "separator": false,
+                "separator_block_width": 0,
+            })
+        );
+    }
+}
@@ -0,0 +1,10 @@
+use super::{Output, Status};
+
+/// Passes every new status to a function of the embedding program
+pub(crate) struct Observer(pub Box<dyn Fn(&str) + Send>);
+
+impl Output for Observer {
+    fn set_status(&mut self, status: &Status) {
+        (self.0)(status.text)
+    }
+}
@@ -0,0 +1,17 @@
+use std::io::{self, Write};
+
+use log::warn;
+
+use super::{Output, Status};
+
+/// Prints every new status as a line to stdout
+pub(crate) struct Stdout;
+
+impl Output for Stdout {
+    fn set_status(&mut self, status: &Status) {
+        let mut stdout = io::stdout().lock();
+        if let Err(e) = writeln!(stdout, "{}", status.text).and_then(|_| stdout.flush()) {
+            warn!("error writing the status to stdout: {e}");
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use std::{
+    ffi::{c_void, CStr, CString},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+    time::{Duration, Instant},
+};
+
+use log::{debug, info, warn};
+use x11::xlib::{
+    BadWindow, Display, Window, XCloseDisplay, XConnectionNumber, XDefaultScreen, XErrorEvent,
+    XFetchName, XFree, XGetErrorText, XInitThreads, XOpenDisplay, XRootWindow, XScreenCount,
+    XSetErrorHandler, XSetIOErrorHandler, XStoreName, XSync,
+};
+
+use super::{Output, Status};
+
+extern "C" {
+    // since libX11 1.7, the x11 crate doesn't have it yet
+    fn XSetIOErrorExitHandler(
+        display: *mut Display,
+        handler: Option<unsafe extern "C" fn(*mut Display, *mut c_void)>,
+        user_data: *mut c_void,
+    );
+}
+
+/// the delay before the first attempt to reconnect, it doubles with every failed attempt
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A connection to the X server.
+/// Once it is lost, Xlib calls on it fail instead of exiting the process.
+pub(crate) struct Connection {
+    pub display: *mut Display,
+    /// the root window of the default screen
+    pub root: Window,
+    /// set by Xlib when it notices that the connection is lost
+    lost: Box<AtomicBool>,
+}
+
+impl Connection {
+    /// Opens the display with the name, or the one of `DISPLAY`
+    pub fn open(name: Option<&CStr>) -> Option<Self> {
+        init_xlib();
+        unsafe {
+            let display = XOpenDisplay(name.map_or(ptr::null(), CStr::as_ptr));
+            if display.is_null() {
+                return None;
+            }
+            let lost = Box::new(AtomicBool::new(false));
+            XSetIOErrorExitHandler(
+                display,
+                Some(connection_lost),
+                &*lost as *const AtomicBool as *mut c_void,
+            );
+            Some(Self {
+                display,
+                root: XRootWindow(display, XDefaultScreen(display)),
+                lost,
+            })
+        }
+    }
+
+    /// whether the connection was lost, noticed by Xlib or by checking its socket
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed) || !is_alive(self.display)
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // safe on lost connections too, thanks to the exit handler
+        unsafe {
+            XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// Sets the name of the root window, which dwm displays as status.
+/// With several X screens, each monitor's status is set on the root window of its screen.
+/// If the connection to the X server is lost, it is opened again on the next update.
+pub(crate) struct XRoot {
+    /// the display to connect to, `DISPLAY` if None
+    display_name: Option<CString>,
+    /// None while there is no connection
+    connection: Option<Connection>,
+    /// the names of the root windows of all screens before the status bar started
+    original_names: Option<Vec<Option<String>>>,
+    /// when the next attempt to connect is made and the delay after it
+    next_attempt: Instant,
+    backoff: Duration,
+    /// displayed again after reconnecting
+    last_status: Option<(String, Option<Vec<String>>)>,
+    /// whether the texts of the monitors without a screen were reported as discarded
+    warned_screens: bool,
+}
+
+// the display is only used by the task that owns the output
+unsafe impl Send for XRoot {}
+
+impl XRoot {
+    pub fn new() -> Result<Self, String> {
+        if std::env::var_os("DISPLAY").is_none() {
+            return Err("DISPLAY is not set, dwmblocksrs needs a running X server".into());
+        }
+        let mut xroot = Self::with_display(None);
+        if !xroot.connect() {
+            warn!("error opening the X display, trying again later");
+        }
+        Ok(xroot)
+    }
+
+    fn with_display(display_name: Option<CString>) -> Self {
+        Self {
+            display_name,
+            connection: None,
+            original_names: None,
+            next_attempt: Instant::now(),
+            backoff: MIN_BACKOFF,
+            last_status: None,
+            warned_screens: false,
+        }
+    }
+
+    /// makes sure that there is a connection, returns whether there is one
+    fn connect(&mut self) -> bool {
+        if let Some(connection) = &self.connection {
+            if !connection.is_lost() {
+                return true;
+            }
+            warn!("lost the connection to the X server");
+            self.connection = None;
+        }
+
+        if Instant::now() < self.next_attempt {
+            return false;
+        }
+        let Some(connection) = Connection::open(self.display_name.as_deref()) else {
+            self.next_attempt = Instant::now() + self.backoff;
+            self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+            return false;
+        };
+
+        if self.last_status.is_some() {
+            info!("reconnected to the X server");
+        }
+        self.backoff = MIN_BACKOFF;
+        if self.original_names.is_none() {
+            let screens = unsafe { XScreenCount(connection.display) };
+            self.original_names = Some(
+                (0..screens)
+                    .map(|screen| {
+                        fetch_name(connection.display, unsafe {
+                            XRootWindow(connection.display, screen)
+                        })
+                    })
+                    .collect(),
+            );
+        }
+        self.connection = Some(connection);
+        true
+    }
+
+    fn store_status(connection: &Connection, text: &str, monitors: Option<&[String]>) {
+        let display = connection.display;
+        match monitors {
+            Some(monitors) => {
+                let screens = unsafe { XScreenCount(display) } as usize;
+                for (screen, text) in monitors.iter().enumerate().take(screens) {
+                    store_name(
+                        display,
+                        unsafe { XRootWindow(display, screen as i32) },
+                        text,
+                    );
+                }
+            }
+            None => store_name(display, connection.root, text),
+        }
+        unsafe {
+            XSync(display, 0);
+        }
+    }
+}
+
+impl Output for XRoot {
+    fn set_status(&mut self, status: &Status) {
+        self.last_status = Some((status.text.to_string(), status.monitors.clone()));
+        if self.connect() {
+            let connection = self.connection.as_ref().unwrap();
+            if let Some(monitors) = &status.monitors {
+                let screens = unsafe { XScreenCount(connection.display) } as usize;
+                if monitors.len() > screens && !self.warned_screens {
+                    warn!(
+                        "there are only {screens} X screens for {} monitors, \
+                         the texts of the other monitors are discarded",
+                        monitors.len()
+                    );
+                    self.warned_screens = true;
+                }
+            }
+            Self::store_status(connection, status.text, status.monitors.as_deref());
+        }
+    }
+
+    fn restore(&mut self) {
+        if !self.connect() {
+            return;
+        }
+        let display = self.connection.as_ref().unwrap().display;
+        for (screen, name) in self.original_names.iter().flatten().enumerate() {
+            let window = unsafe { XRootWindow(display, screen as i32) };
+            store_name(display, window, name.as_deref().unwrap_or_default());
+        }
+        unsafe {
+            XSync(display, 0);
+        }
+    }
+}
+
+fn fetch_name(display: *mut Display, window: Window) -> Option<String> {
+    let mut name = ptr::null_mut();
+    unsafe {
+        if XFetchName(display, window, &mut name) == 0 || name.is_null() {
+            return None;
+        }
+        let text = CStr::from_ptr(name).to_string_lossy().into_owned();
+        XFree(name.cast());
+        Some(text)
+    }
+}
+
+fn store_name(display: *mut Display, window: Window, text: &str) {
+    // https://github.com/hugglesfox/statusd/blob/main/src/xsetroot.rs
+    // https://github.com/KJ002/simple_status/blob/main/src/status.rs
+
+    // a NUL byte would end the name early
+    let c_str = CString::new(text.replace('\0', "")).unwrap();
+    unsafe {
+        XStoreName(display, window, c_str.as_ptr());
+    }
+}
+
+/// checks whether the X server closed the connection, without asking Xlib
+fn is_alive(display: *mut Display) -> bool {
+    let fd = unsafe { XConnectionNumber(display) };
+    let mut poll = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    if unsafe { libc::poll(&mut poll, 1, 0) } <= 0 {
+        return true;
+    }
+    if poll.revents & (libc::POLLHUP | libc::POLLERR) != 0 {
+        return false;
+    }
+    // a readable connection without data was closed
+    let mut byte = 0u8;
+    let read = unsafe {
+        libc::recv(
+            fd,
+            (&mut byte as *mut u8).cast(),
+            1,
+            libc::MSG_PEEK | libc::MSG_DONTWAIT,
+        )
+    };
+    read != 0
+}
+
+/// Makes Xlib usable from the threads that open their own displays.
+/// Errors are logged and lost connections are noticed,
+/// instead of exiting like the default handlers of Xlib do.
+fn init_xlib() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| unsafe {
+        XInitThreads();
+        XSetErrorHandler(Some(log_error));
+        XSetIOErrorHandler(Some(log_io_error));
+    });
+}
+
+unsafe extern "C" fn log_error(display: *mut Display, event: *mut XErrorEvent) -> i32 {
+    // windows can be destroyed at any time, e.g. while the active window is watched
+    if (*event).error_code == BadWindow {
+        debug!("ignoring an X error about a destroyed window");
+        return 0;
+    }
+    let mut text = [0 as std::os::raw::c_char; 256];
+    XGetErrorText(
+        display,
+        (*event).error_code as i32,
+        text.as_mut_ptr(),
+        text.len() as i32,
+    );
+    warn!(
+        "X error: {} (request {})",
+        CStr::from_ptr(text.as_ptr()).to_string_lossy(),
+        (*event).request_code
+    );
+    0
+}
+
+/// called first when a connection is lost, the exit handler of the display is called after it
+unsafe extern "C" fn log_io_error(_: *mut Display) -> i32 {
+    warn!("the connection to the X server broke");
+    0
+}
+
+/// called instead of exiting, the calls on the display fail from now on
+unsafe extern "C" fn connection_lost(_: *mut Display, lost: *mut c_void) {
+    (*lost.cast::<AtomicBool>()).store(true, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::Shutdown,
+        os::unix::net::{UnixListener, UnixStream},
+        sync::mpsc::{self, Sender},
+        thread,
+    };
+
+    use super::*;
+
+    /// the connection setup of a server with one screen and one visual, little endian
+    fn setup() -> Vec<u8> {
+        let mut data = Vec::new();
+        // release, resource id base and mask, motion buffer size
+        for value in [0u32, 0x0020_0000, 0x001f_ffff, 0] {
+            data.extend(value.to_le_bytes());
+        }
+        // vendor length, maximum request length
+        data.extend(4u16.to_le_bytes());
+        data.extend(u16::MAX.to_le_bytes());
+        // screens, formats, byte orders, scanline unit and pad, keycodes
+        data.extend([1, 1, 0, 0, 32, 32, 8, 255, 0, 0, 0, 0]);
+        data.extend(b"test");
+        // the pixmap format
+        data.extend([24, 32, 32, 0, 0, 0, 0, 0]);
+        // the screen: root, colormap, white, black, input masks
+        for value in [0x100u32, 0x20, 0xffffff, 0, 0] {
+            data.extend(value.to_le_bytes());
+        }
+        // size in pixels and millimeters, installed colormaps
+        for value in [1920u16, 1080, 500, 300, 1, 1] {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend(0x21u32.to_le_bytes());
+        // backing stores, save unders, root depth, depths
+        data.extend([0, 0, 24, 1]);
+        // the depth with one true color visual
+        data.extend([24, 0, 1, 0, 0, 0, 0, 0]);
+        data.extend(0x21u32.to_le_bytes());
+        data.extend([4, 8, 0, 1]);
+        for value in [0xff0000u32, 0xff00, 0xff, 0] {
+            data.extend(value.to_le_bytes());
+        }
+
+        let mut setup = vec![1, 0, 11, 0, 0, 0];
+        setup.extend((data.len() as u16 / 4).to_le_bytes());
+        setup.extend(data);
+        setup
+    }
+
+    /// A fake X server that answers the requests with replies of empty values,
+    /// it sends the names that are stored
+    fn serve(mut stream: UnixStream, names: Sender<String>) -> std::io::Result<()> {
+        let mut header = [0; 12];
+        stream.read_exact(&mut header)?;
+        let padded = |length: u16| (length as usize).div_ceil(4) * 4;
+        let authorization = padded(u16::from_le_bytes([header[6], header[7]]))
+            + padded(u16::from_le_bytes([header[8], header[9]]));
+        stream.read_exact(&mut vec![0; authorization])?;
+        stream.write_all(&setup())?;
+
+        let mut sequence = 0u16;
+        loop {
+            let mut request = [0; 4];
+            stream.read_exact(&mut request)?;
+            sequence = sequence.wrapping_add(1);
+            let mut body = vec![0; u16::from_le_bytes([request[2], request[3]]) as usize * 4 - 4];
+            stream.read_exact(&mut body)?;
+            match request[0] {
+                // ChangeProperty
+                18 => {
+                    let length = u32::from_le_bytes(body[16..20].try_into().unwrap()) as usize;
+                    let name = String::from_utf8_lossy(&body[20..20 + length]);
+                    names.send(name.into_owned()).unwrap();
+                }
+                // InternAtom, GetProperty, GetInputFocus and QueryExtension have replies
+                16 | 20 | 43 | 98 => {
+                    let mut reply = [0; 32];
+                    reply[0] = 1;
+                    reply[2..4].copy_from_slice(&sequence.to_le_bytes());
+                    stream.write_all(&reply)?;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn lost_connection() {
+        let number = 4000 + std::process::id() % 1000;
+        std::fs::create_dir_all("/tmp/.X11-unix").unwrap();
+        let path = format!("/tmp/.X11-unix/X{number}");
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let (names_sender, names) = mpsc::channel();
+        let (streams_sender, streams) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                streams_sender.send(stream.try_clone().unwrap()).unwrap();
+                let names = names_sender.clone();
+                thread::spawn(move || serve(stream, names));
+            }
+        });
+        let next_name = || names.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let display = CString::new(format!(":{number}")).unwrap();
+        let mut xroot = XRoot::with_display(Some(display));
+        let status = |text| Status {
+            text,
+            ..Default::default()
+        };
+        xroot.set_status(&status("first"));
+        assert_eq!(next_name(), "first");
+
+        // the server goes away, Xlib notices it while storing the status without exiting
+        let stream = streams.recv().unwrap();
+        stream.shutdown(Shutdown::Both).unwrap();
+        let connection = xroot.connection.as_ref().unwrap();
+        XRoot::store_status(connection, "lost", None);
+        assert!(connection.lost.load(Ordering::Relaxed));
+
+        xroot.set_status(&status("second"));
+        assert_eq!(next_name(), "second");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
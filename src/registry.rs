@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+
+use crate::segments::SegmentKind;
+
+type Factory = Box<dyn Fn(serde_yaml::Value) -> Result<Box<dyn SegmentKind>, String> + Send + Sync>;
+
+/// Custom segment kinds that can be used in the config file, like the built-in ones:
+/// ```yaml
+///     - custom: "counter"
+///       # deserialized into the options of the factory
+///       options: { start: 5 }
+/// ```
+#[derive(Default)]
+pub struct Registry {
+    factories: HashMap<String, Factory>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the factory under the name.
+    /// It creates the segment kind from the `options` of the segment.
+    pub fn register<T: DeserializeOwned>(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn(T) -> Result<Box<dyn SegmentKind>, String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let name = name.into();
+        let error_name = name.clone();
+        self.factories.insert(
+            name,
+            Box::new(move |options| {
+                let options = serde_yaml::from_value(options)
+                    .map_err(|e| format!("invalid options for '{error_name}': {e}"))?;
+                factory(options)
+            }),
+        );
+        self
+    }
+
+    pub(crate) fn create(
+        &self,
+        name: &str,
+        options: serde_yaml::Value,
+    ) -> Result<Box<dyn SegmentKind>, String> {
+        let factory = self
+            .factories
+            .get(name)
+            .ok_or_else(|| format!("there is no custom segment kind named '{name}'"))?;
+        factory(options)
+    }
+}
+
+impl std::fmt::Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.factories.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segments::constant::Constant;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Options {
+        text: String,
+    }
+
+    #[test]
+    fn create() {
+        let mut registry = Registry::new();
+        registry.register("repeat", |options: Options| {
+            Ok(Box::new(Constant::new(options.text.repeat(2))))
+        });
+
+        let options = serde_yaml::from_str("text: ab").unwrap();
+        let mut kind = registry.create("repeat", options).unwrap();
+        assert_eq!(kind.compute_value(), "abab");
+
+        assert!(registry.create("repeat", serde_yaml::Value::Null).is_err());
+        assert!(registry.create("other", serde_yaml::Value::Null).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::str::FromStr;
+
+use crate::local_time::local_time;
+
+/// When a segment is updated, in the format of crontab: minute, hour, day of month, month, day of week.
+/// Each field is `*`, a number, a range like `8-18` or a list of them like `1,15`,
+/// optionally with a step like `*/5`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    /// like cron, a day matches either field if both are restricted
+    any_day: bool,
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let fields = text.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "'{text}' needs 5 fields: minute, hour, day of month, month and day of week"
+            ));
+        };
+
+        let mut weekdays = parse_field(weekdays, 0, 7)?;
+        // both 0 and 7 are sunday
+        weekdays[0] |= weekdays[7];
+        weekdays.truncate(7);
+
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays,
+            any_day: days != "*" && fields[4] != "*",
+        })
+    }
+}
+
+/// the values of the field as flags, indexed by the value
+fn parse_field(field: &str, min: usize, max: usize) -> Result<Vec<bool>, String> {
+    let mut values = vec![false; max + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, parse_number(step)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (parse_number(start)?, parse_number(end)?),
+            None if step > 1 => (parse_number(range)?, max),
+            None => {
+                let value = parse_number(range)?;
+                (value, value)
+            }
+        };
+        if start < min || end > max || start > end || step == 0 {
+            return Err(format!(
+                "'{part}' is not within {min}-{max} in the schedule"
+            ));
+        }
+        for value in (start..=end).step_by(step) {
+            values[value] = true;
+        }
+    }
+    Ok(values)
+}
+
+fn parse_number(text: &str) -> Result<usize, String> {
+    text.parse()
+        .map_err(|_| format!("'{text}' is no number in the schedule"))
+}
+
+impl Schedule {
+    /// The unix timestamp of the first matching minute after the timestamp,
+    /// None if there is none in the next years, e.g. on February 30th
+    pub fn next_after(&self, timestamp: i64) -> Option<i64> {
+        // the start of the next minute
+        let mut time = timestamp - timestamp.rem_euclid(60) + 60;
+        let end = timestamp + 5 * 366 * 24 * 3600;
+
+        while time < end {
+            let tm = local_time(time);
+            let seconds_of_hour = (tm.tm_min * 60 + tm.tm_sec) as i64;
+            if !self.months[tm.tm_mon as usize + 1] || !self.matches_day(&tm) {
+                let seconds_of_day = tm.tm_hour as i64 * 3600 + seconds_of_hour;
+                time += 24 * 3600 - seconds_of_day;
+            } else if !self.hours[tm.tm_hour as usize] {
+                time += 3600 - seconds_of_hour;
+            } else if !self.minutes[tm.tm_min as usize] {
+                time += 60 - tm.tm_sec as i64;
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, tm: &libc::tm) -> bool {
+        let day = self.days[tm.tm_mday as usize];
+        let weekday = self.weekdays[tm.tm_wday as usize];
+        match self.any_day {
+            true => day || weekday,
+            false => day && weekday,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(text: &str) -> Schedule {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let s = schedule("*/15 8-18/5 1,15 * 1-5");
+        let set = |values: &[bool]| {
+            values
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| **v)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(set(&s.minutes), [0, 15, 30, 45]);
+        assert_eq!(set(&s.hours), [8, 13, 18]);
+        assert_eq!(set(&s.days), [1, 15]);
+        assert_eq!(set(&s.months).len(), 12);
+        assert_eq!(set(&s.weekdays), [1, 2, 3, 4, 5]);
+        assert!(s.any_day);
+        assert_eq!(set(&schedule("0 0 * * 7").weekdays), [0]);
+
+        assert!("* * * *".parse::<Schedule>().is_err());
+        assert!("60 * * * *".parse::<Schedule>().is_err());
+        assert!("*/0 * * * *".parse::<Schedule>().is_err());
+        assert!("a * * * *".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn next() {
+        // Monday, 2024-01-01 12:34:56 UTC
+        let now = 1704112496;
+        // the times are in local time
+        if local_time(now).tm_gmtoff != 0 {
+            return;
+        }
+        let minute = |s: &str| schedule(s).next_after(now).map(|t| t - now);
+        assert_eq!(minute("* * * * *"), Some(4));
+        assert_eq!(minute("*/10 * * * *"), Some(4 + 5 * 60));
+        assert_eq!(minute("0 8-18 * * 1-5"), Some(4 + 25 * 60));
+        // Saturday, 2024-01-06 08:00
+        assert_eq!(schedule("0 8 * * 6").next_after(now), Some(1704528000));
+        assert_eq!(minute("0 0 30 2 *"), None);
+    }
+}
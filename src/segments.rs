@@ -1,18 +1,71 @@
+pub mod active_window;
+pub mod astro;
+pub mod bar;
+pub mod battery;
+pub mod bluetooth;
 pub mod constant;
+pub mod context;
+pub mod cpu;
+pub mod dbus;
+pub mod disk;
+pub mod dunst;
+pub mod file;
+pub mod graph;
+pub mod group;
+pub mod idle_inhibit;
+pub mod load;
+#[cfg(feature = "lua")]
+pub mod lua;
+pub mod maildir;
+pub mod manual;
+pub mod mpd;
+pub mod persistent;
+#[cfg(feature = "plugins")]
+pub mod plugin;
 pub mod program_output;
+pub mod scroll;
+pub mod stream;
+pub mod sysfs;
+pub mod sysstat;
+pub mod tasks;
+pub mod ticker;
+pub mod timer;
+pub mod updates;
+pub mod upower;
+pub mod views;
+pub mod volume;
+pub mod wifi;
 
-use std::time::{Duration, Instant};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
-use async_std::channel::Sender;
+use async_std::channel::{Receiver, Sender};
 use async_std::future::timeout;
 use async_std::stream::StreamExt;
+use async_std::task;
+use futures_lite::{future, Future, Stream};
 use lazy_static::lazy_static;
 use log::warn;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use signal_hook_async_std::Signals;
 use std::fmt::Debug;
 
-use crate::color::{Colorable, SegmentColoring};
+use crate::click;
+use crate::color::{Color, ColorFormat, Colorable, SegmentColoring};
+use crate::command_limit;
 use crate::config::Configuration;
+use crate::local_time::{self, local_time};
+use crate::menu::Menu;
+use crate::notification::FailureNotifier;
+use crate::number_format::{first_number, NumberFormat};
+use crate::schedule::Schedule;
+use crate::status_bar::SegmentInfo;
+use crate::template::fill_template;
+use crate::template::{pad, replace_placeholders, truncate, Align};
+use crate::transform::Transform;
 use crate::SegmentId;
 
 lazy_static! {
@@ -20,46 +73,443 @@ lazy_static! {
     static ref SIGRTMAX: i32 = libc::SIGRTMAX();
 }
 
+/// How signals are written in the config: an offset to SIGRTMIN or a name of `signal_names`
+#[derive(JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+pub(crate) enum SignalValue {
+    Offset(u32),
+    Name(String),
+}
+
+/// The largest offset to SIGRTMIN that a signal can have
+pub(crate) fn max_signal_offset() -> u32 {
+    (*SIGRTMAX - *SIGRTMIN) as u32
+}
+
+/// The status area of dwm's extrabar/dualstatus patches in which a segment is displayed
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Area {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// Where something that is rendered from the value, e.g. a graph, is displayed
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Placement {
+    Before,
+    #[default]
+    After,
+    /// only the rendered text is displayed
+    Instead,
+}
+
+impl Placement {
+    fn place(self, rendered: String, value: String) -> String {
+        match self {
+            Placement::Before => format!("{rendered} {value}"),
+            Placement::After => format!("{value} {rendered}"),
+            Placement::Instead => rendered,
+        }
+    }
+}
+
+/// The wall-clock unit that periodic updates are aligned to
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlignTo {
+    Second,
+    Minute,
+    Hour,
+}
+
+impl AlignTo {
+    fn seconds(self) -> u64 {
+        match self {
+            AlignTo::Second => 1,
+            AlignTo::Minute => 60,
+            AlignTo::Hour => 3600,
+        }
+    }
+
+    /// The time from `now` until the next update.
+    /// Updates happen at the multiples of the interval in local time,
+    /// which is rounded up to a multiple of the unit, e.g. 60s intervals at :00.
+    fn until_next(self, interval: Duration, now: Duration, utc_offset: i64) -> Duration {
+        let unit = self.seconds();
+        let period = interval.as_secs().max(1).div_ceil(unit) * unit;
+        let local = now.as_secs_f64() + utc_offset as f64;
+        let elapsed = local.rem_euclid(period as f64);
+        Duration::from_secs_f64(period as f64 - elapsed)
+    }
+}
+
+/// The side of the value on which the icon is displayed
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IconPosition {
+    #[default]
+    Left,
+    Right,
+}
+
+/// A command for a running segment from outside of the bar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SegmentCommand {
+    /// update the segment
+    Update,
+    /// the segment was clicked with the mouse button
+    Click(i32),
+    /// display the text, until the next update unless the segment kind keeps it
+    #[cfg_attr(not(feature = "ipc"), allow(dead_code))]
+    Set(String),
+    /// show the next view of the segment
+    #[cfg_attr(not(feature = "ipc"), allow(dead_code))]
+    NextView,
+}
+
+/// What is passed on to the segment kind, it waits while the kind is still computing a value
+#[derive(Debug)]
+enum KindCommand {
+    Set(String),
+    NextView,
+    Signal(u32),
+}
+
+/// What caused the next update of a segment
+enum Trigger {
+    Signal(i32),
+    Command(SegmentCommand),
+}
+
 #[derive(Debug)]
 pub struct Segment {
-    kind: Box<dyn SegmentKind>,
+    /// shared with the thread that computes the value when a timeout is set
+    kind: Arc<Mutex<Box<dyn SegmentKind>>>,
+    /// whether the computations wait for a free slot of the command limit
+    runs_commands: bool,
     update_interval: Option<Duration>,
     signals: Vec<i32>,
+    menu: Option<(i32, Menu)>,
+    /// run when the segment is clicked
+    click_command: Option<String>,
+    /// the signal that the statuscmd patch of dwm sends on clicks
+    click_signal: Option<i32>,
+    /// hides or shows the segment
+    toggle_signal: Option<i32>,
+    failure_notifier: Option<FailureNotifier>,
+    /// whether computing the last value failed or took too long
+    failed: bool,
+    /// the last value that was computed successfully
+    last_value: Option<String>,
+    /// whether the last value is displayed after a failure
+    stale: bool,
+    /// passed on to the kind once the computation that took too long finished
+    pending: Vec<KindCommand>,
 
     pub left_separator: String,
     pub right_separator: String,
     pub icon: String,
+    /// picked by the number in the value instead of the icon, the first for 0 and the last for 100
+    pub icons: Vec<String>,
+    pub icon_position: IconPosition,
+    /// between the icon and the value, if there is an icon
+    pub icon_separator: String,
     pub hide_if_empty: bool,
+    /// the segment is hidden when the value matches
+    pub hide_if: Option<Regex>,
+    /// the segment is only shown when the value matches
+    pub show_if: Option<Regex>,
+    /// applied to the value in order, before it is decorated
+    pub transforms: Vec<Transform>,
+    /// applied to values that are numbers, after the transforms
+    pub number_format: Option<NumberFormat>,
+    /// the template that arranges `{left}`, `{icon}`, `{value}` and `{right}`,
+    /// they are simply concatenated without one
+    pub format: Option<String>,
+    /// the value is padded to this many characters
+    pub min_width: usize,
+    pub align: Align,
+    /// longer values are shortened to this many characters
+    pub max_width: Option<usize>,
+    /// ends shortened values
+    pub ellipsis: String,
+    pub area: Area,
+    /// identifies the segment in commands from outside
+    pub name: Option<String>,
+    /// the offset of the first signal that updates the segment, not all segments
+    pub update_signal: Option<u32>,
+    /// the monitors the segment is displayed on, all if empty
+    pub monitors: Vec<usize>,
+    /// the names of the segments whose updates also update this one
+    pub depends_on: Vec<String>,
+    /// the value is not waited for longer than this
+    pub timeout: Option<Duration>,
+    /// failures are retried before they are displayed
+    pub retry: Option<Retry>,
+    /// failures and timeouts display the last value instead of an error
+    pub keep_last_on_error: bool,
+    /// put after the last value when it is kept
+    pub stale_marker: String,
+    /// values are reused for updates within this time after they were computed
+    pub cache_ttl: Option<Duration>,
+    /// the first value is computed after this time, to spread the start of the segments
+    pub startup_delay: Duration,
+    /// the times of periodic updates, instead of the update interval
+    pub schedule: Option<Schedule>,
+    /// periodic updates happen at the full second, minute or hour instead of after the start
+    pub align_to: Option<AlignTo>,
+    /// the least time between two updates, triggers in between lead to a single update afterwards
+    pub min_update_interval: Option<Duration>,
+    /// displayed instead of the value when it took longer than the timeout
+    pub timeout_text: String,
+    /// displayed instead of "ERROR" when the value could not be computed
+    pub on_error: Option<OnError>,
+    /// replaces line breaks and NUL bytes in the value, which would break the status
+    pub replacement_character: String,
 
     pub coloring: SegmentColoring,
+    pub color_format: ColorFormat,
+    /// text colors for values that match the patterns
+    pub color_map: Vec<(Regex, Color)>,
 }
 
 pub trait SegmentKind: Debug + Send + Sync {
-    fn compute_value(&mut self) -> String;
+    /// The value of the segment, failures are displayed as "ERROR".
+    /// Segment kinds implement this or `try_compute_value`.
+    fn compute_value(&mut self) -> String {
+        self.try_compute_value().unwrap_or_else(|_| "ERROR".into())
+    }
+
+    /// Called once before the first update.
+    /// Segment kinds that get notified about changes (instead of polling for them)
+    /// return a channel here through which they request an update of the segment.
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        None
+    }
+
+    /// Called once before the first update.
+    /// Segment kinds whose values are pushed to them return them here as a stream.
+    /// Every value is passed to `set_value` and the segment is updated.
+    fn values(&mut self) -> Option<ValueStream> {
+        None
+    }
+
+    /// Computes the value like `compute_value`, but reports failures instead of
+    /// turning them into text, so that the segment can react to them
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        Ok(self.compute_value())
+    }
+
+    /// The realtime signal offsets the segment kind reacts to, in addition to the ones of the segment
+    fn control_signals(&self) -> Vec<u32> {
+        Vec::new()
+    }
+
+    /// Called with the offset to SIGRTMIN whenever a signal of the segment arrives,
+    /// before the segment is updated
+    fn handle_signal(&mut self, _signal_offset: u32) {}
+
+    /// Called with text that is set from outside.
+    /// Segment kinds that display it return true, the others only show it until the next update.
+    fn set_value(&mut self, _value: String) -> bool {
+        false
+    }
+
+    /// Called when the next view of the segment is requested from outside, before the segment is updated
+    fn next_view(&mut self) {}
+
+    /// The text color of the last value, instead of the one of the segment
+    fn color(&self) -> Option<Color> {
+        None
+    }
+
+    /// Whether computing the value runs a command, which counts towards `max_concurrent_commands`
+    fn runs_commands(&self) -> bool {
+        false
+    }
+}
+
+/// The values that a segment kind pushes to the segment
+pub type ValueStream = Pin<Box<dyn Stream<Item = String> + Send>>;
+
+/// How failures of a segment are displayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnError {
+    /// `{error}` is replaced by the error message, e.g. what a script printed to stderr
+    pub text: String,
+    /// the color of the text, the text color of the segment by default
+    pub color: Option<Color>,
+}
+
+/// How often failures are retried before they are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retry {
+    pub attempts: u32,
+    /// the time until the first retry, it doubles with every further one
+    pub backoff: Duration,
+}
+
+/// Builds a segment with the settings that are otherwise only available in the config file
+#[derive(Debug)]
+pub struct SegmentBuilder {
+    kind: Box<dyn SegmentKind>,
+    update_interval: Option<Duration>,
+    signals: Vec<u32>,
+    left_separator: String,
+    right_separator: String,
+    icon: String,
+    hide_if_empty: bool,
+    coloring: SegmentColoring,
+}
+
+impl SegmentBuilder {
+    /// Updates the segment periodically
+    pub fn update_interval(mut self, update_interval: Duration) -> Self {
+        self.update_interval = Some(update_interval);
+        self
+    }
+
+    /// Updates the segment when SIGRTMIN+offset arrives
+    pub fn signal(mut self, offset: u32) -> Self {
+        self.signals.push(offset);
+        self
+    }
+
+    pub fn left_separator(mut self, separator: impl Into<String>) -> Self {
+        self.left_separator = separator.into();
+        self
+    }
+
+    pub fn right_separator(mut self, separator: impl Into<String>) -> Self {
+        self.right_separator = separator.into();
+        self
+    }
+
+    pub fn icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = icon.into();
+        self
+    }
+
+    /// Leaves the segment out when its value is empty
+    pub fn hide_if_empty(mut self, hide_if_empty: bool) -> Self {
+        self.hide_if_empty = hide_if_empty;
+        self
+    }
+
+    /// The colors of the value, the separators and the icon
+    pub fn coloring(mut self, coloring: SegmentColoring) -> Self {
+        self.coloring = coloring;
+        self
+    }
+
+    /// Fails if a signal is out of the range of realtime signals
+    pub fn build(self) -> Result<Segment, String> {
+        let mut segment = Segment::new(self.kind, self.update_interval, self.signals)?;
+        segment.left_separator = self.left_separator;
+        segment.right_separator = self.right_separator;
+        segment.icon = self.icon;
+        segment.hide_if_empty = self.hide_if_empty;
+        segment.coloring = self.coloring;
+        Ok(segment)
+    }
+}
+
+pub(crate) fn timeout_text_default() -> String {
+    "TIMEOUT".into()
+}
+
+pub(crate) fn replacement_character_default() -> String {
+    " ".into()
+}
+
+pub(crate) fn ellipsis_default() -> String {
+    "…".into()
 }
 
 impl Segment {
+    /// Starts building a segment of the kind, which is not updated unless configured otherwise
+    pub fn builder(kind: Box<dyn SegmentKind>) -> SegmentBuilder {
+        SegmentBuilder {
+            kind,
+            update_interval: None,
+            signals: Vec::new(),
+            left_separator: String::new(),
+            right_separator: String::new(),
+            icon: String::new(),
+            hide_if_empty: false,
+            coloring: Default::default(),
+        }
+    }
+
     pub fn new(
         kind: Box<dyn SegmentKind>,
         update_interval: Option<Duration>,
-        signal_offsets: Vec<u32>,
+        mut signal_offsets: Vec<u32>,
     ) -> Result<Self, String> {
+        let update_signal = signal_offsets.first().copied();
+        signal_offsets.extend(kind.control_signals());
         Ok(Self {
-            kind,
+            runs_commands: kind.runs_commands(),
+            kind: Arc::new(Mutex::new(kind)),
             update_interval,
             signals: Self::convert_signal_offsets(signal_offsets)?,
+            menu: None,
+            click_command: None,
+            click_signal: None,
+            toggle_signal: None,
+            failure_notifier: None,
+            failed: false,
+            last_value: None,
+            stale: false,
+            pending: Vec::new(),
             left_separator: Default::default(),
             right_separator: Default::default(),
             icon: Default::default(),
+            icons: Vec::new(),
+            icon_position: Default::default(),
+            icon_separator: Default::default(),
             hide_if_empty: Default::default(),
+            hide_if: None,
+            show_if: None,
+            transforms: Vec::new(),
+            number_format: None,
+            format: None,
+            min_width: 0,
+            align: Default::default(),
+            max_width: None,
+            ellipsis: ellipsis_default(),
+            area: Default::default(),
+            name: None,
+            monitors: Vec::new(),
+            depends_on: Vec::new(),
+            timeout: None,
+            retry: None,
+            keep_last_on_error: false,
+            update_signal,
+            stale_marker: String::new(),
+            cache_ttl: None,
+            startup_delay: Duration::ZERO,
+            schedule: None,
+            align_to: None,
+            min_update_interval: None,
+            timeout_text: timeout_text_default(),
+            on_error: None,
+            replacement_character: replacement_character_default(),
             coloring: Default::default(),
+            color_format: Default::default(),
+            color_map: Vec::new(),
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new_from_config(
         kind: Box<dyn SegmentKind>,
         update_interval: Option<Duration>,
-        signal_offsets: Vec<u32>,
+        mut signal_offsets: Vec<u32>,
         left_separator: Option<String>,
         right_separator: Option<String>,
         icon: Option<String>,
@@ -75,49 +525,283 @@ impl Segment {
             .unwrap_or_else(|| "".into());
         let icon = icon.unwrap_or_else(|| "".into());
         let coloring = coloring.or_default(&config.coloring);
+        signal_offsets.extend(kind.control_signals());
 
         Ok(Segment {
-            kind,
+            runs_commands: kind.runs_commands(),
+            kind: Arc::new(Mutex::new(kind)),
             update_interval,
             signals: Self::convert_signal_offsets(signal_offsets)?,
+            menu: None,
+            click_command: None,
+            click_signal: None,
+            toggle_signal: None,
+            failure_notifier: config.failure_notifier.clone(),
+            failed: false,
+            last_value: None,
+            stale: false,
+            pending: Vec::new(),
 
             left_separator,
             right_separator,
             icon,
+            icons: Vec::new(),
+            icon_position: Default::default(),
+            icon_separator: Default::default(),
             hide_if_empty,
+            hide_if: None,
+            show_if: None,
+            transforms: Vec::new(),
+            number_format: None,
+            format: None,
+            min_width: 0,
+            align: Default::default(),
+            max_width: None,
+            ellipsis: ellipsis_default(),
+            area: Default::default(),
+            name: None,
+            monitors: Vec::new(),
+            depends_on: Vec::new(),
+            timeout: None,
+            retry: None,
+            keep_last_on_error: false,
+            update_signal: None,
+            stale_marker: String::new(),
+            cache_ttl: None,
+            startup_delay: Duration::ZERO,
+            schedule: None,
+            align_to: None,
+            min_update_interval: None,
+            timeout_text: timeout_text_default(),
+            on_error: None,
+            replacement_character: config
+                .replacement_character
+                .clone()
+                .unwrap_or_else(replacement_character_default),
 
             coloring,
+            color_format: config.color_format,
+            color_map: Vec::new(),
         })
     }
 
+    /// Shows the menu when the signal SIGRTMIN+`signal_offset` arrives
+    pub(crate) fn set_menu(&mut self, signal_offset: u32, menu: Menu) -> Result<(), String> {
+        let signal = Self::convert_signal_offsets(vec![signal_offset])?[0];
+        self.signals.push(signal);
+        self.menu = Some((signal, menu));
+        Ok(())
+    }
+
+    /// Also updates the segment when SIGRTMIN+`signal_offset` arrives
+    pub(crate) fn add_signal(&mut self, signal_offset: u32) -> Result<(), String> {
+        let signal = Self::convert_signal_offsets(vec![signal_offset])?[0];
+        if !self.signals.contains(&signal) {
+            self.signals.push(signal);
+        }
+        Ok(())
+    }
+
+    /// Runs the command when the segment is clicked.
+    /// With the statuscmd patch of dwm, the segment is identified by a marker byte with
+    /// the value of `signal_offset`, and dwm sends SIGRTMIN+`signal_offset` on clicks.
+    pub(crate) fn set_click_command(
+        &mut self,
+        signal_offset: Option<u32>,
+        command: String,
+    ) -> Result<(), String> {
+        if let Some(signal_offset) = signal_offset {
+            // the marker byte must be a control character, but not NUL or a line break
+            if !(1..32).contains(&signal_offset) || [10, 13].contains(&signal_offset) {
+                return Err(format!(
+                    "signal {signal_offset} can not be used for clicks, \
+                     it has to be between 1 and 31 and not 10 or 13 (line breaks)"
+                ));
+            }
+            let signal = Self::convert_signal_offsets(vec![signal_offset])?[0];
+            click::register(signal)?;
+            if !self.signals.contains(&signal) {
+                self.signals.push(signal);
+            }
+            self.click_signal = Some(signal);
+        }
+        self.click_command = Some(command);
+        Ok(())
+    }
+
+    /// Hides or shows the segment when the signal SIGRTMIN+`signal_offset` arrives
+    pub(crate) fn set_toggle_signal(&mut self, signal_offset: u32) -> Result<(), String> {
+        self.toggle_signal = Some(Self::convert_signal_offsets(vec![signal_offset])?[0]);
+        Ok(())
+    }
+
+    pub(crate) fn toggle_signal(&self) -> Option<i32> {
+        self.toggle_signal
+    }
+
     fn convert_signal_offsets(signal_offsets: Vec<u32>) -> Result<Vec<i32>, String> {
-        let signals = signal_offsets
+        if let Some(offset) = signal_offsets
+            .iter()
+            .find(|offset| **offset > max_signal_offset())
+        {
+            return Err(format!(
+                "signal {offset} is greater than SIGRTMAX-SIGRTMIN ({})",
+                max_signal_offset()
+            ));
+        }
+
+        Ok(signal_offsets
             .into_iter()
             .map(move |signal| signal as i32 + *SIGRTMIN)
-            .collect::<Vec<_>>();
-
-        if signals.iter().any(|signal| *signal > *SIGRTMAX) {
-            return Err("A used signal is greater than SIGRTMAX.".into());
-        }
+            .collect())
+    }
 
-        Ok(signals)
+    /// The realtime signal offsets that the kind of the segment reacts to
+    pub(crate) fn control_signals(&self) -> Vec<u32> {
+        self.kind.lock().unwrap().control_signals()
     }
 
     pub(crate) async fn run_update_loop(
         mut self,
         id: SegmentId,
         channel: Sender<(SegmentId, String)>,
+        commands: Receiver<SegmentCommand>,
     ) {
         // register_signal handler
         let mut signals = Signals::new(&self.signals).unwrap();
+        let update_requests = self.kind.lock().unwrap().update_requests();
+        let mut values = self.kind.lock().unwrap().values();
+        // text that was set from outside, it is displayed instead of the next value
+        let mut text = None;
+        let mut previous_update = None::<Instant>;
+        // the last computed value and when it was computed
+        let mut cache = None::<(Instant, String)>;
+        // the number of failed computations that were retried
+        let mut retries = 0;
+
+        if !self.startup_delay.is_zero() {
+            task::sleep(self.startup_delay).await;
+        }
 
         loop {
+            // signals that arrive while waiting are coalesced, so they cause a single update
+            if let (Some(min_interval), Some(previous)) =
+                (self.min_update_interval, previous_update)
+            {
+                if let Some(wait) = min_interval.checked_sub(previous.elapsed()) {
+                    task::sleep(wait).await;
+                    // the requests in the meantime are handled by this update,
+                    // clicks and the like still happen before it
+                    if let Some(requests) = &update_requests {
+                        while requests.try_recv().is_ok() {}
+                    }
+                    while let Some(Some(signal)) = future::poll_once(signals.next()).await {
+                        self.handle(Trigger::Signal(signal), &mut text).await;
+                    }
+                    while let Ok(command) = commands.try_recv() {
+                        self.handle(Trigger::Command(command), &mut text).await;
+                    }
+                }
+            }
+
+            // the commands that waited for a computation that took too long
+            self.pass_on(&mut text);
+
             let last_update = Instant::now();
+            previous_update = Some(last_update);
             // compute initial value for that segment and send it through the channel
-            channel.send((id, self.compute_value())).await.unwrap();
+            let (value, failed) = match (text.take(), &cache) {
+                (Some(text), _) => (self.decorate(text), false),
+                (None, Some((computed, value)))
+                    if self.cache_ttl.is_some_and(|ttl| computed.elapsed() < ttl) =>
+                {
+                    (value.clone(), false)
+                }
+                (None, _) => {
+                    let value = self.next_value().await;
+                    if self.cache_ttl.is_some() && !self.failed {
+                        cache = Some((Instant::now(), value.clone()));
+                    }
+                    (value, self.failed)
+                }
+            };
+
+            // failures are retried before they are displayed, the last value stays in the meantime
+            let retry_in = match &self.retry {
+                Some(retry) if failed && retries < retry.attempts => {
+                    let backoff = retry.backoff.saturating_mul(2u32.saturating_pow(retries));
+                    retries += 1;
+                    Some(backoff)
+                }
+                _ => {
+                    retries = 0;
+                    channel.send((id, value)).await.unwrap();
+                    None
+                }
+            };
+
+            // the next update is caused by a signal, a command, a value of the stream
+            // or requested by the segment kind
+            let next_event = future::or(
+                async { signals.next().await.map(Trigger::Signal) },
+                future::or(
+                    async {
+                        match commands.recv().await {
+                            Ok(command) => Some(Trigger::Command(command)),
+                            Err(_) => future::pending().await,
+                        }
+                    },
+                    future::or(
+                        async {
+                            match &mut values {
+                                // a value is set like one from outside
+                                Some(values) => match values.next().await {
+                                    Some(value) => {
+                                        Some(Trigger::Command(SegmentCommand::Set(value)))
+                                    }
+                                    None => future::pending().await,
+                                },
+                                None => future::pending().await,
+                            }
+                        },
+                        async {
+                            match &update_requests {
+                                Some(requests) => {
+                                    // when the segment kind stops sending requests, we only wait for signals
+                                    if requests.recv().await.is_err() {
+                                        future::pending::<()>().await;
+                                    }
+                                }
+                                None => future::pending::<()>().await,
+                            }
+                            None
+                        },
+                    ),
+                ),
+            );
 
             // if we have an update interval for that segment
-            if let Some(update_interval) = self.update_interval {
+            let trigger = if let Some(retry_in) = retry_in {
+                timeout(retry_in, next_event).await.ok().flatten()
+            } else if let Some(schedule) = &self.schedule {
+                let now = local_time::now();
+                match schedule.next_after(now) {
+                    Some(next) => {
+                        let duration = Duration::from_secs((next - now) as u64);
+                        timeout(duration, next_event).await.ok().flatten()
+                    }
+                    None => next_event.await,
+                }
+            } else if let (Some(update_interval), Some(align_to)) =
+                (self.update_interval, self.align_to)
+            {
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default();
+                let utc_offset = local_time(now.as_secs() as i64).tm_gmtoff;
+                let duration = align_to.until_next(update_interval, now, utc_offset);
+                timeout(duration, next_event).await.ok().flatten()
+            } else if let Some(update_interval) = self.update_interval {
                 // calculate time since the last update
                 let duration = Instant::elapsed(&last_update);
 
@@ -125,32 +809,362 @@ impl Segment {
                 match update_interval.checked_sub(duration) {
                     Some(duration) => {
                         // wait for signals or timeout at that duration
-                        let _ = timeout(duration, signals.next()).await;
+                        timeout(duration, next_event).await.ok().flatten()
                     }
                     // otherwise, update directly
-                    None => warn!("execution of segment {id} took longer than update interval"),
-                };
+                    None => {
+                        warn!("execution of segment {id} took longer than update interval");
+                        None
+                    }
+                }
             } else {
                 // if we have no periodic updates, simply wait for signals
-                signals.next().await;
+                next_event.await
             };
+
+            if let Some(trigger) = trigger {
+                self.handle(trigger, &mut text).await;
+            }
         }
     }
 
+    /// runs what the trigger of an update does before the update, e.g. the click command
+    async fn handle(&mut self, trigger: Trigger, text: &mut Option<String>) {
+        let signal = match trigger {
+            Trigger::Signal(signal) => Some(signal),
+            Trigger::Command(SegmentCommand::Click(button)) => {
+                self.click(button).await;
+                None
+            }
+            Trigger::Command(SegmentCommand::Set(new_text)) => {
+                self.pending.push(KindCommand::Set(new_text));
+                self.pass_on(text);
+                None
+            }
+            Trigger::Command(SegmentCommand::NextView) => {
+                self.pending.push(KindCommand::NextView);
+                self.pass_on(text);
+                None
+            }
+            Trigger::Command(SegmentCommand::Update) => None,
+        };
+
+        if let Some(signal) = signal {
+            self.pending
+                .push(KindCommand::Signal((signal - *SIGRTMIN) as u32));
+            self.pass_on(text);
+
+            // clicks run the command before the segment is updated
+            if self.click_signal == Some(signal) {
+                if let Some(button) = click::take_button(signal) {
+                    self.click(button).await;
+                }
+            }
+
+            // the menu signal opens the menu before the segment is updated
+            if let Some((menu_signal, menu)) = &self.menu {
+                if signal == *menu_signal {
+                    let menu = menu.clone();
+                    blocking::unblock(move || menu.show()).await;
+                }
+            }
+        }
+    }
+
+    /// Passes the pending commands on to the kind, unless a computation that took too long still uses it.
+    /// Then they are passed on before a later update.
+    fn pass_on(&mut self, text: &mut Option<String>) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let kind = self.kind.clone();
+        let Ok(mut kind) = kind.try_lock() else {
+            warn!("segment is still computing its last value, the command is applied later");
+            return;
+        };
+        for command in self.pending.drain(..) {
+            match command {
+                KindCommand::Set(new_text) => {
+                    if !kind.set_value(new_text.clone()) {
+                        *text = Some(new_text);
+                    }
+                }
+                KindCommand::NextView => kind.next_view(),
+                KindCommand::Signal(offset) => kind.handle_signal(offset),
+            }
+        }
+    }
+
+    /// runs the click command, if there is one
+    async fn click(&self, button: i32) {
+        if let Some(command) = self.click_command.clone() {
+            blocking::unblock(move || click::run_command(&command, button)).await;
+        }
+    }
+
+    /// what the status bar needs to know about the segment
+    pub(crate) fn info(&self) -> SegmentInfo {
+        SegmentInfo {
+            area: self.area,
+            name: self.name.clone(),
+            color: self.coloring.text,
+            monitors: self.monitors.clone(),
+        }
+    }
+
+    /// computes the value, respecting the timeout
+    pub(crate) async fn next_value(&mut self) -> String {
+        match self.timeout {
+            Some(timeout) => self.compute_value_with_timeout(timeout).await,
+            None => {
+                let value = self.computation().await;
+                self.render(value)
+            }
+        }
+    }
+
+    /// Computes the value on a thread of the blocking pool, commands wait for their turn first.
+    /// The turn is kept until the computation ends, even if it isn't waited for anymore.
+    fn computation(&self) -> impl Future<Output = Result<String, String>> {
+        let kind = self.kind.clone();
+        let runs_commands = self.runs_commands;
+        async move {
+            let slot = match runs_commands {
+                true => Some(command_limit::acquire().await),
+                false => None,
+            };
+            blocking::unblock(move || {
+                let _slot = slot;
+                kind.lock().unwrap().try_compute_value()
+            })
+            .await
+        }
+    }
+
+    /// Computes the value on this thread, for segment kinds that consist of segments.
+    /// They compute their values on a thread of the blocking pool, where waiting for the turn is fine.
     pub(crate) fn compute_value(&mut self) -> String {
-        let new_value = self.kind.compute_value();
+        let _slot = self
+            .runs_commands
+            .then(|| task::block_on(command_limit::acquire()));
+        let value = self.kind.lock().unwrap().try_compute_value();
+        self.render(value)
+    }
+
+    /// Computes the value in the background and stops waiting for it after the timeout.
+    /// While a computation that took too long is still running, no new one is started.
+    async fn compute_value_with_timeout(&mut self, timeout: Duration) -> String {
+        let kind = self.kind.clone();
+        if kind.try_lock().is_err() {
+            warn!("segment is still computing its last value");
+            return self.decorate_text(self.timeout_text.clone(), None);
+        }
+
+        let computation = self.computation();
+        match async_std::future::timeout(timeout, computation).await {
+            Ok(value) => self.render(value),
+            Err(_) => {
+                self.failed = true;
+                let message = format!("computing the value took longer than {timeout:?}");
+                warn!("{message}");
+                if let Some(notifier) = &mut self.failure_notifier {
+                    notifier.failure(&message);
+                }
+                if let Some(text) = self.stale_value() {
+                    return text;
+                }
+                self.decorate_text(self.timeout_text.clone(), None)
+            }
+        }
+    }
+
+    fn render(&mut self, value: Result<String, String>) -> String {
+        self.failed = value.is_err();
+        match value {
+            Ok(value) => {
+                if let Some(notifier) = &mut self.failure_notifier {
+                    notifier.success();
+                }
+                if self.keep_last_on_error {
+                    self.last_value = Some(value.clone());
+                }
+                // other segments can display the value
+                if let Some(name) = &self.name {
+                    crate::context::set(name.clone(), value.clone());
+                }
+                let color = self.kind.lock().unwrap().color();
+                self.decorate_with_color(value, color)
+            }
+            Err(e) => {
+                if let Some(notifier) = &mut self.failure_notifier {
+                    notifier.failure(&e);
+                }
+                if let Some(text) = self.stale_value() {
+                    return text;
+                }
+                match &self.on_error {
+                    Some(on_error) => {
+                        let text = fill_template(&on_error.text, &[("error", e)]);
+                        self.decorate_text(text, on_error.color)
+                    }
+                    None => self.decorate_text("ERROR".into(), None),
+                }
+            }
+        }
+    }
+
+    /// the last value with the stale marker, if it is kept on failures
+    fn stale_value(&mut self) -> Option<String> {
+        if !self.keep_last_on_error {
+            return None;
+        }
+        let value = self.last_value.clone()?;
+        self.stale = true;
+        let text = self.decorate(value);
+        self.stale = false;
+        Some(text)
+    }
+
+    /// the icon for the number in the value
+    fn ramp_icon(&self, value: &str) -> &str {
+        match (self.icons.len(), first_number(value)) {
+            (0, _) | (_, None) => &self.icon,
+            (icons, Some(number)) => {
+                let level = (number / 100.0).clamp(0.0, 1.0);
+                let index = ((level * icons as f64) as usize).min(icons - 1);
+                &self.icons[index]
+            }
+        }
+    }
+
+    /// adds the separators and the icon to the value
+    fn decorate(&self, new_value: String) -> String {
+        self.decorate_with_color(new_value, None)
+    }
 
-        if self.hide_if_empty && new_value.is_empty() {
+    /// like `decorate`, but the text color can be replaced
+    fn decorate_with_color(&self, new_value: String, color: Option<Color>) -> String {
+        let new_value = self
+            .transforms
+            .iter()
+            .fold(new_value, |value, transform| transform.apply(value));
+        let new_value = match &self.number_format {
+            Some(number_format) => number_format.apply(new_value),
+            None => new_value,
+        };
+        // also the line breaks that transforms added
+        let new_value = self.sanitize(new_value);
+        let new_value = match self.stale {
+            true => new_value + &self.stale_marker,
+            false => new_value,
+        };
+        let hidden = (self.hide_if_empty && new_value.is_empty())
+            || self
+                .hide_if
+                .as_ref()
+                .is_some_and(|p| p.is_match(&new_value))
+            || self
+                .show_if
+                .as_ref()
+                .is_some_and(|p| !p.is_match(&new_value));
+        if hidden {
             return "".into();
         }
 
-        format!(
-            "{}{}{}{}",
-            self.left_separator.color(self.coloring.left_separator),
-            self.icon.color(self.coloring.icon),
-            new_value.color(self.coloring.text),
-            self.right_separator.color(self.coloring.right_separator)
-        )
+        // the first matching pattern of the color map decides the text color
+        let text_color = self
+            .color_map
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(&new_value))
+            .map(|(_, color)| *color)
+            .or(color);
+        self.frame(new_value, text_color)
+    }
+
+    /// Adds the separators and the icon to a text that is not a value, e.g. the error text.
+    /// The transforms and the conditions for hiding the segment don't apply to it.
+    fn decorate_text(&self, text: String, color: Option<Color>) -> String {
+        let text = self.sanitize(text);
+        self.frame(text, color)
+    }
+
+    /// replaces line breaks and NUL bytes, which would break the status
+    fn sanitize(&self, text: String) -> String {
+        text.replace(['\n', '\r', '\0'], &self.replacement_character)
+    }
+
+    /// shortens and pads the text, and adds the separators, the icon and the colors
+    fn frame(&self, new_value: String, color: Option<Color>) -> String {
+        // the statuscmd patch of dwm finds the clicked segment by this marker
+        let marker = match self.click_signal {
+            Some(signal) => char::from((signal - *SIGRTMIN) as u8).to_string(),
+            None => "".into(),
+        };
+        let text_color = color.unwrap_or(self.coloring.text);
+
+        let new_value = match self.max_width {
+            Some(max_width) => truncate(&new_value, max_width, &self.ellipsis, self.color_format),
+            None => new_value,
+        };
+        // the value is padded before it is colored, so that only visible characters count
+        let new_value = pad(&new_value, self.min_width, self.align);
+
+        // a format places the icon itself
+        let icon = match (self.ramp_icon(&new_value), self.icon_position) {
+            (icon, _) if icon.is_empty() || self.format.is_some() => icon.to_string(),
+            (icon, IconPosition::Left) => format!("{}{}", icon, self.icon_separator),
+            (icon, IconPosition::Right) => format!("{}{}", self.icon_separator, icon),
+        };
+
+        // the text and the colors of the parts of the segment
+        let part = |key: &str| match key {
+            "left" => Some((
+                self.left_separator.as_str(),
+                self.coloring.left_separator,
+                self.coloring.left_separator_background,
+            )),
+            "icon" => Some((
+                icon.as_str(),
+                self.coloring.icon,
+                self.coloring.icon_background,
+            )),
+            "value" => Some((
+                new_value.as_str(),
+                text_color,
+                self.coloring.text_background,
+            )),
+            "right" => Some((
+                self.right_separator.as_str(),
+                self.coloring.right_separator,
+                self.coloring.right_separator_background,
+            )),
+            _ => None,
+        };
+
+        let decorated = match &self.format {
+            // parts are padded before they are colored, so that only visible characters count
+            Some(format) => replace_placeholders(format, |placeholder| {
+                let (text, color, background) = part(placeholder.key)?;
+                Some(
+                    placeholder
+                        .pad(text)
+                        .color(color, background, self.color_format)
+                        .into_owned(),
+                )
+            }),
+            None => match self.icon_position {
+                IconPosition::Left => ["left", "icon", "value", "right"],
+                IconPosition::Right => ["left", "value", "icon", "right"],
+            }
+            .into_iter()
+            .filter_map(part)
+            .map(|(text, color, background)| {
+                text.color(color, background, self.color_format)
+                    .into_owned()
+            })
+            .collect(),
+        };
+        format!("{marker}{decorated}")
     }
 }
 
@@ -158,7 +1172,7 @@ impl Segment {
 mod tests {
     use super::*;
     use crate::segments::constant::Constant;
-    use std::path::PathBuf;
+    use crate::segments::manual::Manual;
 
     #[macro_export]
     macro_rules! test_segment_kinds {
@@ -178,18 +1192,365 @@ mod tests {
     impl Default for Segment {
         fn default() -> Self {
             Self {
-                kind: Box::new(Constant::new("test".into())),
+                kind: Arc::new(Mutex::new(Box::new(Constant::new("test".into())))),
+                runs_commands: false,
                 update_interval: Default::default(),
                 signals: Default::default(),
+                menu: Default::default(),
+                click_command: Default::default(),
+                click_signal: Default::default(),
+                toggle_signal: Default::default(),
+                failure_notifier: Default::default(),
+                failed: false,
+                last_value: None,
+                stale: false,
+                pending: Vec::new(),
                 left_separator: Default::default(),
                 right_separator: Default::default(),
                 icon: Default::default(),
+                icons: Vec::new(),
+                icon_position: Default::default(),
+                icon_separator: Default::default(),
                 hide_if_empty: Default::default(),
+                hide_if: None,
+                show_if: None,
+                transforms: Vec::new(),
+                number_format: None,
+                format: Default::default(),
+                min_width: 0,
+                align: Default::default(),
+                max_width: None,
+                ellipsis: ellipsis_default(),
+                area: Default::default(),
+                name: Default::default(),
+                monitors: Default::default(),
+                depends_on: Default::default(),
+                timeout: Default::default(),
+                retry: None,
+                keep_last_on_error: false,
+                update_signal: None,
+                stale_marker: String::new(),
+                cache_ttl: None,
+                startup_delay: Duration::ZERO,
+                schedule: None,
+                align_to: None,
+                min_update_interval: None,
+                timeout_text: timeout_text_default(),
+                on_error: None,
+                replacement_character: replacement_character_default(),
                 coloring: Default::default(),
+                color_format: Default::default(),
+                color_map: Default::default(),
             }
         }
     }
 
+    #[derive(Debug)]
+    struct Requesting {
+        requests: Option<Receiver<()>>,
+        counter: u32,
+    }
+
+    impl SegmentKind for Requesting {
+        fn compute_value(&mut self) -> String {
+            self.counter += 1;
+            self.counter.to_string()
+        }
+
+        fn update_requests(&mut self) -> Option<Receiver<()>> {
+            self.requests.take()
+        }
+    }
+
+    #[async_std::test]
+    async fn update_requests() {
+        let (request_tx, request_rx) = async_std::channel::unbounded();
+        let segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(Requesting {
+                requests: Some(request_rx),
+                counter: 0,
+            }))),
+            ..Default::default()
+        };
+        let (tx, rx) = async_std::channel::unbounded();
+        let (_command_tx, command_rx) = async_std::channel::unbounded();
+        async_std::task::spawn(segment.run_update_loop(0, tx, command_rx));
+
+        assert_eq!(rx.recv().await.unwrap(), (0, "1".into()));
+        request_tx.send(()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), (0, "2".into()));
+    }
+
+    #[async_std::test]
+    async fn min_update_interval() {
+        let (request_tx, request_rx) = async_std::channel::unbounded();
+        let segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(Requesting {
+                requests: Some(request_rx),
+                counter: 0,
+            }))),
+            min_update_interval: Some(Duration::from_millis(100)),
+            signals: vec![*SIGRTMIN + 21],
+            ..Default::default()
+        };
+        let (tx, rx) = async_std::channel::unbounded();
+        let (command_tx, command_rx) = async_std::channel::unbounded();
+        async_std::task::spawn(segment.run_update_loop(0, tx, command_rx));
+
+        assert_eq!(rx.recv().await.unwrap(), (0, "1".into()));
+        for _ in 0..5 {
+            request_tx.send(()).await.unwrap();
+        }
+        // the signals and commands during the wait are handled by the same update
+        async_std::task::sleep(Duration::from_millis(20)).await;
+        unsafe {
+            libc::raise(*SIGRTMIN + 21);
+        }
+        command_tx.send(SegmentCommand::Update).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), (0, "2".into()));
+        assert!(
+            async_std::future::timeout(Duration::from_millis(300), rx.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[async_std::test]
+    async fn scroll_update_interval() {
+        let inner = Box::new(Requesting {
+            requests: None,
+            counter: 0,
+        });
+        let config = scroll::ScrollConfig {
+            width: 5,
+            interval_ms: Duration::from_millis(20),
+        };
+        let update_interval = Some(Duration::from_millis(100));
+        let segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(scroll::Scroll::new(
+                inner,
+                config,
+                update_interval,
+            )))),
+            update_interval,
+            ..Default::default()
+        };
+        let (tx, rx) = async_std::channel::unbounded();
+        let (_command_tx, command_rx) = async_std::channel::unbounded();
+        async_std::task::spawn(segment.run_update_loop(0, tx, command_rx));
+
+        // the steps don't keep the value from being computed again
+        let computed_again = async { while rx.recv().await.unwrap().1 != "2" {} };
+        async_std::future::timeout(Duration::from_secs(1), computed_again)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn click_signals() {
+        let mut segment = Segment::default();
+        for signal in [0, 10, 13, 32] {
+            assert!(segment
+                .set_click_command(Some(signal), "true".into())
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn align_to() {
+        let minute = Duration::from_secs(60);
+        // 12:00:30.5 UTC
+        let now = Duration::from_secs_f64(43230.5);
+        assert_eq!(
+            AlignTo::Minute.until_next(minute, now, 0),
+            Duration::from_secs_f64(29.5)
+        );
+        assert_eq!(
+            AlignTo::Minute.until_next(Duration::from_secs(90), now, 0),
+            Duration::from_secs_f64(89.5)
+        );
+        assert_eq!(
+            AlignTo::Second.until_next(Duration::from_secs(1), now, 0),
+            Duration::from_secs_f64(0.5)
+        );
+        // 17:30:30.5 in India
+        assert_eq!(
+            AlignTo::Hour.until_next(minute, now, 19800),
+            Duration::from_secs_f64(1769.5)
+        );
+    }
+
+    #[async_std::test]
+    async fn cache_ttl() {
+        let (request_tx, request_rx) = async_std::channel::unbounded();
+        let segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(Requesting {
+                requests: Some(request_rx),
+                counter: 0,
+            }))),
+            cache_ttl: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let (tx, rx) = async_std::channel::unbounded();
+        let (_command_tx, command_rx) = async_std::channel::unbounded();
+        async_std::task::spawn(segment.run_update_loop(0, tx, command_rx));
+
+        assert_eq!(rx.recv().await.unwrap(), (0, "1".into()));
+        request_tx.send(()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), (0, "1".into()));
+        async_std::task::sleep(Duration::from_millis(250)).await;
+        request_tx.send(()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), (0, "2".into()));
+    }
+
+    #[derive(Debug)]
+    struct Flaky {
+        requests: Option<Receiver<()>>,
+        results: Vec<Result<String, String>>,
+    }
+
+    impl SegmentKind for Flaky {
+        fn compute_value(&mut self) -> String {
+            self.try_compute_value().unwrap_or_default()
+        }
+
+        fn try_compute_value(&mut self) -> Result<String, String> {
+            self.results.remove(0)
+        }
+
+        fn update_requests(&mut self) -> Option<Receiver<()>> {
+            self.requests.take()
+        }
+    }
+
+    #[async_std::test]
+    async fn retry() {
+        let results = ["a", "", "", "b", "", "", ""]
+            .into_iter()
+            .map(|r| match r {
+                "" => Err("failed".into()),
+                r => Ok(r.into()),
+            })
+            .collect();
+        let (request_tx, request_rx) = async_std::channel::unbounded();
+        let segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(Flaky {
+                requests: Some(request_rx),
+                results,
+            }))),
+            retry: Some(Retry {
+                attempts: 2,
+                backoff: Duration::from_millis(10),
+            }),
+            ..Default::default()
+        };
+        let (tx, rx) = async_std::channel::unbounded();
+        let (_command_tx, command_rx) = async_std::channel::unbounded();
+        async_std::task::spawn(segment.run_update_loop(0, tx, command_rx));
+
+        assert_eq!(rx.recv().await.unwrap(), (0, "a".into()));
+        // succeeds on the second retry
+        request_tx.send(()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), (0, "b".into()));
+        // fails after all retries
+        request_tx.send(()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), (0, "ERROR".into()));
+    }
+
+    #[test]
+    fn keep_last_on_error() {
+        let results = vec![Err("failed".into()), Ok("1".into()), Err("failed".into())];
+        let mut segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(Flaky {
+                requests: None,
+                results,
+            }))),
+            keep_last_on_error: true,
+            stale_marker: "?".into(),
+            ..Default::default()
+        };
+        assert_eq!(segment.compute_value(), "ERROR");
+        assert_eq!(segment.compute_value(), "1");
+        assert_eq!(segment.compute_value(), "1?");
+    }
+
+    #[async_std::test]
+    async fn stream() {
+        let (value_tx, value_rx) = async_std::channel::unbounded();
+        let segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(stream::SegmentStream::new(value_rx)))),
+            ..Default::default()
+        };
+        let (tx, rx) = async_std::channel::unbounded();
+        let (_command_tx, command_rx) = async_std::channel::unbounded();
+        async_std::task::spawn(segment.run_update_loop(0, tx, command_rx));
+
+        assert_eq!(rx.recv().await.unwrap(), (0, "".into()));
+        value_tx.send("a".into()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), (0, "a".into()));
+        value_tx.send("b".into()).await.unwrap();
+        assert_eq!(rx.recv().await.unwrap(), (0, "b".into()));
+    }
+
+    #[derive(Debug)]
+    struct Slow;
+
+    impl SegmentKind for Slow {
+        fn compute_value(&mut self) -> String {
+            std::thread::sleep(Duration::from_secs(1));
+            "slow".into()
+        }
+    }
+
+    #[async_std::test]
+    async fn timeout() {
+        let mut segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(Slow))),
+            ..Default::default()
+        };
+        let timeout = Duration::from_millis(50);
+        assert_eq!(segment.compute_value_with_timeout(timeout).await, "TIMEOUT");
+        // the last computation is still running
+        assert_eq!(segment.compute_value_with_timeout(timeout).await, "TIMEOUT");
+    }
+
+    /// keeps the text that is set, but takes a while to display it
+    #[derive(Debug)]
+    struct SlowManual(String);
+
+    impl SegmentKind for SlowManual {
+        fn compute_value(&mut self) -> String {
+            std::thread::sleep(Duration::from_millis(200));
+            self.0.clone()
+        }
+
+        fn set_value(&mut self, value: String) -> bool {
+            self.0 = value;
+            true
+        }
+    }
+
+    #[async_std::test]
+    async fn commands_during_computation() {
+        let mut segment = Segment {
+            kind: Arc::new(Mutex::new(Box::new(SlowManual("old".into())))),
+            ..Default::default()
+        };
+        let timeout = Duration::from_millis(50);
+        assert_eq!(segment.compute_value_with_timeout(timeout).await, "TIMEOUT");
+
+        let mut text = None;
+        let set = Trigger::Command(SegmentCommand::Set("new".into()));
+        segment.handle(set, &mut text).await;
+        assert_eq!(segment.pending.len(), 1);
+
+        // applied once the computation finished
+        async_std::task::sleep(Duration::from_millis(300)).await;
+        segment.pass_on(&mut text);
+        assert_eq!(text, None);
+        assert_eq!(segment.compute_value(), "new");
+    }
+
     mod segment {
         use crate::color::Color;
 
@@ -201,6 +1562,32 @@ mod tests {
             assert_eq!(&s.compute_value(), "test");
         }
 
+        #[test]
+        fn builder() {
+            let mut s = Segment::builder(Box::new(Constant::new("test".into())))
+                .left_separator("<")
+                .right_separator(">")
+                .icon("$")
+                .coloring(SegmentColoring {
+                    text: Color::Colored(2),
+                    ..Default::default()
+                })
+                .build()
+                .unwrap();
+            assert_eq!(&s.compute_value(), "<$\x02test\x01>");
+
+            let mut s = Segment::builder(Box::new(Constant::new("".into())))
+                .hide_if_empty(true)
+                .left_separator("<")
+                .build()
+                .unwrap();
+            assert_eq!(&s.compute_value(), "");
+            assert!(Segment::builder(Box::new(Constant::new("".into())))
+                .signal(1000)
+                .build()
+                .is_err());
+        }
+
         #[test]
         fn left_separator() {
             let mut s = Segment {
@@ -242,7 +1629,7 @@ mod tests {
         #[test]
         fn hide_if_empty_false() {
             let mut s = Segment {
-                kind: Box::new(Constant::new("".into())),
+                kind: Arc::new(Mutex::new(Box::new(Constant::new("".into())))),
                 left_separator: ">".into(),
                 right_separator: "<".into(),
                 icon: "$".into(),
@@ -254,14 +1641,238 @@ mod tests {
         #[test]
         fn hide_if_empty_true() {
             let mut s = Segment {
-                kind: Box::new(Constant::new("".into())),
+                kind: Arc::new(Mutex::new(Box::new(Constant::new("".into())))),
+                left_separator: ">".into(),
+                right_separator: "<".into(),
+                icon: "$".into(),
+                hide_if_empty: true,
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "");
+        }
+
+        #[test]
+        fn format() {
+            let mut s = Segment {
+                left_separator: ">".into(),
+                right_separator: "<".into(),
+                icon: "$".into(),
+                format: Some("{left}({icon} {value:>6}){right:^3}".into()),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), ">($   test) < ");
+        }
+
+        #[test]
+        fn format_omitted_parts() {
+            let mut s = Segment {
                 left_separator: ">".into(),
+                icon: "$".into(),
+                format: Some("[{value}] {unknown}".into()),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "[test] {unknown}");
+        }
+
+        #[test]
+        fn format_padding_colored() {
+            let mut s = Segment {
+                format: Some("{value:<6}".into()),
+                coloring: SegmentColoring {
+                    text: Color::Colored(2),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "\x02test  \x01");
+        }
+
+        #[test]
+        fn min_width() {
+            let mut s = Segment {
+                left_separator: ">".into(),
+                min_width: 6,
+                align: Align::Right,
+                coloring: SegmentColoring {
+                    text: Color::Colored(2),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), ">\x02  test\x01");
+            s.min_width = 2;
+            assert_eq!(&s.compute_value(), ">\x02test\x01");
+        }
+
+        #[test]
+        fn max_width() {
+            let mut s = Segment {
                 right_separator: "<".into(),
+                max_width: Some(3),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "te…<");
+            s.max_width = Some(4);
+            assert_eq!(&s.compute_value(), "test<");
+        }
+
+        #[test]
+        fn transforms() {
+            let mut s = Segment {
+                kind: Arc::new(Mutex::new(Box::new(Constant::new(" \n".into())))),
                 icon: "$".into(),
                 hide_if_empty: true,
+                transforms: vec![Transform::Trim],
                 ..Default::default()
             };
             assert_eq!(&s.compute_value(), "");
+            s.transforms = vec![Transform::Trim, Transform::Uppercase];
+            s.kind = Arc::new(Mutex::new(Box::new(Constant::new(" test ".into()))));
+            assert_eq!(&s.compute_value(), "$TEST");
+        }
+
+        #[test]
+        fn number_format() {
+            let mut s = Segment {
+                kind: Arc::new(Mutex::new(Box::new(Constant::new("1234.5".into())))),
+                number_format: Some(NumberFormat {
+                    precision: Some(0),
+                    thousands_separator: ".".into(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "1.234");
+        }
+
+        #[test]
+        fn icons() {
+            let mut s = Segment {
+                kind: Arc::new(Mutex::new(Box::new(Manual::new("".into())))),
+                icon: "?".into(),
+                icons: vec!["a".into(), "b".into(), "c".into()],
+                ..Default::default()
+            };
+            let mut icon = |value: &str| {
+                s.kind.lock().unwrap().set_value(value.into());
+                s.compute_value()
+            };
+            assert_eq!(icon("0%"), "a0%");
+            assert_eq!(icon("40%"), "b40%");
+            assert_eq!(icon("100%"), "c100%");
+            assert_eq!(icon("off"), "?off");
+        }
+
+        #[test]
+        fn icon_position() {
+            let mut s = Segment {
+                left_separator: ">".into(),
+                icon: "$".into(),
+                icon_separator: " ".into(),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), ">$ test");
+            s.icon_position = IconPosition::Right;
+            assert_eq!(&s.compute_value(), ">test $");
+            s.icon = "".into();
+            assert_eq!(&s.compute_value(), ">test");
+        }
+
+        #[test]
+        fn hide_if() {
+            let mut s = Segment {
+                icon: "$".into(),
+                hide_if: Some(Regex::new("^disconnected$").unwrap()),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "$test");
+            s.hide_if = Some(Regex::new("^te").unwrap());
+            assert_eq!(&s.compute_value(), "");
+        }
+
+        #[test]
+        fn show_if() {
+            let mut s = Segment {
+                icon: "$".into(),
+                show_if: Some(Regex::new(r"\d").unwrap()),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "");
+            s.show_if = Some(Regex::new("s").unwrap());
+            assert_eq!(&s.compute_value(), "$test");
+        }
+
+        #[derive(Debug)]
+        struct Failing;
+
+        impl SegmentKind for Failing {
+            fn compute_value(&mut self) -> String {
+                "ERROR".into()
+            }
+
+            fn try_compute_value(&mut self) -> Result<String, String> {
+                Err("no network".into())
+            }
+        }
+
+        #[test]
+        fn on_error() {
+            let mut s = Segment {
+                kind: Arc::new(Mutex::new(Box::new(Failing))),
+                icon: "$".into(),
+                coloring: SegmentColoring {
+                    text: Color::Colored(2),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "$\x02ERROR\x01");
+            s.on_error = Some(OnError {
+                text: "! {error}".into(),
+                color: Some(Color::Colored(3)),
+            });
+            assert_eq!(&s.compute_value(), "$\x03! no network\x01");
+        }
+
+        #[test]
+        fn line_breaks() {
+            let mut s = Segment {
+                kind: Arc::new(Mutex::new(Box::new(Constant::new("a\nb\0".into())))),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "a b ");
+            s.replacement_character = "".into();
+            assert_eq!(&s.compute_value(), "ab");
+        }
+
+        #[test]
+        fn transforms_before_line_breaks() {
+            let mut s = Segment {
+                kind: Arc::new(Mutex::new(Box::new(Constant::new("a\nb".into())))),
+                transforms: vec![Transform::Replace {
+                    pattern: Regex::new("\n").unwrap(),
+                    with: ", ".into(),
+                }],
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "a, b");
+        }
+
+        #[test]
+        fn error_text_is_not_transformed() {
+            let mut s = Segment {
+                kind: Arc::new(Mutex::new(Box::new(Failing))),
+                transforms: vec![Transform::Uppercase],
+                hide_if: Some(Regex::new("^ERR").unwrap()),
+                on_error: Some(OnError {
+                    text: "err: {error}".into(),
+                    color: None,
+                }),
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "err: no network");
+            s.on_error = None;
+            assert_eq!(&s.compute_value(), "ERROR");
         }
 
         #[test]
@@ -276,6 +1887,23 @@ mod tests {
             assert_eq!(&s.compute_value(), "\x02test\x01");
         }
 
+        #[test]
+        fn color_map() {
+            let mut s = Segment {
+                coloring: SegmentColoring {
+                    text: Color::Colored(2),
+                    ..Default::default()
+                },
+                color_map: vec![
+                    (Regex::new("^x").unwrap(), Color::Colored(3)),
+                    (Regex::new("es").unwrap(), Color::Colored(4)),
+                    (Regex::new("t").unwrap(), Color::Colored(5)),
+                ],
+                ..Default::default()
+            };
+            assert_eq!(&s.compute_value(), "\x04test\x01");
+        }
+
         #[test]
         fn color_left_separator() {
             let mut s = Segment {
@@ -326,6 +1954,7 @@ mod tests {
                     icon: Color::Colored(3),
                     text: Color::Colored(4),
                     right_separator: Color::Colored(5),
+                    ..Default::default()
                 },
                 ..Default::default()
             };
@@ -349,10 +1978,7 @@ mod tests {
                 Default::default(),
                 &Configuration {
                     left_separator: Some(">".into()),
-                    right_separator: None,
-                    script_dir: PathBuf::default(),
-                    update_all_signal: None,
-                    coloring: Default::default(),
+                    ..Default::default()
                 },
             )
             .unwrap();
@@ -373,10 +1999,7 @@ mod tests {
                 Default::default(),
                 &Configuration {
                     left_separator: Some(">".into()),
-                    right_separator: None,
-                    script_dir: PathBuf::default(),
-                    update_all_signal: None,
-                    coloring: Default::default(),
+                    ..Default::default()
                 },
             )
             .unwrap();
@@ -396,11 +2019,8 @@ mod tests {
                 false,
                 Default::default(),
                 &Configuration {
-                    left_separator: None,
                     right_separator: Some(">".into()),
-                    script_dir: PathBuf::default(),
-                    update_all_signal: None,
-                    coloring: Default::default(),
+                    ..Default::default()
                 },
             )
             .unwrap();
@@ -425,6 +2045,7 @@ mod tests {
                         icon: Color::Colored(3),
                         text: Color::Colored(4),
                         right_separator: Color::Colored(5),
+                        ..Default::default()
                     },
                     ..Default::default()
                 },
@@ -452,6 +2073,7 @@ mod tests {
                     icon: Color::Colored(7),
                     text: Color::Colored(8),
                     right_separator: Color::Colored(9),
+                    ..Default::default()
                 },
                 &Configuration {
                     coloring: SegmentColoring {
@@ -459,6 +2081,7 @@ mod tests {
                         icon: Color::Colored(3),
                         text: Color::Colored(4),
                         right_separator: Color::Colored(5),
+                        ..Default::default()
                     },
                     ..Default::default()
                 },
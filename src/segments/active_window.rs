@@ -0,0 +1,274 @@
+use std::{
+    ffi::CString,
+    os::raw::{c_uchar, c_ulong},
+    ptr, slice,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use async_std::channel::{self, Receiver, Sender, TrySendError};
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use x11::xlib::{
+    AnyPropertyType, Atom, Display, PropertyChangeMask, PropertyNotify, Window, XConnectionNumber,
+    XEvent, XFree, XGetWindowProperty, XInternAtom, XNextEvent, XPending, XSelectInput, XA_WINDOW,
+};
+
+use super::SegmentKind;
+use crate::output::xroot::Connection;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActiveWindowConfig {
+    /// `{title}` is replaced by the title of the focused window
+    pub format: String,
+    /// longer titles are truncated to this number of characters
+    pub max_length: Option<usize>,
+}
+
+impl Default for ActiveWindowConfig {
+    fn default() -> Self {
+        Self {
+            format: "{title}".into(),
+            max_length: None,
+        }
+    }
+}
+
+/// Displays the title of the focused window.
+/// The segment is updated as soon as the focus or the title changes.
+#[derive(Debug)]
+pub struct ActiveWindow {
+    config: ActiveWindowConfig,
+    /// the title, as it was last read by the watching thread
+    title: Arc<Mutex<String>>,
+}
+
+impl ActiveWindow {
+    pub fn new(config: ActiveWindowConfig) -> Self {
+        Self {
+            config,
+            title: Default::default(),
+        }
+    }
+
+    fn format(&self, title: &str) -> String {
+        if title.is_empty() {
+            return "".into();
+        }
+        let title = match self.config.max_length {
+            Some(max_length) => truncate(title, max_length),
+            None => title.to_string(),
+        };
+        fill_template(&self.config.format, &[("title", title)])
+    }
+}
+
+/// truncates the text to `max_length` characters, the last one being an ellipsis
+fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() <= max_length {
+        return text.to_string();
+    }
+    let mut truncated = text
+        .chars()
+        .take(max_length.saturating_sub(1))
+        .collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+struct Atoms {
+    active_window: Atom,
+    net_wm_name: Atom,
+    wm_name: Atom,
+    utf8_string: Atom,
+}
+
+impl Atoms {
+    unsafe fn new(display: *mut Display) -> Self {
+        let atom = |name: &str| {
+            let name = CString::new(name).unwrap();
+            XInternAtom(display, name.as_ptr(), 0)
+        };
+        Self {
+            active_window: atom("_NET_ACTIVE_WINDOW"),
+            net_wm_name: atom("_NET_WM_NAME"),
+            wm_name: atom("WM_NAME"),
+            utf8_string: atom("UTF8_STRING"),
+        }
+    }
+}
+
+/// reads a property of the window, None if the window does not have it
+unsafe fn window_property(
+    display: *mut Display,
+    window: Window,
+    property: Atom,
+    kind: Atom,
+) -> Option<Vec<u8>> {
+    let mut actual_kind = 0;
+    let mut format = 0;
+    let mut items = 0;
+    let mut remaining = 0;
+    let mut data: *mut c_uchar = ptr::null_mut();
+
+    let status = XGetWindowProperty(
+        display,
+        window,
+        property,
+        0,
+        1024,
+        0,
+        kind,
+        &mut actual_kind,
+        &mut format,
+        &mut items,
+        &mut remaining,
+        &mut data,
+    );
+    if status != 0 || data.is_null() {
+        return None;
+    }
+
+    // items of format 32 are stored as longs
+    let item_size = match format {
+        32 => std::mem::size_of::<c_ulong>(),
+        format => format as usize / 8,
+    };
+    let bytes = slice::from_raw_parts(data, items as usize * item_size).to_vec();
+    XFree(data.cast());
+    (actual_kind != 0).then_some(bytes)
+}
+
+unsafe fn active_window(display: *mut Display, root: Window, atoms: &Atoms) -> Window {
+    window_property(display, root, atoms.active_window, XA_WINDOW)
+        .and_then(|bytes| Some(c_ulong::from_ne_bytes(bytes.try_into().ok()?)))
+        .unwrap_or(0)
+}
+
+unsafe fn window_title(display: *mut Display, window: Window, atoms: &Atoms) -> String {
+    if window == 0 {
+        return "".into();
+    }
+    window_property(display, window, atoms.net_wm_name, atoms.utf8_string)
+        .or_else(|| window_property(display, window, atoms.wm_name, AnyPropertyType as Atom))
+        .map(|title| String::from_utf8_lossy(&title).into_owned())
+        .unwrap_or_default()
+}
+
+/// waits until the active window or its title changes, false if the watching should stop
+unsafe fn wait_for_change(
+    connection: &Connection,
+    atoms: &Atoms,
+    active: Window,
+    requests: &Sender<()>,
+) -> bool {
+    let display = connection.display;
+    let mut event: XEvent = std::mem::zeroed();
+    loop {
+        while XPending(display) > 0 {
+            XNextEvent(display, &mut event);
+            if event.get_type() != PropertyNotify {
+                continue;
+            }
+            let event = event.property;
+            if (event.window == connection.root && event.atom == atoms.active_window)
+                || (event.window == active
+                    && (event.atom == atoms.net_wm_name || event.atom == atoms.wm_name))
+            {
+                return true;
+            }
+        }
+
+        // the segment does not exist anymore
+        if requests.is_closed() {
+            return false;
+        }
+        if connection.is_lost() {
+            warn!("lost the connection to the X server, the active window is not watched anymore");
+            return false;
+        }
+        // wakes up regularly to notice that the segment was dropped
+        let mut poll = libc::pollfd {
+            fd: XConnectionNumber(display),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        libc::poll(&mut poll, 1, 1000);
+    }
+}
+
+fn watch(title: Arc<Mutex<String>>, requests: Sender<()>) {
+    let Some(connection) = Connection::open(None) else {
+        warn!("error opening the display to watch the active window");
+        return;
+    };
+    let (display, root) = (connection.display, connection.root);
+
+    unsafe {
+        let atoms = Atoms::new(display);
+        XSelectInput(display, root, PropertyChangeMask);
+
+        let mut active = 0;
+        loop {
+            let new_active = active_window(display, root, &atoms);
+            if new_active != active {
+                // we are only interested in the title changes of the active window
+                if active != 0 {
+                    XSelectInput(display, active, 0);
+                }
+                if new_active != 0 {
+                    XSelectInput(display, new_active, PropertyChangeMask);
+                }
+                active = new_active;
+            }
+
+            *title.lock().unwrap() = window_title(display, active, &atoms);
+            if let Err(TrySendError::Closed(_)) = requests.try_send(()) {
+                return;
+            }
+            if !wait_for_change(&connection, &atoms, active, &requests) {
+                return;
+            }
+        }
+    }
+}
+
+impl SegmentKind for ActiveWindow {
+    fn compute_value(&mut self) -> String {
+        let title = self.title.lock().unwrap().clone();
+        self.format(&title)
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        let (tx, rx) = channel::bounded(1);
+        let title = self.title.clone();
+        thread::spawn(move || watch(title, tx));
+        Some(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("a long title", 6), "a lon…");
+        assert_eq!(truncate("ääää", 3), "ää…");
+    }
+
+    #[test]
+    fn format() {
+        let window = ActiveWindow::new(ActiveWindowConfig {
+            format: "[{title}]".into(),
+            max_length: Some(4),
+        });
+        assert_eq!(window.format("terminal"), "[ter…]");
+        // no focused window
+        assert_eq!(window.format(""), "");
+    }
+}
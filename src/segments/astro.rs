@@ -0,0 +1,155 @@
+use std::f64::consts::PI;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::local_time::{local_time, now};
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AstroConfig {
+    /// in degrees, north is positive
+    pub latitude: f64,
+    /// in degrees, east is positive
+    pub longitude: f64,
+    /// `{sunrise}`, `{sunset}`, `{moon}`, `{moon_icon}` and `{illumination}` are replaced
+    #[serde(default = "format_default")]
+    pub format: String,
+}
+
+fn format_default() -> String {
+    "{sunrise} {sunset} {moon_icon}".into()
+}
+
+/// Displays sunrise, sunset and the phase of the moon, computed locally
+#[derive(Debug)]
+pub struct Astro {
+    config: AstroConfig,
+}
+
+const UNIX_EPOCH_JULIAN: f64 = 2440587.5;
+const J2000: f64 = 2451545.0;
+const SYNODIC_MONTH: f64 = 29.530588853;
+/// a new moon at 2000-01-06 18:14 UTC
+const NEW_MOON_JULIAN: f64 = 2451550.26;
+
+const MOON_PHASES: [(&str, &str); 8] = [
+    ("new moon", "🌑"),
+    ("waxing crescent", "🌒"),
+    ("first quarter", "🌓"),
+    ("waxing gibbous", "🌔"),
+    ("full moon", "🌕"),
+    ("waning gibbous", "🌖"),
+    ("last quarter", "🌗"),
+    ("waning crescent", "🌘"),
+];
+
+impl Astro {
+    pub fn new(config: AstroConfig) -> Self {
+        Self { config }
+    }
+
+    fn format(&self, timestamp: i64) -> String {
+        let time = |t: Option<i64>| match t {
+            Some(t) => {
+                let tm = local_time(t);
+                format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)
+            }
+            None => "--:--".into(),
+        };
+
+        let (sunrise, sunset) = sun_times(timestamp, self.config.latitude, self.config.longitude);
+        let age = moon_age(timestamp);
+        let (moon, moon_icon) = moon_phase(age);
+        let illumination = (1.0 - (2.0 * PI * age / SYNODIC_MONTH).cos()) / 2.0 * 100.0;
+
+        fill_template(
+            &self.config.format,
+            &[
+                ("sunrise", time(sunrise)),
+                ("sunset", time(sunset)),
+                ("moon", moon.to_string()),
+                ("moon_icon", moon_icon.to_string()),
+                ("illumination", format!("{illumination:.0}")),
+            ],
+        )
+    }
+}
+
+fn julian_date(timestamp: i64) -> f64 {
+    timestamp as f64 / 86400.0 + UNIX_EPOCH_JULIAN
+}
+
+fn unix_timestamp(julian_date: f64) -> i64 {
+    ((julian_date - UNIX_EPOCH_JULIAN) * 86400.0).round() as i64
+}
+
+/// computes sunrise and sunset of the day with the sunrise equation,
+/// None means that the sun does not rise or set on that day
+fn sun_times(timestamp: i64, latitude: f64, longitude: f64) -> (Option<i64>, Option<i64>) {
+    let (sin, cos) = (|d: f64| d.to_radians().sin(), |d: f64| d.to_radians().cos());
+
+    // the days since J2000 in local solar time and the mean solar noon of that day
+    let day = (julian_date(timestamp) + longitude / 360.0 - J2000 + 0.5).floor();
+    let mean_solar_time = day + 0.0008 - longitude / 360.0;
+
+    let anomaly = (357.5291 + 0.98560028 * mean_solar_time).rem_euclid(360.0);
+    let center = 1.9148 * sin(anomaly) + 0.02 * sin(2.0 * anomaly) + 0.0003 * sin(3.0 * anomaly);
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+    let transit =
+        J2000 + mean_solar_time + 0.0053 * sin(anomaly) - 0.0069 * sin(2.0 * ecliptic_longitude);
+
+    let declination = (sin(ecliptic_longitude) * sin(23.4397)).asin().to_degrees();
+    let hour_angle =
+        (sin(-0.833) - sin(latitude) * sin(declination)) / (cos(latitude) * cos(declination));
+    if !(-1.0..=1.0).contains(&hour_angle) {
+        return (None, None);
+    }
+    let hour_angle = hour_angle.acos().to_degrees();
+
+    (
+        Some(unix_timestamp(transit - hour_angle / 360.0)),
+        Some(unix_timestamp(transit + hour_angle / 360.0)),
+    )
+}
+
+/// the days since the last new moon
+fn moon_age(timestamp: i64) -> f64 {
+    (julian_date(timestamp) - NEW_MOON_JULIAN).rem_euclid(SYNODIC_MONTH)
+}
+
+fn moon_phase(age: f64) -> (&'static str, &'static str) {
+    let index = (age / SYNODIC_MONTH * 8.0).round() as usize % 8;
+    MOON_PHASES[index]
+}
+
+impl SegmentKind for Astro {
+    fn compute_value(&mut self) -> String {
+        self.format(now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sunrise_sunset() {
+        // 2022-03-20 at the equator, sunrise and sunset are at about 06:04 and 18:10 UTC
+        let (sunrise, sunset) = sun_times(1647777600, 0.0, 0.0);
+        assert!((sunrise.unwrap() - 1647756240).abs() < 300);
+        assert!((sunset.unwrap() - 1647799800).abs() < 300);
+
+        // polar night
+        assert_eq!(sun_times(1640001600, 80.0, 0.0), (None, None));
+    }
+
+    #[test]
+    fn moon() {
+        // 2022-03-18 was full moon, 2022-04-01 was new moon
+        assert_eq!(moon_phase(moon_age(1647604800)).0, "full moon");
+        assert_eq!(moon_phase(moon_age(1648814400)).0, "new moon");
+    }
+}
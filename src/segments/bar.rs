@@ -0,0 +1,136 @@
+use async_std::channel::Receiver;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::{Placement, SegmentKind, ValueStream};
+use crate::number_format::first_number;
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarConfig {
+    /// the number of characters of the bar
+    pub width: usize,
+    /// the value of a full bar
+    pub max: f64,
+    pub filled: String,
+    pub empty: String,
+    pub position: Placement,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            width: 10,
+            max: 100.0,
+            filled: "█".into(),
+            empty: "░".into(),
+            position: Placement::Instead,
+        }
+    }
+}
+
+/// Displays the value of another segment kind, e.g. a percentage, as a progress bar like `███░░`.
+/// The first number in the value is used, values without a number are displayed unchanged.
+#[derive(Debug)]
+pub struct Bar {
+    inner: Box<dyn SegmentKind>,
+    config: BarConfig,
+}
+
+impl Bar {
+    pub fn new(inner: Box<dyn SegmentKind>, config: BarConfig) -> Self {
+        Self { inner, config }
+    }
+
+    fn render(&self, value: String) -> String {
+        let Some(number) = first_number(&value) else {
+            return value;
+        };
+
+        let level = match self.config.max {
+            max if max > 0.0 => (number / max).clamp(0.0, 1.0),
+            _ => 0.0,
+        };
+        let filled = (level * self.config.width as f64).round() as usize;
+        let bar = format!(
+            "{}{}",
+            self.config.filled.repeat(filled),
+            self.config.empty.repeat(self.config.width - filled)
+        );
+        self.config.position.place(bar, value)
+    }
+}
+
+impl SegmentKind for Bar {
+    fn compute_value(&mut self) -> String {
+        let value = self.inner.compute_value();
+        self.render(value)
+    }
+
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let value = self.inner.try_compute_value()?;
+        Ok(self.render(value))
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        self.inner.update_requests()
+    }
+
+    fn values(&mut self) -> Option<ValueStream> {
+        self.inner.values()
+    }
+
+    fn control_signals(&self) -> Vec<u32> {
+        self.inner.control_signals()
+    }
+
+    fn handle_signal(&mut self, signal_offset: u32) {
+        self.inner.handle_signal(signal_offset)
+    }
+
+    fn set_value(&mut self, value: String) -> bool {
+        self.inner.set_value(value)
+    }
+
+    fn next_view(&mut self) {
+        self.inner.next_view()
+    }
+
+    fn runs_commands(&self) -> bool {
+        self.inner.runs_commands()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segments::constant::Constant;
+
+    fn bar(value: &str, config: BarConfig) -> String {
+        Bar::new(Box::new(Constant::new(value.into())), config).compute_value()
+    }
+
+    #[test]
+    fn render() {
+        let config = || BarConfig {
+            width: 5,
+            ..Default::default()
+        };
+        assert_eq!(bar("0%", config()), "░░░░░");
+        assert_eq!(bar("vol 60%", config()), "███░░");
+        assert_eq!(bar("120", config()), "█████");
+        assert_eq!(bar("muted", config()), "muted");
+    }
+
+    #[test]
+    fn glyphs() {
+        let config = BarConfig {
+            width: 4,
+            max: 1.0,
+            filled: "=".into(),
+            empty: "-".into(),
+            position: Placement::After,
+        };
+        assert_eq!(bar("0.5", config), "0.5 ==--");
+    }
+}
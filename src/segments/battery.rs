@@ -0,0 +1,174 @@
+use std::{fs::read_to_string, path::PathBuf};
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatteryConfig {
+    /// name of the battery in /sys/class/power_supply
+    pub device: String,
+    /// in all formats, `{capacity}` and `{time}` are replaced
+    pub charging: String,
+    pub discharging: String,
+    pub full: String,
+    /// used when the battery is neither charging nor discharging, but not full
+    pub idle: String,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            device: "BAT0".into(),
+            charging: "+{capacity}%".into(),
+            discharging: "{capacity}% {time}".into(),
+            full: "{capacity}%".into(),
+            idle: "{capacity}%".into(),
+        }
+    }
+}
+
+/// Displays the state of a battery from /sys/class/power_supply
+#[derive(Debug)]
+pub struct Battery {
+    path: PathBuf,
+    config: BatteryConfig,
+}
+
+impl Battery {
+    pub fn new(config: BatteryConfig) -> Self {
+        let path = PathBuf::from("/sys/class/power_supply").join(&config.device);
+        Self { path, config }
+    }
+
+    fn read(&self, file: &str) -> Option<String> {
+        read_to_string(self.path.join(file))
+            .ok()
+            .map(|s| s.trim().to_string())
+    }
+
+    fn read_number(&self, file: &str) -> Option<f64> {
+        self.read(file)?.parse().ok()
+    }
+
+    /// remaining time until the battery is empty, formatted as h:mm
+    fn time_to_empty(&self) -> Option<String> {
+        // depending on the battery, the values are reported as energy or as charge
+        let (now, rate) = match (
+            self.read_number("energy_now"),
+            self.read_number("power_now"),
+        ) {
+            (Some(now), Some(rate)) => (now, rate),
+            _ => (
+                self.read_number("charge_now")?,
+                self.read_number("current_now")?,
+            ),
+        };
+
+        if rate <= 0.0 {
+            return None;
+        }
+
+        let minutes = (now / rate * 60.0) as u64;
+        Some(format!("{}:{:02}", minutes / 60, minutes % 60))
+    }
+}
+
+impl SegmentKind for Battery {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let capacity = match self.read("capacity") {
+            Some(capacity) => capacity,
+            None => {
+                let message = format!(
+                    "error reading capacity of battery '{}'",
+                    self.path.to_str().unwrap()
+                );
+                warn!("{message}");
+                return Err(message);
+            }
+        };
+
+        let (template, time) = match self.read("status").as_deref() {
+            Some("Charging") => (&self.config.charging, None),
+            Some("Discharging") => (&self.config.discharging, self.time_to_empty()),
+            Some("Full") => (&self.config.full, None),
+            _ => (&self.config.idle, None),
+        };
+
+        Ok(fill_template(
+            template,
+            &[("capacity", capacity), ("time", time.unwrap_or_default())],
+        )
+        .trim()
+        .to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    fn battery(name: &str, files: &[(&str, &str)]) -> Battery {
+        let path =
+            env::temp_dir().join(format!("dwmblocksrs-battery-{name}-{}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        for (file, content) in files {
+            fs::write(path.join(file), content).unwrap();
+        }
+        Battery {
+            path,
+            config: Default::default(),
+        }
+    }
+
+    #[test]
+    fn discharging() {
+        let mut b = battery(
+            "discharging",
+            &[
+                ("capacity", "50\n"),
+                ("status", "Discharging\n"),
+                ("energy_now", "30000000\n"),
+                ("power_now", "20000000\n"),
+            ],
+        );
+        assert_eq!(b.compute_value(), "50% 1:30");
+        fs::remove_dir_all(b.path).unwrap();
+    }
+
+    #[test]
+    fn discharging_charge() {
+        let mut b = battery(
+            "charge",
+            &[
+                ("capacity", "20"),
+                ("status", "Discharging"),
+                ("charge_now", "1000"),
+                ("current_now", "4000"),
+            ],
+        );
+        assert_eq!(b.compute_value(), "20% 0:15");
+        fs::remove_dir_all(b.path).unwrap();
+    }
+
+    #[test]
+    fn charging() {
+        let mut b = battery("charging", &[("capacity", "70"), ("status", "Charging")]);
+        assert_eq!(b.compute_value(), "+70%");
+        fs::remove_dir_all(b.path).unwrap();
+    }
+
+    #[test]
+    fn missing() {
+        let mut b = Battery::new(BatteryConfig {
+            device: "does-not-exist".into(),
+            ..Default::default()
+        });
+        assert!(b.try_compute_value().is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use zbus::blocking::{fdo::ObjectManagerProxy, Connection};
+use zbus::zvariant::OwnedValue;
+
+use super::SegmentKind;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BluetoothBatteryConfig {
+    /// how a single device is displayed, `{name}` and `{percentage}` are replaced
+    pub format: String,
+    /// the string between two devices
+    pub separator: String,
+}
+
+impl Default for BluetoothBatteryConfig {
+    fn default() -> Self {
+        Self {
+            format: "{name} {percentage}%".into(),
+            separator: " ".into(),
+        }
+    }
+}
+
+/// Lists the battery levels of all connected bluetooth devices that report one
+/// through the `org.bluez.Battery1` interface
+#[derive(Debug)]
+pub struct BluetoothBattery {
+    config: BluetoothBatteryConfig,
+    connection: Option<Connection>,
+}
+
+impl BluetoothBattery {
+    pub fn new(config: BluetoothBatteryConfig) -> Self {
+        Self {
+            config,
+            connection: None,
+        }
+    }
+
+    fn devices(&mut self) -> zbus::Result<Vec<(String, u8)>> {
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(Connection::system()?),
+        };
+
+        let objects = ObjectManagerProxy::builder(connection)
+            .destination("org.bluez")?
+            .path("/")?
+            .build()?
+            .get_managed_objects()?;
+
+        let mut devices = objects
+            .into_iter()
+            .filter_map(|(path, interfaces)| {
+                let device = interfaces
+                    .iter()
+                    .find(|(name, _)| name.as_str() == "org.bluez.Device1")?
+                    .1;
+                let battery = interfaces
+                    .iter()
+                    .find(|(name, _)| name.as_str() == "org.bluez.Battery1")?
+                    .1;
+
+                if !device.get("Connected").and_then(as_bool).unwrap_or(false) {
+                    return None;
+                }
+
+                let name = device
+                    .get("Alias")
+                    .or_else(|| device.get("Name"))
+                    .and_then(as_string)
+                    .unwrap_or_else(|| path.to_string());
+                let percentage = battery.get("Percentage").and_then(as_u8)?;
+
+                Some((name, percentage))
+            })
+            .collect::<Vec<_>>();
+
+        // the object manager returns the devices in no particular order
+        devices.sort();
+        Ok(devices)
+    }
+
+    fn format_devices(&self, devices: &[(String, u8)]) -> String {
+        devices
+            .iter()
+            .map(|(name, percentage)| {
+                fill_template(
+                    &self.config.format,
+                    &[
+                        ("name", name.clone()),
+                        ("percentage", percentage.to_string()),
+                    ],
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(&self.config.separator)
+    }
+}
+
+fn as_bool(value: &OwnedValue) -> Option<bool> {
+    value.downcast_ref::<bool>().ok()
+}
+
+fn as_u8(value: &OwnedValue) -> Option<u8> {
+    value.downcast_ref::<u8>().ok()
+}
+
+fn as_string(value: &OwnedValue) -> Option<String> {
+    value.downcast_ref::<&str>().ok().map(String::from)
+}
+
+impl SegmentKind for BluetoothBattery {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match self.devices() {
+            Ok(devices) => Ok(self.format_devices(&devices)),
+            Err(e) => {
+                let message = format!("error reading bluetooth battery levels: {e}");
+                warn!("{message}");
+                // reconnect on the next update
+                self.connection = None;
+                Err(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        let segment = BluetoothBattery::new(Default::default());
+        assert_eq!(
+            segment.format_devices(&[("Headphones".into(), 80), ("Mouse".into(), 5)]),
+            "Headphones 80% Mouse 5%"
+        );
+    }
+
+    #[test]
+    fn no_devices() {
+        let segment = BluetoothBattery::new(Default::default());
+        assert_eq!(segment.format_devices(&[]), "");
+    }
+}
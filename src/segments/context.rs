@@ -0,0 +1,36 @@
+use super::SegmentKind;
+use crate::context;
+use crate::template::replace_placeholders;
+
+/// Displays values of the shared context, e.g. of other segments.
+/// `{key}` is replaced by the value stored under the key, or nothing if there is none yet.
+#[derive(Debug)]
+pub struct Context {
+    template: String,
+}
+
+impl Context {
+    pub fn new(template: String) -> Self {
+        Self { template }
+    }
+}
+
+impl SegmentKind for Context {
+    fn compute_value(&mut self) -> String {
+        replace_placeholders(&self.template, |placeholder| {
+            Some(placeholder.pad(&context::get(placeholder.key).unwrap_or_default()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template() {
+        context::set("context-kind-test", "42");
+        let mut kind = Context::new("[{context-kind-test:>3}] {context-kind-missing}".into());
+        assert_eq!(kind.compute_value(), "[ 42] ");
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs::read_to_string;
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CpuMode {
+    /// usage of all cores together
+    #[default]
+    Aggregate,
+    /// usage of every single core
+    PerCore,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct CpuConfig {
+    pub mode: CpuMode,
+    /// `{usage}` is replaced by the usage in percent
+    pub format: String,
+    /// the string between two cores in per core mode
+    pub separator: String,
+}
+
+impl Default for CpuConfig {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            format: "{usage}%".into(),
+            separator: " ".into(),
+        }
+    }
+}
+
+/// busy and total time of a cpu (in jiffies)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Sample {
+    busy: u64,
+    total: u64,
+}
+
+/// Displays the cpu usage since the last update, read from /proc/stat
+#[derive(Debug)]
+pub struct Cpu {
+    config: CpuConfig,
+    last_samples: Vec<Sample>,
+}
+
+impl Cpu {
+    pub fn new(config: CpuConfig) -> Self {
+        Self {
+            config,
+            last_samples: Vec::new(),
+        }
+    }
+
+    /// parses the cpu lines of /proc/stat, the first sample is the aggregate of all cores
+    fn parse_samples(stat: &str) -> Vec<Sample> {
+        stat.lines()
+            .filter(|line| line.starts_with("cpu"))
+            .map(|line| {
+                // guest and guest_nice are already counted in user and nice
+                let times = line
+                    .split_whitespace()
+                    .skip(1)
+                    .take(8)
+                    .map(|n| n.parse::<u64>().unwrap_or(0))
+                    .collect::<Vec<_>>();
+                let total = times.iter().sum::<u64>();
+                // idle and iowait
+                let idle = times.get(3).unwrap_or(&0) + times.get(4).unwrap_or(&0);
+                Sample {
+                    busy: total - idle,
+                    total,
+                }
+            })
+            .collect()
+    }
+
+    fn usage(last: Sample, current: Sample) -> u64 {
+        let total = current.total.saturating_sub(last.total);
+        if total == 0 {
+            return 0;
+        }
+        let busy = current.busy.saturating_sub(last.busy);
+        (busy * 100 + total / 2) / total
+    }
+
+    fn format(&mut self, samples: Vec<Sample>) -> String {
+        let usages = samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                let last = self.last_samples.get(i).copied().unwrap_or_default();
+                Self::usage(last, *sample)
+            })
+            .collect::<Vec<_>>();
+        self.last_samples = samples;
+
+        let format =
+            |usage: &u64| fill_template(&self.config.format, &[("usage", usage.to_string())]);
+
+        match self.config.mode {
+            CpuMode::Aggregate => usages.first().map(format).unwrap_or_default(),
+            CpuMode::PerCore => usages
+                .iter()
+                .skip(1)
+                .map(format)
+                .collect::<Vec<_>>()
+                .join(&self.config.separator),
+        }
+    }
+}
+
+impl SegmentKind for Cpu {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match read_to_string("/proc/stat") {
+            Ok(stat) => Ok(self.format(Self::parse_samples(&stat))),
+            Err(e) => {
+                let message = format!("error reading /proc/stat: {e}");
+                warn!("{message}");
+                Err(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str = "cpu  100 0 100 700 100 0 0 0 0 0
+cpu0 50 0 50 350 50 0 0 0 0 0
+cpu1 50 0 50 350 50 0 0 0 0 0
+intr 1234";
+
+    const SECOND: &str = "cpu  200 0 200 800 100 0 0 0 0 0
+cpu0 150 0 100 350 50 0 0 0 0 0
+cpu1 50 0 100 400 50 0 0 0 0 0";
+
+    #[test]
+    fn parse() {
+        let samples = Cpu::parse_samples(FIRST);
+        assert_eq!(samples.len(), 3);
+        assert_eq!(
+            samples[0],
+            Sample {
+                busy: 200,
+                total: 1000
+            }
+        );
+
+        // with 50 of the 100 user time as guest time
+        let samples = Cpu::parse_samples("cpu  100 0 100 700 100 0 0 0 50 0");
+        assert_eq!(
+            samples[0],
+            Sample {
+                busy: 200,
+                total: 1000
+            }
+        );
+    }
+
+    #[test]
+    fn aggregate() {
+        let mut cpu = Cpu::new(Default::default());
+        assert_eq!(cpu.format(Cpu::parse_samples(FIRST)), "20%");
+        assert_eq!(cpu.format(Cpu::parse_samples(SECOND)), "67%");
+    }
+
+    #[test]
+    fn per_core() {
+        let mut cpu = Cpu::new(CpuConfig {
+            mode: CpuMode::PerCore,
+            ..Default::default()
+        });
+        cpu.format(Cpu::parse_samples(FIRST));
+        assert_eq!(cpu.format(Cpu::parse_samples(SECOND)), "100% 50%");
+    }
+}
@@ -0,0 +1,148 @@
+use async_std::channel::Receiver;
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use zbus::{
+    blocking::{fdo::PropertiesProxy, Connection},
+    message::Type,
+    names::InterfaceName,
+    zvariant::Value,
+    MatchRule,
+};
+
+use super::SegmentKind;
+use crate::dbus::{watch_signals, Bus};
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DbusConfig {
+    /// `session` (default) or `system`
+    #[serde(default)]
+    pub bus: Bus,
+    /// the well-known name of the service, e.g. `org.freedesktop.UPower`
+    pub service: String,
+    pub path: String,
+    pub interface: String,
+    pub property: String,
+    /// `{value}` is replaced by the value of the property
+    #[serde(default = "format_default")]
+    pub format: String,
+}
+
+fn format_default() -> String {
+    "{value}".into()
+}
+
+/// Displays a property of a D-Bus object.
+/// The segment is updated as soon as the property changes.
+#[derive(Debug)]
+pub struct DbusProperty {
+    config: DbusConfig,
+    /// matches the PropertiesChanged signals of the interface
+    rule: MatchRule<'static>,
+    connection: Option<Connection>,
+}
+
+impl DbusProperty {
+    pub fn new(config: DbusConfig) -> Result<Self, String> {
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender(config.service.clone())
+            .and_then(|rule| rule.path(config.path.clone()))
+            .and_then(|rule| rule.interface("org.freedesktop.DBus.Properties"))
+            .and_then(|rule| rule.member("PropertiesChanged"))
+            .and_then(|rule| rule.arg(0, config.interface.clone()))
+            .map_err(|e| format!("invalid d-bus property {config:?}: {e}"))?
+            .build();
+
+        Ok(Self {
+            config,
+            rule,
+            connection: None,
+        })
+    }
+
+    fn property(&mut self) -> zbus::Result<String> {
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(self.config.bus.connect()?),
+        };
+
+        let value = PropertiesProxy::builder(connection)
+            .destination(self.config.service.as_str())?
+            .path(self.config.path.as_str())?
+            .build()?
+            .get(
+                InterfaceName::try_from(self.config.interface.as_str())?,
+                &self.config.property,
+            )?;
+        Ok(render(&value))
+    }
+}
+
+/// renders simple values without the type annotations of the D-Bus text format
+fn render(value: &Value) -> String {
+    match value {
+        Value::U8(v) => v.to_string(),
+        Value::Bool(v) => v.to_string(),
+        Value::I16(v) => v.to_string(),
+        Value::U16(v) => v.to_string(),
+        Value::I32(v) => v.to_string(),
+        Value::U32(v) => v.to_string(),
+        Value::I64(v) => v.to_string(),
+        Value::U64(v) => v.to_string(),
+        Value::F64(v) => v.to_string(),
+        Value::Str(v) => v.to_string(),
+        Value::ObjectPath(v) => v.to_string(),
+        Value::Value(v) => render(v),
+        value => value.to_string(),
+    }
+}
+
+impl SegmentKind for DbusProperty {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match self.property() {
+            Ok(value) => Ok(fill_template(&self.config.format, &[("value", value)])),
+            Err(e) => {
+                let message = format!(
+                    "error reading d-bus property {} of {}: {}",
+                    self.config.property, self.config.service, e
+                );
+                warn!("{message}");
+                // reconnect on the next update
+                self.connection = None;
+                Err(message)
+            }
+        }
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        Some(watch_signals(self.config.bus, self.rule.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_values() {
+        assert_eq!(render(&Value::from(42u32)), "42");
+        assert_eq!(render(&Value::from("Playing")), "Playing");
+        assert_eq!(render(&Value::Value(Box::new(Value::from(true)))), "true");
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config = DbusConfig {
+            bus: Bus::Session,
+            service: "not a name".into(),
+            path: "/".into(),
+            interface: "org.example".into(),
+            property: "Property".into(),
+            format: format_default(),
+        };
+        assert!(DbusProperty::new(config).is_err());
+    }
+}
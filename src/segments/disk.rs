@@ -0,0 +1,163 @@
+use std::{ffi::CString, mem::MaybeUninit};
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::number_format::{NumberFormat, Scale};
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiskConfig {
+    pub mounts: Vec<String>,
+    /// `{mount}`, `{free}`, `{used}`, `{total}`, `{free_percent}` and `{used_percent}` are replaced
+    pub format: String,
+    /// the string between two mount points
+    pub separator: String,
+    /// how the sizes are displayed
+    pub number_format: NumberFormat,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            mounts: vec!["/".into()],
+            format: "{free}".into(),
+            separator: " ".into(),
+            number_format: NumberFormat {
+                scale: Scale::Iec,
+                short: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Displays the used and free space of file systems
+#[derive(Debug)]
+pub struct Disk {
+    config: DiskConfig,
+}
+
+/// sizes of a file system in bytes
+#[derive(Debug, Clone, Copy)]
+struct Usage {
+    total: u64,
+    used: u64,
+    /// available for unprivileged users, without the blocks reserved for root
+    free: u64,
+}
+
+impl Disk {
+    pub fn new(config: DiskConfig) -> Self {
+        Self { config }
+    }
+
+    fn usage(mount: &str) -> Result<Usage, String> {
+        let path = CString::new(mount).map_err(|e| e.to_string())?;
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        let stat = unsafe { stat.assume_init() };
+
+        // like df, the reserved blocks are neither used nor free
+        Ok(Usage {
+            total: stat.f_blocks * stat.f_frsize,
+            used: stat.f_blocks.saturating_sub(stat.f_bfree) * stat.f_frsize,
+            free: stat.f_bavail * stat.f_frsize,
+        })
+    }
+
+    fn format(&self, mount: &str, usage: Usage) -> String {
+        let bytes = |n: u64| self.config.number_format.format(n as f64);
+        // the percentages are of the space that unprivileged users can use, like in df
+        let percent = |n: u64| match usage.used + usage.free {
+            0 => 0,
+            usable => (n * 100 + usable / 2) / usable,
+        };
+
+        fill_template(
+            &self.config.format,
+            &[
+                ("mount", mount.to_string()),
+                ("free", bytes(usage.free)),
+                ("used", bytes(usage.used)),
+                ("total", bytes(usage.total)),
+                ("free_percent", percent(usage.free).to_string()),
+                ("used_percent", percent(usage.used).to_string()),
+            ],
+        )
+    }
+}
+
+impl SegmentKind for Disk {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let texts = self
+            .config
+            .mounts
+            .iter()
+            .map(|mount| match Self::usage(mount) {
+                Ok(usage) => Ok(self.format(mount, usage)),
+                Err(e) => {
+                    let message = format!("error reading disk usage of '{mount}': {e}");
+                    warn!("{message}");
+                    Err(message)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(texts.join(&self.config.separator))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        let format_bytes = |bytes: u64| DiskConfig::default().number_format.format(bytes as f64);
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(1536), "1.5K");
+        assert_eq!(format_bytes(50 * 1024 * 1024 * 1024), "50G");
+    }
+
+    #[test]
+    fn format() {
+        let disk = Disk::new(DiskConfig {
+            format: "{mount}: {used}/{total} ({used_percent}%)".into(),
+            ..Default::default()
+        });
+        let usage = Usage {
+            total: 4 * 1024 * 1024,
+            used: 3 * 1024 * 1024,
+            free: 1024 * 1024,
+        };
+        assert_eq!(disk.format("/", usage), "/: 3.0M/4.0M (75%)");
+        // reserved blocks don't count as used
+        let usage = Usage {
+            total: 20 * 1024 * 1024,
+            used: 9 * 1024 * 1024,
+            free: 9 * 1024 * 1024,
+        };
+        assert_eq!(disk.format("/", usage), "/: 9.0M/20M (50%)");
+    }
+
+    #[test]
+    fn root() {
+        let mut disk = Disk::new(Default::default());
+        assert!(disk.try_compute_value().is_ok());
+    }
+
+    #[test]
+    fn missing_mount() {
+        let mut disk = Disk::new(DiskConfig {
+            mounts: vec!["/does/not/exist".into()],
+            ..Default::default()
+        });
+        assert!(disk.try_compute_value().is_err());
+    }
+}
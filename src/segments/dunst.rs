@@ -0,0 +1,129 @@
+use async_std::channel::Receiver;
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use zbus::{
+    blocking::{fdo::PropertiesProxy, Connection},
+    message::Type,
+    names::InterfaceName,
+    MatchRule,
+};
+
+use super::SegmentKind;
+use crate::dbus::{watch_signals, Bus};
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DunstConfig {
+    /// displayed while notifications are paused, `{count}` is replaced by the number of waiting notifications
+    pub paused: String,
+    /// displayed while notifications are shown, `{count}` is replaced by the number of displayed notifications
+    pub running: String,
+}
+
+impl Default for DunstConfig {
+    fn default() -> Self {
+        Self {
+            paused: "paused {count}".into(),
+            running: "".into(),
+        }
+    }
+}
+
+const DUNST_PATH: &str = "/org/freedesktop/Notifications";
+const DUNST_INTERFACE: &str = "org.dunstproject.cmd0";
+
+/// Displays whether dunst is paused and how many notifications are waiting.
+/// The segment is updated as soon as dunst reports a change.
+#[derive(Debug)]
+pub struct Dunst {
+    config: DunstConfig,
+    connection: Option<Connection>,
+}
+
+impl Dunst {
+    pub fn new(config: DunstConfig) -> Self {
+        Self {
+            config,
+            connection: None,
+        }
+    }
+
+    /// returns whether dunst is paused and the number of waiting and displayed notifications
+    fn state(&mut self) -> zbus::Result<(bool, u32, u32)> {
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(Bus::Session.connect()?),
+        };
+
+        let properties = PropertiesProxy::builder(connection)
+            .destination("org.freedesktop.Notifications")?
+            .path(DUNST_PATH)?
+            .build()?
+            .get_all(InterfaceName::from_static_str_unchecked(DUNST_INTERFACE))?;
+
+        let get = |name: &str| {
+            properties
+                .get(name)
+                .ok_or_else(|| zbus::Error::Failure(format!("dunst has no property {name}")))
+        };
+        Ok((
+            get("paused")?.downcast_ref::<bool>()?,
+            get("waitingLength")?.downcast_ref::<u32>()?,
+            get("displayedLength")?.downcast_ref::<u32>()?,
+        ))
+    }
+
+    fn format(&self, paused: bool, waiting: u32, displayed: u32) -> String {
+        let (template, count) = if paused {
+            (&self.config.paused, waiting)
+        } else {
+            (&self.config.running, displayed)
+        };
+        fill_template(template, &[("count", count.to_string())])
+    }
+}
+
+impl SegmentKind for Dunst {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match self.state() {
+            Ok((paused, waiting, displayed)) => Ok(self.format(paused, waiting, displayed)),
+            Err(e) => {
+                let message = format!("error reading state of dunst: {e}");
+                warn!("{message}");
+                // reconnect on the next update
+                self.connection = None;
+                Err(message)
+            }
+        }
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        // dunst emits PropertiesChanged when it is paused or the notification queues change
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .path(DUNST_PATH)
+            .unwrap()
+            .build();
+        Some(watch_signals(Bus::Session, rule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        let dunst = Dunst::new(Default::default());
+        assert_eq!(dunst.format(true, 3, 0), "paused 3");
+        assert_eq!(dunst.format(false, 0, 2), "");
+
+        let dunst = Dunst::new(DunstConfig {
+            running: "{count}".into(),
+            ..Default::default()
+        });
+        assert_eq!(dunst.format(false, 0, 2), "2");
+    }
+}
@@ -0,0 +1,86 @@
+use std::{fs::read_to_string, io::ErrorKind, path::PathBuf};
+
+use async_std::channel::Receiver;
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::inotify::watch_for_changes;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub path: String,
+    /// only display the last non-empty line of the file
+    #[serde(default)]
+    pub last_line: bool,
+}
+
+/// Displays the contents of a file.
+/// The segment is updated as soon as the file changes,
+/// so scripts can simply write their output to the file.
+#[derive(Debug)]
+pub struct File {
+    path: PathBuf,
+    last_line: bool,
+}
+
+impl File {
+    pub fn new(path: PathBuf, last_line: bool) -> Self {
+        Self { path, last_line }
+    }
+
+    fn format(&self, content: &str) -> String {
+        if self.last_line {
+            content
+                .lines()
+                .rev()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        } else {
+            content.trim().to_string()
+        }
+    }
+}
+
+impl SegmentKind for File {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match read_to_string(&self.path) {
+            Ok(content) => Ok(self.format(&content)),
+            // the file may be created later
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok("".into()),
+            Err(e) => {
+                let message = format!("error reading '{}': {}", self.path.display(), e);
+                warn!("{message}");
+                Err(message)
+            }
+        }
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        Some(watch_for_changes(vec![self.path.clone()]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn contents() {
+        let path = env::temp_dir().join(format!("dwmblocksrs-file-{}", std::process::id()));
+        let mut file = File::new(path.clone(), false);
+        assert_eq!(file.compute_value(), "");
+
+        fs::write(&path, "first\nsecond\n\n").unwrap();
+        assert_eq!(file.compute_value(), "first\nsecond");
+        let mut file = File::new(path.clone(), true);
+        assert_eq!(file.compute_value(), "second");
+
+        fs::remove_file(path).unwrap();
+    }
+}
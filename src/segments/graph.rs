@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+
+use async_std::channel::Receiver;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::{Placement, SegmentKind, ValueStream};
+use crate::number_format::first_number;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct GraphConfig {
+    /// the number of displayed values
+    #[serde(default = "samples_default")]
+    pub samples: usize,
+    /// the value of the lowest bar, the lowest displayed value by default
+    pub min: Option<f64>,
+    /// the value of the highest bar, the highest displayed value by default
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub position: Placement,
+}
+
+fn samples_default() -> usize {
+    10
+}
+
+/// Displays the last values of another segment kind as a sparkline, e.g. `▁▂▃▅▇`.
+/// The first number in each value is used.
+#[derive(Debug)]
+pub struct Graph {
+    inner: Box<dyn SegmentKind>,
+    config: GraphConfig,
+    samples: VecDeque<f64>,
+}
+
+impl Graph {
+    pub fn new(inner: Box<dyn SegmentKind>, config: GraphConfig) -> Self {
+        Self {
+            inner,
+            config,
+            samples: VecDeque::with_capacity(config.samples),
+        }
+    }
+
+    fn add(&mut self, value: String) -> String {
+        if let Some(sample) = first_number(&value) {
+            if self.samples.len() == self.config.samples {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(sample);
+        }
+
+        self.config.position.place(self.sparkline(), value)
+    }
+
+    fn sparkline(&self) -> String {
+        let lowest = self.samples.iter().copied().fold(f64::INFINITY, f64::min);
+        let highest = self
+            .samples
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let min = self.config.min.unwrap_or(lowest);
+        let max = self.config.max.unwrap_or(highest);
+
+        self.samples
+            .iter()
+            .map(|sample| {
+                let level = match max - min {
+                    range if range > 0.0 => (sample - min) / range,
+                    _ => 0.0,
+                };
+                let bar = (level * (BARS.len() - 1) as f64).round();
+                BARS[bar.clamp(0.0, (BARS.len() - 1) as f64) as usize]
+            })
+            .collect()
+    }
+}
+
+impl SegmentKind for Graph {
+    fn compute_value(&mut self) -> String {
+        let value = self.inner.compute_value();
+        self.add(value)
+    }
+
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let value = self.inner.try_compute_value()?;
+        Ok(self.add(value))
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        self.inner.update_requests()
+    }
+
+    fn values(&mut self) -> Option<ValueStream> {
+        self.inner.values()
+    }
+
+    fn control_signals(&self) -> Vec<u32> {
+        self.inner.control_signals()
+    }
+
+    fn handle_signal(&mut self, signal_offset: u32) {
+        self.inner.handle_signal(signal_offset)
+    }
+
+    fn set_value(&mut self, value: String) -> bool {
+        self.inner.set_value(value)
+    }
+
+    fn next_view(&mut self) {
+        self.inner.next_view()
+    }
+
+    fn runs_commands(&self) -> bool {
+        self.inner.runs_commands()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segments::manual::Manual;
+
+    fn graph(config: GraphConfig) -> Graph {
+        Graph::new(Box::new(Manual::new("".into())), config)
+    }
+
+    #[test]
+    fn sparkline() {
+        let mut graph = graph(GraphConfig {
+            samples: 3,
+            min: Some(0.0),
+            max: Some(100.0),
+            position: Placement::After,
+        });
+        let mut add = |value: &str| {
+            graph.set_value(value.into());
+            graph.compute_value()
+        };
+        assert_eq!(add("0%"), "0% ▁");
+        assert_eq!(add("50%"), "50% ▁▅");
+        assert_eq!(add("cpu 100.0%"), "cpu 100.0% ▁▅█");
+        assert_eq!(add("25"), "25 ▅█▃");
+        assert_eq!(add("n/a"), "n/a ▅█▃");
+    }
+
+    #[test]
+    fn automatic_range() {
+        let mut graph = graph(GraphConfig {
+            samples: 10,
+            min: None,
+            max: None,
+            position: Placement::Instead,
+        });
+        for value in ["7", "7"] {
+            graph.set_value(value.into());
+            graph.compute_value();
+        }
+        assert_eq!(graph.sparkline(), "▁▁");
+        graph.set_value("14".into());
+        graph.compute_value();
+        graph.set_value("21".into());
+        assert_eq!(graph.compute_value(), "▁▁▅█");
+    }
+}
@@ -0,0 +1,113 @@
+use async_std::channel::{self, Receiver};
+use async_std::task;
+
+use super::{Segment, SegmentKind};
+
+/// Displays several segments as one, separated by the separator.
+/// The segments inside are updated together with the group, their own intervals and signals are not used.
+/// The group can be collapsed by a signal, it then shows the collapsed text instead.
+#[derive(Debug)]
+pub struct Group {
+    segments: Vec<Segment>,
+    separator: String,
+    collapse_signal: Option<u32>,
+    collapsed_text: String,
+    collapsed: bool,
+}
+
+impl Group {
+    pub fn new(
+        segments: Vec<Segment>,
+        separator: String,
+        collapse_signal: Option<u32>,
+        collapsed_text: String,
+    ) -> Self {
+        Self {
+            segments,
+            separator,
+            collapse_signal,
+            collapsed_text,
+            collapsed: false,
+        }
+    }
+}
+
+impl SegmentKind for Group {
+    fn compute_value(&mut self) -> String {
+        if self.collapsed {
+            return self.collapsed_text.clone();
+        }
+        self.segments
+            .iter_mut()
+            .map(|segment| segment.compute_value())
+            // hidden segments don't leave two separators behind
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+            .join(&self.separator)
+    }
+
+    /// updates the group when a segment inside requests it
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        merge_update_requests(&self.segments)
+    }
+
+    fn control_signals(&self) -> Vec<u32> {
+        self.collapse_signal.into_iter().collect()
+    }
+
+    fn handle_signal(&mut self, signal_offset: u32) {
+        if self.collapse_signal == Some(signal_offset) {
+            self.collapsed = !self.collapsed;
+        }
+    }
+}
+
+/// the update requests of all the segments in one channel
+pub(crate) fn merge_update_requests(segments: &[Segment]) -> Option<Receiver<()>> {
+    let requests = segments
+        .iter()
+        .filter_map(|segment| segment.kind.lock().unwrap().update_requests())
+        .collect::<Vec<_>>();
+    if requests.is_empty() {
+        return None;
+    }
+
+    let (tx, rx) = channel::bounded(1);
+    for requests in requests {
+        let tx = tx.clone();
+        task::spawn(async move {
+            while requests.recv().await.is_ok() {
+                if let Err(channel::TrySendError::Closed(_)) = tx.try_send(()) {
+                    return;
+                }
+            }
+        });
+    }
+    Some(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segments::constant::Constant;
+
+    fn constant(text: &str) -> Segment {
+        Segment::builder(Box::new(Constant::new(text.into())))
+            .hide_if_empty(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn join() {
+        let segments = vec![constant("a"), constant(""), constant("b")];
+        let mut group = Group::new(segments, " | ".into(), Some(3), "…".into());
+        assert_eq!(group.compute_value(), "a | b");
+        assert_eq!(group.control_signals(), [3]);
+
+        group.handle_signal(3);
+        assert_eq!(group.compute_value(), "…");
+        group.handle_signal(3);
+        assert_eq!(group.compute_value(), "a | b");
+    }
+}
@@ -0,0 +1,200 @@
+use std::process::Command;
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use zbus::{blocking::Connection, zvariant::OwnedFd};
+
+use super::{SegmentKind, SignalValue};
+use crate::dbus::Bus;
+
+/// How idling is detected and inhibited
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleInhibitBackend {
+    /// idle inhibitors of systemd-logind
+    #[default]
+    Logind,
+    /// the DPMS and screensaver state of the X server, changed with `xset`
+    Xset,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdleInhibitConfig {
+    pub backend: IdleInhibitBackend,
+    /// SIGRTMIN+`toggle` inhibits idling or stops inhibiting it
+    #[schemars(with = "Option<SignalValue>")]
+    pub toggle: Option<u32>,
+    /// displayed while idling is inhibited
+    pub inhibited: String,
+    /// displayed otherwise
+    pub uninhibited: String,
+}
+
+impl Default for IdleInhibitConfig {
+    fn default() -> Self {
+        Self {
+            backend: Default::default(),
+            toggle: None,
+            inhibited: "☕".into(),
+            uninhibited: "".into(),
+        }
+    }
+}
+
+/// (what, who, why, mode, uid, pid) as returned by `ListInhibitors`
+type Inhibitor = (String, String, String, String, u32, u32);
+
+/// Displays whether idling (screen locking, blanking, ...) is inhibited
+/// and optionally toggles the inhibition with a signal
+#[derive(Debug)]
+pub struct IdleInhibit {
+    config: IdleInhibitConfig,
+    connection: Option<Connection>,
+    /// the inhibitor lock taken by us, idling is inhibited as long as it is open
+    inhibitor: Option<OwnedFd>,
+}
+
+impl IdleInhibit {
+    pub fn new(config: IdleInhibitConfig) -> Self {
+        Self {
+            config,
+            connection: None,
+            inhibitor: None,
+        }
+    }
+
+    fn call_logind<B>(&mut self, method: &str, body: &B) -> zbus::Result<zbus::message::Message>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(Bus::System.connect()?),
+        };
+        connection.call_method(
+            Some("org.freedesktop.login1"),
+            "/org/freedesktop/login1",
+            Some("org.freedesktop.login1.Manager"),
+            method,
+            body,
+        )
+    }
+
+    fn inhibited(&mut self) -> Result<bool, String> {
+        match self.config.backend {
+            IdleInhibitBackend::Logind => {
+                let inhibitors = self
+                    .call_logind("ListInhibitors", &())
+                    .and_then(|reply| reply.body().deserialize::<Vec<Inhibitor>>())
+                    .map_err(|e| {
+                        // reconnect on the next update
+                        self.connection = None;
+                        format!("error listing the inhibitors of logind: {e}")
+                    })?;
+                Ok(idle_inhibited(&inhibitors))
+            }
+            IdleInhibitBackend::Xset => {
+                let output = Command::new("xset")
+                    .arg("q")
+                    .output()
+                    .map_err(|e| format!("error running xset: {e}"))?;
+                Ok(dpms_disabled(&String::from_utf8_lossy(&output.stdout)))
+            }
+        }
+    }
+
+    fn toggle(&mut self) -> Result<(), String> {
+        match self.config.backend {
+            IdleInhibitBackend::Logind => {
+                // closing our lock releases it
+                if self.inhibitor.take().is_some() {
+                    return Ok(());
+                }
+                let reason = "toggled in the status bar";
+                let fd = self
+                    .call_logind("Inhibit", &("idle", "dwmblocksrs", reason, "block"))
+                    .and_then(|reply| reply.body().deserialize::<OwnedFd>())
+                    .map_err(|e| format!("error inhibiting idling with logind: {e}"))?;
+                self.inhibitor = Some(fd);
+            }
+            IdleInhibitBackend::Xset => {
+                let args: &[&str] = if self.inhibited()? {
+                    &["+dpms", "s", "on"]
+                } else {
+                    &["-dpms", "s", "off"]
+                };
+                Command::new("xset")
+                    .args(args)
+                    // stdout can be the output of the status bar
+                    .stdout(std::io::stderr())
+                    .status()
+                    .map_err(|e| format!("error running xset: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn idle_inhibited(inhibitors: &[Inhibitor]) -> bool {
+    inhibitors
+        .iter()
+        .any(|(what, _, _, mode, _, _)| mode == "block" && what.split(':').any(|w| w == "idle"))
+}
+
+fn dpms_disabled(xset_output: &str) -> bool {
+    xset_output.contains("DPMS is Disabled")
+}
+
+impl SegmentKind for IdleInhibit {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match self.inhibited() {
+            Ok(true) => Ok(self.config.inhibited.clone()),
+            Ok(false) => Ok(self.config.uninhibited.clone()),
+            Err(message) => {
+                warn!("{message}");
+                Err(message)
+            }
+        }
+    }
+
+    fn control_signals(&self) -> Vec<u32> {
+        self.config.toggle.into_iter().collect()
+    }
+
+    fn handle_signal(&mut self, signal_offset: u32) {
+        if self.config.toggle == Some(signal_offset) {
+            if let Err(e) = self.toggle() {
+                warn!("{e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inhibitor(what: &str, mode: &str) -> Inhibitor {
+        (what.into(), "".into(), "".into(), mode.into(), 0, 0)
+    }
+
+    #[test]
+    fn logind_inhibitors() {
+        assert!(!idle_inhibited(&[inhibitor("sleep", "block")]));
+        assert!(!idle_inhibited(&[inhibitor("idle", "delay")]));
+        assert!(idle_inhibited(&[
+            inhibitor("sleep", "delay"),
+            inhibitor("sleep:idle", "block")
+        ]));
+    }
+
+    #[test]
+    fn xset() {
+        assert!(dpms_disabled("DPMS (Energy Star):\n  DPMS is Disabled\n"));
+        assert!(!dpms_disabled(
+            "DPMS (Energy Star):\n  Standby: 600\n  DPMS is Enabled\n"
+        ));
+    }
+}
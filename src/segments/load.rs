@@ -0,0 +1,117 @@
+use std::fs::read_to_string;
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoadConfig {
+    /// `{load1}`, `{load5}`, `{load15}`, `{uptime}`, `{days}`, `{hours}` and `{minutes}` are replaced
+    pub format: String,
+}
+
+impl Default for LoadConfig {
+    fn default() -> Self {
+        Self {
+            format: "{load1} {load5} {load15}".into(),
+        }
+    }
+}
+
+/// Displays the load average and the uptime
+#[derive(Debug)]
+pub struct Load {
+    config: LoadConfig,
+}
+
+impl Load {
+    pub fn new(config: LoadConfig) -> Self {
+        Self { config }
+    }
+
+    fn format(&self, loadavg: &str, uptime: &str) -> Option<String> {
+        let mut loads = loadavg.split_whitespace();
+        let load1 = loads.next()?;
+        let load5 = loads.next()?;
+        let load15 = loads.next()?;
+
+        let seconds = uptime.split_whitespace().next()?.parse::<f64>().ok()? as u64;
+        let days = seconds / 86400;
+        let hours = seconds % 86400 / 3600;
+        let minutes = seconds % 3600 / 60;
+
+        let uptime = match (days, hours) {
+            (0, 0) => format!("{minutes}m"),
+            (0, _) => format!("{hours}h {minutes}m"),
+            _ => format!("{days}d {hours}h {minutes}m"),
+        };
+
+        Some(fill_template(
+            &self.config.format,
+            &[
+                ("load1", load1.to_string()),
+                ("load5", load5.to_string()),
+                ("load15", load15.to_string()),
+                ("uptime", uptime),
+                ("days", days.to_string()),
+                ("hours", hours.to_string()),
+                ("minutes", minutes.to_string()),
+            ],
+        ))
+    }
+}
+
+impl SegmentKind for Load {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let loadavg = read_to_string("/proc/loadavg").unwrap_or_default();
+        let uptime = read_to_string("/proc/uptime").unwrap_or_default();
+
+        self.format(&loadavg, &uptime).ok_or_else(|| {
+            let message = "error reading /proc/loadavg and /proc/uptime".to_string();
+            warn!("{message}");
+            message
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOADAVG: &str = "0.52 0.58 0.59 1/389 12345\n";
+
+    #[test]
+    fn load() {
+        let load = Load::new(Default::default());
+        assert_eq!(
+            load.format(LOADAVG, "100.0 200.0").unwrap(),
+            "0.52 0.58 0.59"
+        );
+    }
+
+    #[test]
+    fn uptime() {
+        let load = Load::new(LoadConfig {
+            format: "{uptime}".into(),
+        });
+        assert_eq!(load.format(LOADAVG, "59.9 0").unwrap(), "0m");
+        assert_eq!(load.format(LOADAVG, "7260.5 0").unwrap(), "2h 1m");
+        assert_eq!(load.format(LOADAVG, "180000.0 0").unwrap(), "2d 2h 0m");
+    }
+
+    #[test]
+    fn invalid() {
+        let load = Load::new(Default::default());
+        assert_eq!(load.format("", ""), None);
+    }
+
+    #[test]
+    fn proc() {
+        let mut load = Load::new(Default::default());
+        assert!(load.try_compute_value().is_ok());
+    }
+}
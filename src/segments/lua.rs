@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use mlua::{Function, Lua as Interpreter, MultiValue, Value};
+
+use super::SegmentKind;
+use crate::color::Color;
+
+/// Runs a Lua script on every update and displays the text it returns.
+/// The script can return a color name as second value, which replaces the text color.
+/// Global variables are kept between the runs.
+#[derive(Debug)]
+pub struct Lua {
+    lua: Interpreter,
+    script: Function,
+    colors: HashMap<String, Color>,
+    color: Option<Color>,
+}
+
+impl Lua {
+    /// `colors` are the colors the script can choose from
+    pub fn new(code: &str, colors: HashMap<String, Color>) -> Result<Self, String> {
+        let lua = Interpreter::new();
+        let script = lua
+            .load(code)
+            .set_name("segment")
+            .into_function()
+            .map_err(|e| format!("invalid lua script: {e}"))?;
+        Ok(Self {
+            lua,
+            script,
+            colors,
+            color: None,
+        })
+    }
+
+    fn run(&mut self) -> Result<String, String> {
+        let values = self
+            .script
+            .call::<MultiValue>(())
+            .map_err(|e| e.to_string())?;
+        let mut values = values.into_iter();
+        let text = match values.next() {
+            Some(Value::Nil) | None => String::new(),
+            Some(value) => self
+                .lua
+                .coerce_string(value)
+                .ok()
+                .flatten()
+                .map(|text| text.to_string_lossy())
+                .ok_or("the lua script returned no text")?,
+        };
+        self.color = match values.next() {
+            Some(Value::String(name)) => {
+                let name = name.to_string_lossy();
+                let color = self.colors.get(&name).ok_or(format!(
+                    "the lua script returned the undefined color {name}"
+                ))?;
+                Some(*color)
+            }
+            Some(Value::Nil) | None => None,
+            Some(_) => return Err("the lua script returned no color name".into()),
+        };
+        Ok(text)
+    }
+}
+
+impl SegmentKind for Lua {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let value = self.run();
+        if value.is_err() {
+            self.color = None;
+        }
+        value
+    }
+
+    fn color(&self) -> Option<Color> {
+        self.color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state() {
+        let mut lua = Lua::new("n = (n or 0) + 1 return 'run ' .. n", HashMap::new()).unwrap();
+        assert_eq!(lua.compute_value(), "run 1");
+        assert_eq!(lua.compute_value(), "run 2");
+        assert_eq!(lua.color(), None);
+    }
+
+    #[test]
+    fn color() {
+        let colors = HashMap::from([("red".to_string(), Color::Colored(2))]);
+        let mut lua = Lua::new("return 42, 'red'", colors).unwrap();
+        assert_eq!(lua.try_compute_value(), Ok("42".into()));
+        assert_eq!(lua.color(), Some(Color::Colored(2)));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Lua::new("return (", HashMap::new()).is_err());
+        let mut lua = Lua::new("error('no network')", HashMap::new()).unwrap();
+        assert!(lua.try_compute_value().unwrap_err().contains("no network"));
+        let mut lua = Lua::new("return 'x', 'blue'", HashMap::new()).unwrap();
+        assert!(lua.try_compute_value().is_err());
+    }
+}
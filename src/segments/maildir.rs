@@ -0,0 +1,94 @@
+use std::{fs::read_dir, path::PathBuf};
+
+use async_std::channel::Receiver;
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::inotify::watch_for_changes;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MaildirConfig {
+    pub paths: Vec<String>,
+    #[serde(default = "format_default")]
+    pub format: String,
+}
+
+fn format_default() -> String {
+    "{count}".into()
+}
+
+/// Displays the number of unread mails in maildirs.
+/// The segment is updated as soon as a mail arrives or is read.
+#[derive(Debug)]
+pub struct Maildir {
+    /// the `new` directories of the maildirs
+    directories: Vec<PathBuf>,
+    /// `{count}` is replaced by the number of unread mails
+    format: String,
+}
+
+impl Maildir {
+    pub fn new(maildirs: Vec<PathBuf>, format: String) -> Self {
+        let directories = maildirs.into_iter().map(|dir| dir.join("new")).collect();
+        Self {
+            directories,
+            format,
+        }
+    }
+}
+
+impl SegmentKind for Maildir {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let mut count = 0;
+        for directory in &self.directories {
+            match read_dir(directory) {
+                Ok(entries) => count += entries.count(),
+                Err(e) => {
+                    let message = format!("error reading maildir '{}': {}", directory.display(), e);
+                    warn!("{message}");
+                    return Err(message);
+                }
+            }
+        }
+
+        // no unread mails, so the segment can be hidden
+        if count == 0 {
+            return Ok("".into());
+        }
+        Ok(fill_template(&self.format, &[("count", count.to_string())]))
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        Some(watch_for_changes(self.directories.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn count() {
+        let dir = env::temp_dir().join(format!("dwmblocksrs-maildir-{}", std::process::id()));
+        fs::create_dir_all(dir.join("new")).unwrap();
+        let mut maildir = Maildir::new(vec![dir.clone()], "{count} new".into());
+
+        assert_eq!(maildir.compute_value(), "");
+        fs::write(dir.join("new").join("mail1"), "").unwrap();
+        fs::write(dir.join("new").join("mail2"), "").unwrap();
+        assert_eq!(maildir.compute_value(), "2 new");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing() {
+        let mut maildir = Maildir::new(vec!["/does/not/exist".into()], "{count}".into());
+        assert!(maildir.try_compute_value().is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use super::SegmentKind;
+
+/// Displays text that is set from outside, e.g. with `dwmblocksrs set`.
+/// The text is kept until it is set again.
+#[derive(Debug)]
+pub struct Manual {
+    text: String,
+}
+
+impl Manual {
+    pub fn new(text: String) -> Self {
+        Self { text }
+    }
+}
+
+impl SegmentKind for Manual {
+    fn compute_value(&mut self) -> String {
+        self.text.clone()
+    }
+
+    fn set_value(&mut self, value: String) -> bool {
+        self.text = value;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let mut manual = Manual::new("initial".into());
+        assert_eq!(manual.compute_value(), "initial");
+        assert!(manual.set_value("muted".into()));
+        assert_eq!(manual.compute_value(), "muted");
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    os::unix::net::UnixStream,
+    thread,
+    time::Duration,
+};
+
+use async_std::channel::{self, Receiver, Sender, TrySendError};
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MpdConfig {
+    /// host name or path of the unix socket
+    pub host: String,
+    pub port: u16,
+    /// `{artist}`, `{title}`, `{album}` and `{file}` are replaced
+    pub playing: String,
+    pub paused: String,
+    pub stopped: String,
+}
+
+impl Default for MpdConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".into(),
+            port: 6600,
+            playing: "{artist} - {title}".into(),
+            paused: "{artist} - {title} (paused)".into(),
+            stopped: "".into(),
+        }
+    }
+}
+
+type Connection = (BufReader<Box<dyn Read + Send>>, Box<dyn Write + Send>);
+
+/// Displays the song that mpd currently plays.
+/// The segment is updated as soon as the song or the state of the player changes.
+#[derive(Debug)]
+pub struct Mpd {
+    config: MpdConfig,
+}
+
+impl Mpd {
+    pub fn new(config: MpdConfig) -> Self {
+        Self { config }
+    }
+
+    fn connect(config: &MpdConfig) -> io::Result<Connection> {
+        let (reader, writer): (Box<dyn Read + Send>, Box<dyn Write + Send>) =
+            if config.host.starts_with('/') {
+                let stream = UnixStream::connect(&config.host)?;
+                (Box::new(stream.try_clone()?), Box::new(stream))
+            } else {
+                let stream = TcpStream::connect((config.host.as_str(), config.port))?;
+                (Box::new(stream.try_clone()?), Box::new(stream))
+            };
+        let mut connection = (BufReader::new(reader), writer);
+
+        let mut greeting = String::new();
+        connection.0.read_line(&mut greeting)?;
+        if !greeting.starts_with("OK MPD") {
+            return Err(io::Error::other(format!(
+                "unexpected greeting '{}'",
+                greeting.trim()
+            )));
+        }
+        Ok(connection)
+    }
+
+    /// sends the command and returns the key value pairs of the response
+    fn command(connection: &mut Connection, command: &str) -> io::Result<HashMap<String, String>> {
+        writeln!(connection.1, "{command}")?;
+        connection.1.flush()?;
+
+        let mut response = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if connection.0.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let line = line.trim_end();
+            if line == "OK" {
+                return Ok(response);
+            }
+            if line.starts_with("ACK") {
+                return Err(io::Error::other(line.to_string()));
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                response.insert(key.to_lowercase(), value.to_string());
+            }
+        }
+    }
+
+    fn format(&self, status: &HashMap<String, String>, song: &HashMap<String, String>) -> String {
+        let template = match status.get("state").map(String::as_str) {
+            Some("play") => &self.config.playing,
+            Some("pause") => &self.config.paused,
+            _ => return self.config.stopped.clone(),
+        };
+
+        let field = |key: &str| song.get(key).cloned().unwrap_or_default();
+        // songs without tags at least show their file name
+        let title = song
+            .get("title")
+            .cloned()
+            .unwrap_or_else(|| field("file").rsplit('/').next().unwrap().to_string());
+
+        fill_template(
+            template,
+            &[
+                ("artist", field("artist")),
+                ("title", title),
+                ("album", field("album")),
+                ("file", field("file")),
+            ],
+        )
+    }
+
+    /// Waits for changes of the player with mpd's idle command and requests updates.
+    /// If the connection is lost, mpd is reconnected.
+    fn subscribe(config: MpdConfig, requests: Sender<()>) {
+        loop {
+            if let Err(e) = Self::watch_player(&config, &requests) {
+                warn!("error waiting for mpd events: {e}");
+            }
+            if requests.is_closed() {
+                return;
+            }
+            thread::sleep(Duration::from_secs(5));
+        }
+    }
+
+    fn watch_player(config: &MpdConfig, requests: &Sender<()>) -> io::Result<()> {
+        let mut connection = Self::connect(config)?;
+        // the connection might have been lost, so the state could have changed in the meantime
+        let _ = requests.try_send(());
+        loop {
+            Self::command(&mut connection, "idle player")?;
+            if let Err(TrySendError::Closed(_)) = requests.try_send(()) {
+                // the segment does not exist anymore
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl SegmentKind for Mpd {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let result = Self::connect(&self.config).and_then(|mut connection| {
+            let status = Self::command(&mut connection, "status")?;
+            let song = Self::command(&mut connection, "currentsong")?;
+            Ok(self.format(&status, &song))
+        });
+
+        result.map_err(|e| {
+            let message = format!("error querying mpd: {e}");
+            warn!("{message}");
+            message
+        })
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        let (tx, rx) = channel::bounded(1);
+        let config = self.config.clone();
+        thread::spawn(move || Self::subscribe(config, tx));
+        Some(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn playing() {
+        let mpd = Mpd::new(Default::default());
+        let status = map(&[("state", "play")]);
+        let song = map(&[("artist", "Artist"), ("title", "Song")]);
+        assert_eq!(mpd.format(&status, &song), "Artist - Song");
+    }
+
+    #[test]
+    fn untagged() {
+        let mpd = Mpd::new(MpdConfig {
+            paused: "{title}".into(),
+            ..Default::default()
+        });
+        let status = map(&[("state", "pause")]);
+        let song = map(&[("file", "music/song.flac")]);
+        assert_eq!(mpd.format(&status, &song), "song.flac");
+    }
+
+    #[test]
+    fn stopped() {
+        let mpd = Mpd::new(Default::default());
+        assert_eq!(mpd.format(&map(&[("state", "stop")]), &map(&[])), "");
+    }
+
+    #[test]
+    fn command() {
+        let (server, client) = UnixStream::pair().unwrap();
+        let mut connection: Connection = (
+            BufReader::new(Box::new(client.try_clone().unwrap())),
+            Box::new(client),
+        );
+        let mut server_writer = server.try_clone().unwrap();
+        server_writer
+            .write_all(b"state: play\nvolume: 50\nOK\n")
+            .unwrap();
+        let response = Mpd::command(&mut connection, "status").unwrap();
+        assert_eq!(response.get("state").unwrap(), "play");
+        assert_eq!(response.get("volume").unwrap(), "50");
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    io::{BufRead, BufReader},
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use async_std::channel::{self, Receiver, Sender, TrySendError};
+use log::warn;
+
+use super::{
+    program_output::{kill_group, Running},
+    SegmentKind,
+};
+
+/// the delay before the first restart, it doubles with every quick exit
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// if the command ran for at least this long, the backoff is reset
+const STABLE_RUNTIME: Duration = Duration::from_secs(60);
+
+/// Runs a long-running shell command and displays the last line it printed.
+/// The segment is updated for every line.
+/// If the command exits, it is restarted after a delay that grows when it exits quickly.
+/// When the segment is dropped, e.g. on reload, the command and the processes it started are killed.
+#[derive(Debug)]
+pub struct Persistent {
+    command: String,
+    /// the last line printed by the command
+    line: Arc<Mutex<String>>,
+    process: Arc<Mutex<Process>>,
+}
+
+/// The command that is running, shared with the thread that runs it
+#[derive(Debug, Default)]
+struct Process {
+    /// the process group of the command
+    group: Option<u32>,
+    /// set when the segment is dropped, the command is not started again
+    stopped: bool,
+}
+
+impl Persistent {
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            line: Default::default(),
+            process: Default::default(),
+        }
+    }
+}
+
+impl Drop for Persistent {
+    fn drop(&mut self) {
+        let mut process = self.process.lock().unwrap();
+        process.stopped = true;
+        if let Some(group) = process.group {
+            kill_group(group);
+        }
+    }
+}
+
+/// runs the command until it exits, returns false if the segment does not exist anymore
+fn run(
+    command: &str,
+    line: &Mutex<String>,
+    requests: &Sender<()>,
+    process: &Mutex<Process>,
+) -> std::io::Result<bool> {
+    let mut child = {
+        let mut process = process.lock().unwrap();
+        if process.stopped {
+            return Ok(false);
+        }
+        let child = unsafe {
+            Command::new("/bin/sh")
+                .arg("-c")
+                .arg(command)
+                .stdout(Stdio::piped())
+                // the processes the command starts are killed with it
+                .process_group(0)
+                // terminate the command together with us
+                .pre_exec(|| {
+                    libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+                    Ok(())
+                })
+                .spawn()?
+        };
+        process.group = Some(child.id());
+        child
+    };
+    let _running = Running::new(child.id());
+
+    let stdout = child.stdout.take().unwrap();
+    let mut error = None;
+    for new_line in BufReader::new(stdout).lines() {
+        let new_line = match new_line {
+            Ok(new_line) => new_line,
+            Err(e) => {
+                // the command is restarted
+                kill_group(child.id());
+                error = Some(e);
+                break;
+            }
+        };
+        *line.lock().unwrap() = new_line;
+        if let Err(TrySendError::Closed(_)) = requests.try_send(()) {
+            kill_group(child.id());
+            break;
+        }
+    }
+
+    // the group is forgotten before the command is waited for, so its id cannot be reused yet
+    let stopped = {
+        let mut process = process.lock().unwrap();
+        process.group = None;
+        process.stopped || requests.is_closed()
+    };
+    let status = child.wait()?;
+    if stopped {
+        return Ok(false);
+    }
+    if let Some(e) = error {
+        return Err(e);
+    }
+    warn!("persistent command '{command}' exited ({status})");
+    Ok(true)
+}
+
+fn keep_running(
+    command: String,
+    line: Arc<Mutex<String>>,
+    requests: Sender<()>,
+    process: Arc<Mutex<Process>>,
+) {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let started = Instant::now();
+        match run(&command, &line, &requests, &process) {
+            Ok(false) => return,
+            Ok(true) => {}
+            Err(e) => warn!("error running persistent command '{command}': {e}"),
+        }
+
+        if started.elapsed() >= STABLE_RUNTIME {
+            backoff = MIN_BACKOFF;
+        }
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+
+        if requests.is_closed() || process.lock().unwrap().stopped {
+            return;
+        }
+    }
+}
+
+impl SegmentKind for Persistent {
+    fn compute_value(&mut self) -> String {
+        self.line.lock().unwrap().clone()
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        let (tx, rx) = channel::bounded(1);
+        let command = self.command.clone();
+        let line = self.line.clone();
+        let process = self.process.clone();
+        thread::spawn(move || keep_running(command, line, tx, process));
+        Some(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn lines() {
+        let mut persistent = Persistent::new("echo first; sleep 0.2; echo second; sleep 10".into());
+        assert_eq!(persistent.compute_value(), "");
+
+        let requests = persistent.update_requests().unwrap();
+        let next_line = || async_std::future::timeout(Duration::from_secs(5), requests.recv());
+
+        next_line().await.unwrap().unwrap();
+        assert_eq!(persistent.compute_value(), "first");
+        next_line().await.unwrap().unwrap();
+        assert_eq!(persistent.compute_value(), "second");
+    }
+
+    /// whether the process exited, zombies included
+    fn exited(pid: &str) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat")).map_or(true, |stat| {
+            stat.rsplit(") ").next().unwrap().starts_with('Z')
+        })
+    }
+
+    #[async_std::test]
+    async fn killed_when_dropped() {
+        // a quiet command that started another process
+        let mut persistent = Persistent::new("sleep 30 & echo $!; wait".into());
+        let requests = persistent.update_requests().unwrap();
+        async_std::future::timeout(Duration::from_secs(5), requests.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let sleep = persistent.compute_value();
+        assert!(!exited(&sleep));
+
+        drop(persistent);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !exited(&sleep) {
+            assert!(
+                Instant::now() < deadline,
+                "the started process is still running"
+            );
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    ffi::{c_char, c_void, CStr, CString},
+    path::Path,
+};
+
+use super::SegmentKind;
+
+/// A segment that a plugin created, the interface between dwmblocksrs and shared objects.
+///
+/// A plugin exports a function with the configured name and the C signature
+/// `bool create_segment(const char *options, struct PluginSegment *segment)`.
+/// It gets the options of the segment as JSON and fills in the segment, or returns false on errors.
+/// The functions of a segment are called from one thread at a time, which is not always the same.
+#[repr(C)]
+pub struct PluginSegment {
+    /// passed to the functions, e.g. the state of the segment
+    pub data: *mut c_void,
+    /// Returns the value as a NUL-terminated string, or NULL if it could not be computed.
+    /// The string has to stay valid until the next call.
+    pub compute_value: extern "C" fn(data: *mut c_void) -> *const c_char,
+    /// releases the data, called before the shared object is closed
+    pub free: extern "C" fn(data: *mut c_void),
+}
+
+type CreateSegment = extern "C" fn(options: *const c_char, segment: *mut PluginSegment) -> bool;
+
+/// Displays the value of a segment of a plugin
+#[derive(Debug)]
+pub struct Plugin {
+    library: *mut c_void,
+    segment: *mut PluginSegment,
+}
+
+// the plugin is only used by one thread at a time, as the interface requires,
+// and methods that take `&self` don't use it
+unsafe impl Send for Plugin {}
+unsafe impl Sync for Plugin {}
+
+impl Plugin {
+    /// Loads the shared object and creates the segment with the function of the symbol
+    pub fn load(path: &Path, symbol: &str, options: &str) -> Result<Self, String> {
+        let c_path = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| "the path of the plugin contains a NUL byte".to_string())?;
+        let c_symbol = CString::new(symbol)
+            .map_err(|_| "the symbol of the plugin contains a NUL byte".to_string())?;
+        let c_options = CString::new(options)
+            .map_err(|_| "the options of the plugin contain a NUL byte".to_string())?;
+
+        unsafe {
+            let library = libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+            if library.is_null() {
+                return Err(format!(
+                    "error loading plugin '{}': {}",
+                    path.display(),
+                    dl_error()
+                ));
+            }
+
+            let create = libc::dlsym(library, c_symbol.as_ptr());
+            if create.is_null() {
+                let error = dl_error();
+                libc::dlclose(library);
+                return Err(format!(
+                    "plugin '{}' has no function '{symbol}': {error}",
+                    path.display()
+                ));
+            }
+            let create: CreateSegment = std::mem::transmute(create);
+
+            let segment = Box::into_raw(Box::new(std::mem::MaybeUninit::<PluginSegment>::uninit()))
+                .cast::<PluginSegment>();
+            if !create(c_options.as_ptr(), segment) {
+                drop(Box::from_raw(
+                    segment.cast::<std::mem::MaybeUninit<PluginSegment>>(),
+                ));
+                libc::dlclose(library);
+                return Err(format!(
+                    "plugin '{}' could not create the segment",
+                    path.display()
+                ));
+            }
+
+            Ok(Self { library, segment })
+        }
+    }
+}
+
+/// the message of the last error of the dynamic linker
+fn dl_error() -> String {
+    unsafe {
+        let error = libc::dlerror();
+        match error.is_null() {
+            true => "unknown error".into(),
+            false => CStr::from_ptr(error).to_string_lossy().into_owned(),
+        }
+    }
+}
+
+impl SegmentKind for Plugin {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        unsafe {
+            let segment = &*self.segment;
+            let value = (segment.compute_value)(segment.data);
+            match value.is_null() {
+                true => Err("the plugin could not compute the value".into()),
+                false => Ok(CStr::from_ptr(value).to_string_lossy().into_owned()),
+            }
+        }
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        unsafe {
+            let segment = Box::from_raw(self.segment);
+            (segment.free)(segment.data);
+            libc::dlclose(self.library);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing() {
+        let error =
+            Plugin::load(Path::new("/does/not/exist.so"), "create_segment", "{}").unwrap_err();
+        assert!(error.contains("/does/not/exist.so"));
+    }
+}
@@ -1,52 +1,284 @@
-use std::{path::PathBuf, process::Command};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Read, Write},
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
 
+use lazy_static::lazy_static;
 use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
 
 use super::SegmentKind;
+use crate::command_cache::run_deduplicated;
+
+lazy_static! {
+    /// the process groups of the programs that are running
+    static ref RUNNING: Mutex<HashSet<u32>> = Mutex::new(HashSet::new());
+}
+
+/// Kills the programs that are still running and the processes they started, e.g. on shutdown
+pub(crate) fn kill_running() {
+    for group in RUNNING.lock().unwrap().iter() {
+        kill_group(*group);
+    }
+}
+
+pub(super) fn kill_group(group: u32) {
+    unsafe {
+        libc::kill(-(group as i32), libc::SIGKILL);
+    }
+}
+
+/// Keeps the process group registered as running until it is dropped
+pub(super) struct Running(u32);
+
+impl Running {
+    pub(super) fn new(group: u32) -> Self {
+        RUNNING.lock().unwrap().insert(group);
+        Self(group)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// What happens with output that is not valid UTF-8
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InvalidUtf8 {
+    /// invalid bytes are replaced by `�`
+    #[default]
+    Lossy,
+    /// the segment fails
+    Error,
+    /// nothing is displayed
+    Hide,
+}
 
 #[derive(Debug)]
 pub struct ProgramOutput {
     program: PathBuf,
     args: Vec<String>,
-    trim: bool
+    trim: bool,
+    deduplicate: bool,
+    timeout: Option<Duration>,
+    /// added to the environment of the program
+    env: HashMap<String, String>,
+    /// the working directory of the program
+    cwd: Option<PathBuf>,
+    /// written to the standard input of the program
+    stdin: Option<String>,
+    /// non-zero exit codes are errors, with the stderr of the program as message
+    fail_on_error: bool,
+    on_invalid_utf8: InvalidUtf8,
 }
 
 impl ProgramOutput {
     pub fn new(program: PathBuf, args: Vec<String>, trim: bool) -> Self {
-        ProgramOutput { program, args , trim}
+        ProgramOutput {
+            program,
+            args,
+            trim,
+            deduplicate: false,
+            timeout: None,
+            env: HashMap::new(),
+            cwd: None,
+            stdin: None,
+            fail_on_error: false,
+            on_invalid_utf8: InvalidUtf8::Lossy,
+        }
+    }
+
+    /// Share the output with other segments that run the same command at the same time
+    pub fn deduplicate(mut self, deduplicate: bool) -> Self {
+        self.deduplicate = deduplicate;
+        self
+    }
+
+    /// Kill the program if it runs longer than the timeout
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run the program with these additional environment variables
+    pub fn env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Run the program in this directory instead of the current one
+    pub fn current_dir(mut self, cwd: Option<PathBuf>) -> Self {
+        self.cwd = cwd;
+        self
+    }
+
+    /// Write the text to the standard input of the program on every run
+    pub fn stdin(mut self, stdin: Option<String>) -> Self {
+        self.stdin = stdin;
+        self
+    }
+
+    /// Report non-zero exit codes as errors instead of displaying the output
+    pub fn fail_on_error(mut self, fail_on_error: bool) -> Self {
+        self.fail_on_error = fail_on_error;
+        self
+    }
+
+    /// Decide what happens with output that is not valid UTF-8
+    pub fn on_invalid_utf8(mut self, on_invalid_utf8: InvalidUtf8) -> Self {
+        self.on_invalid_utf8 = on_invalid_utf8;
+        self
+    }
+
+    fn run(&self) -> io::Result<Output> {
+        let run = || {
+            let mut command = Command::new(&self.program);
+            command.args(&self.args).envs(&self.env);
+            if let Some(cwd) = &self.cwd {
+                command.current_dir(cwd);
+            }
+            output_with(command, self.stdin.as_deref(), self.timeout)
+        };
+        // the same command can print something else in another environment
+        if self.deduplicate && self.env.is_empty() && self.cwd.is_none() && self.stdin.is_none() {
+            run_deduplicated((self.program.clone(), self.args.clone()), run)
+        } else {
+            run()
+        }
+    }
+}
+
+/// Like `Command::output`, but the input is written to the process
+/// and the process is killed when it does not exit in time.
+/// The process runs in its own process group, so that the processes it started are killed with it.
+fn output_with(
+    mut command: Command,
+    input: Option<&str>,
+    timeout: Option<Duration>,
+) -> io::Result<Output> {
+    let mut child = command
+        .stdin(match input {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+    let _running = Running::new(child.id());
+
+    // the input is written in the background, so a program that doesn't read it does not block
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        let input = input.to_string();
+        thread::spawn(move || stdin.write_all(input.as_bytes()));
     }
+
+    // the pipes are read in the background, so the process does not block on full pipes
+    let read_all = |mut pipe: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            pipe.read_to_end(&mut buffer).map(|_| buffer)
+        })
+    };
+    let stdout = read_all(Box::new(child.stdout.take().unwrap()));
+    let stderr = read_all(Box::new(child.stderr.take().unwrap()));
+
+    let status = match timeout {
+        Some(timeout) => {
+            let deadline = Instant::now() + timeout;
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    kill_group(child.id());
+                    child.wait()?;
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("killed after {timeout:?}"),
+                    ));
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        None => child.wait()?,
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap()?,
+        stderr: stderr.join().unwrap()?,
+    })
 }
 
 impl SegmentKind for ProgramOutput {
-    fn compute_value(&mut self) -> String {
-        let output = match Command::new(&self.program).args(&self.args).output() {
+    fn runs_commands(&self) -> bool {
+        true
+    }
+
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let output = match self.run() {
             Ok(output) => output,
             Err(e) => {
-                warn!(
+                let message = format!(
                     "error running program {} {:?}: {}",
                     self.program.to_str().unwrap(),
                     self.args,
                     e
                 );
-                return "ERROR".into();
+                warn!("{message}");
+                return Err(message);
             }
         };
 
         if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
             warn!(
                 "program {} {:?} exited with non-zero error code ({}): {}",
                 self.program.to_str().unwrap(),
                 self.args,
                 output.status,
-                String::from_utf8(output.stderr).unwrap().trim()
+                stderr.trim()
             );
+            if self.fail_on_error {
+                return Err(match stderr.trim() {
+                    "" => output.status.to_string(),
+                    stderr => stderr.into(),
+                });
+            }
         }
 
-        let mut output_string = String::from_utf8(output.stdout).unwrap();
+        let mut output_string = match String::from_utf8(output.stdout) {
+            Ok(output) => output,
+            Err(e) => {
+                let message = format!(
+                    "program {} {:?} printed invalid UTF-8: {}",
+                    self.program.to_str().unwrap(),
+                    self.args,
+                    e.utf8_error()
+                );
+                warn!("{message}");
+                match self.on_invalid_utf8 {
+                    InvalidUtf8::Lossy => String::from_utf8_lossy(e.as_bytes()).into(),
+                    InvalidUtf8::Error => return Err(message),
+                    InvalidUtf8::Hide => String::new(),
+                }
+            }
+        };
         if self.trim {
             output_string = output_string.trim().into();
         }
-        output_string
+        Ok(output_string)
     }
 }
 
@@ -57,5 +289,83 @@ mod tests {
 
     test_segment_kinds!(
         program: ProgramOutput::new("echo".into(),vec!["hello".into()], true) => "hello",
+        shell_pipeline: ProgramOutput::new("/bin/sh".into(), vec!["-c".into(), "echo hello | tr h j".into()], true) => "jello",
+        program_missing: ProgramOutput::new("/does/not/exist".into(), vec![], true) => "ERROR",
     );
+
+    #[test]
+    fn timeout() {
+        let mut program = ProgramOutput::new("sleep".into(), vec!["10".into()], true)
+            .timeout(Some(Duration::from_millis(100)));
+        let start = Instant::now();
+        assert!(program.try_compute_value().is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn timeout_kills_children() {
+        let pid_file =
+            std::env::temp_dir().join(format!("dwmblocksrs-children-{}", std::process::id()));
+        let script = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        let mut program = ProgramOutput::new("/bin/sh".into(), vec!["-c".into(), script], true)
+            .timeout(Some(Duration::from_millis(200)));
+        assert!(program.try_compute_value().is_err());
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        std::fs::remove_file(pid_file).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        // the killed child is gone or a zombie that waits for init
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid.trim()));
+        assert!(stat.map_or(true, |stat| stat.contains(") Z ")));
+    }
+
+    #[test]
+    fn environment() {
+        let mut program = ProgramOutput::new(
+            "/bin/sh".into(),
+            vec!["-c".into(), "echo $GREETING $(pwd)".into()],
+            true,
+        )
+        .env(HashMap::from([("GREETING".into(), "hello".into())]))
+        .current_dir(Some("/".into()));
+        assert_eq!(program.compute_value(), "hello /");
+    }
+
+    #[test]
+    fn stdin() {
+        let mut program = ProgramOutput::new("tr".into(), vec!["a-z".into(), "A-Z".into()], true)
+            .stdin(Some("query".into()));
+        assert_eq!(program.compute_value(), "QUERY");
+        let mut program = ProgramOutput::new("true".into(), vec![], true)
+            .stdin(Some("x".repeat(1 << 20)))
+            .timeout(Some(Duration::from_secs(5)));
+        assert_eq!(program.compute_value(), "");
+    }
+
+    #[test]
+    fn fail_on_error() {
+        let script = vec!["-c".into(), "echo out; echo no network >&2; exit 1".into()];
+        let mut program = ProgramOutput::new("/bin/sh".into(), script, true);
+        assert_eq!(program.try_compute_value(), Ok("out".into()));
+        let mut program = program.fail_on_error(true);
+        assert_eq!(program.try_compute_value(), Err("no network".into()));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let program = |on_invalid_utf8| {
+            ProgramOutput::new("printf".into(), vec!["a\\377b".into()], true)
+                .on_invalid_utf8(on_invalid_utf8)
+                .try_compute_value()
+        };
+        assert_eq!(program(InvalidUtf8::Lossy), Ok("a\u{fffd}b".into()));
+        assert!(program(InvalidUtf8::Error).is_err());
+        assert_eq!(program(InvalidUtf8::Hide), Ok("".into()));
+    }
+
+    #[test]
+    fn failure() {
+        let mut program = ProgramOutput::new("/does/not/exist".into(), vec![], true);
+        assert!(program.try_compute_value().is_err());
+    }
 }
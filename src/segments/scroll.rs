@@ -0,0 +1,246 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use async_std::{
+    channel::{self, Receiver, Sender, TrySendError},
+    task,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{SegmentKind, ValueStream};
+use crate::duration::{self, DurationValue};
+
+/// between the end and the beginning of the scrolling text
+const GAP: &str = "   ";
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct ScrollConfig {
+    /// the number of displayed characters
+    pub width: usize,
+    /// the time between two steps, in milliseconds or a duration like `1s`
+    #[serde(
+        default = "interval_ms_default",
+        deserialize_with = "duration::milliseconds",
+        serialize_with = "duration::text"
+    )]
+    #[schemars(with = "DurationValue")]
+    pub interval_ms: Duration,
+}
+
+fn interval_ms_default() -> Duration {
+    Duration::from_millis(500)
+}
+
+/// Scrolls the values of another segment kind that are longer than the width,
+/// one character per interval.
+/// The steps are updates of the segment, so the update interval of the segment
+/// is kept by computing the value of the inner segment kind again when it elapsed.
+#[derive(Debug)]
+pub struct Scroll {
+    inner: Box<dyn SegmentKind>,
+    config: ScrollConfig,
+    /// the update interval of the segment
+    update_interval: Option<Duration>,
+    /// when the value of the inner segment kind was computed
+    computed: Option<Instant>,
+    /// the whole value of the inner segment kind
+    text: String,
+    /// the grapheme at which the displayed window starts
+    offset: usize,
+    /// set when it is time for the next step
+    step: Arc<AtomicBool>,
+    /// set when the inner segment kind requested an update
+    changed: Arc<AtomicBool>,
+}
+
+impl Scroll {
+    pub fn new(
+        inner: Box<dyn SegmentKind>,
+        config: ScrollConfig,
+        update_interval: Option<Duration>,
+    ) -> Self {
+        Self {
+            inner,
+            config,
+            update_interval,
+            computed: None,
+            text: String::new(),
+            offset: 0,
+            step: Default::default(),
+            changed: Default::default(),
+        }
+    }
+
+    /// true if the update only has to move the text
+    fn is_step(&self) -> bool {
+        let step = self.step.swap(false, Ordering::Relaxed);
+        let changed = self.changed.swap(false, Ordering::Relaxed);
+        let due = match (self.update_interval, self.computed) {
+            (Some(interval), Some(computed)) => computed.elapsed() >= interval,
+            _ => false,
+        };
+        step && !changed && !due
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.computed = Some(Instant::now());
+        if text != self.text {
+            self.text = text;
+            self.offset = 0;
+        }
+    }
+
+    /// the part of the text that is displayed now
+    fn window(&self) -> String {
+        let graphemes = self.text.graphemes(true).count();
+        if graphemes <= self.config.width {
+            return self.text.clone();
+        }
+
+        let cycle = self.text.graphemes(true).chain(GAP.graphemes(true));
+        cycle
+            .clone()
+            .chain(cycle)
+            .skip(self.offset % (graphemes + GAP.len()))
+            .take(self.config.width)
+            .collect()
+    }
+
+    fn advance(&mut self) {
+        let graphemes = self.text.graphemes(true).count();
+        if graphemes > self.config.width {
+            self.offset = (self.offset + 1) % (graphemes + GAP.len());
+        }
+    }
+}
+
+impl SegmentKind for Scroll {
+    fn compute_value(&mut self) -> String {
+        if self.is_step() {
+            self.advance();
+        } else {
+            let text = self.inner.compute_value();
+            self.set_text(text);
+        }
+        self.window()
+    }
+
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        if self.is_step() {
+            self.advance();
+        } else {
+            let text = self.inner.try_compute_value()?;
+            self.set_text(text);
+        }
+        Ok(self.window())
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        let (tx, rx) = channel::bounded(1);
+
+        let step = self.step.clone();
+        let interval = self.config.interval_ms;
+        let steps = tx.clone();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            step.store(true, Ordering::Relaxed);
+            if let Err(TrySendError::Closed(_)) = steps.try_send(()) {
+                return;
+            }
+        });
+
+        if let Some(requests) = self.inner.update_requests() {
+            let changed = self.changed.clone();
+            thread::spawn(move || forward(requests, changed, tx));
+        }
+        Some(rx)
+    }
+
+    fn values(&mut self) -> Option<ValueStream> {
+        self.inner.values()
+    }
+
+    fn control_signals(&self) -> Vec<u32> {
+        self.inner.control_signals()
+    }
+
+    fn handle_signal(&mut self, signal_offset: u32) {
+        self.inner.handle_signal(signal_offset)
+    }
+
+    fn set_value(&mut self, value: String) -> bool {
+        self.inner.set_value(value)
+    }
+
+    fn next_view(&mut self) {
+        self.inner.next_view()
+    }
+
+    fn runs_commands(&self) -> bool {
+        self.inner.runs_commands()
+    }
+}
+
+/// passes the update requests of the inner segment kind on
+fn forward(requests: Receiver<()>, changed: Arc<AtomicBool>, tx: Sender<()>) {
+    while task::block_on(requests.recv()).is_ok() {
+        changed.store(true, Ordering::Relaxed);
+        if let Err(TrySendError::Closed(_)) = tx.try_send(()) {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segments::constant::Constant;
+
+    fn scroll(text: &str, width: usize) -> Scroll {
+        Scroll::new(
+            Box::new(Constant::new(text.into())),
+            ScrollConfig {
+                width,
+                interval_ms: Duration::from_millis(500),
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn short() {
+        let mut scroll = scroll("abc", 3);
+        assert_eq!(scroll.compute_value(), "abc");
+        scroll.step.store(true, Ordering::Relaxed);
+        assert_eq!(scroll.compute_value(), "abc");
+    }
+
+    #[test]
+    fn steps() {
+        let mut scroll = scroll("abcd", 3);
+        assert_eq!(scroll.compute_value(), "abc");
+        let mut step = || {
+            scroll.step.store(true, Ordering::Relaxed);
+            scroll.compute_value()
+        };
+        assert_eq!(step(), "bcd");
+        assert_eq!(step(), "cd ");
+        assert_eq!(step(), "d  ");
+        assert_eq!(step(), "   ");
+        assert_eq!(step(), "  a");
+        assert_eq!(step(), " ab");
+        assert_eq!(step(), "abc");
+        // the position is kept when the value doesn't change
+        step();
+        assert_eq!(scroll.compute_value(), "bcd");
+    }
+}
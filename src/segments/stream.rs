@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+
+use futures_lite::{Stream, StreamExt};
+
+use super::{SegmentKind, ValueStream};
+
+/// Displays the values of a stream, e.g. of a socket or of file notifications.
+/// The segment is updated for every value, and shows the last one on other updates.
+pub struct SegmentStream {
+    /// taken by the segment when it starts
+    stream: Mutex<Option<ValueStream>>,
+    value: String,
+}
+
+impl SegmentStream {
+    pub fn new(stream: impl Stream<Item = String> + Send + 'static) -> Self {
+        Self {
+            stream: Mutex::new(Some(stream.boxed())),
+            value: String::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for SegmentStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SegmentStream")
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl SegmentKind for SegmentStream {
+    fn compute_value(&mut self) -> String {
+        self.value.clone()
+    }
+
+    fn values(&mut self) -> Option<ValueStream> {
+        self.stream.get_mut().unwrap().take()
+    }
+
+    fn set_value(&mut self, value: String) -> bool {
+        self.value = value;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn values() {
+        let mut segment = SegmentStream::new(futures_lite::stream::iter(["a".to_string()]));
+        let mut values = segment.values().unwrap();
+        assert!(segment.values().is_none());
+        assert_eq!(segment.compute_value(), "");
+
+        assert!(segment.set_value(values.next().await.unwrap()));
+        assert_eq!(segment.compute_value(), "a");
+        assert_eq!(values.next().await, None);
+    }
+}
@@ -0,0 +1,108 @@
+use std::{fs::read_to_string, path::PathBuf};
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SysfsConfig {
+    pub path: String,
+    /// the read value is multiplied by this factor
+    #[serde(default = "scale_default")]
+    pub scale: f64,
+    /// and then this offset is added
+    #[serde(default)]
+    pub offset: f64,
+    /// number of decimal places that are displayed
+    #[serde(default)]
+    pub precision: usize,
+    /// appended after the value
+    #[serde(default)]
+    pub unit: String,
+}
+
+fn scale_default() -> f64 {
+    1.0
+}
+
+/// Reads a value from a file (typically in /sys) and displays it after applying scale and offset
+#[derive(Debug)]
+pub struct Sysfs {
+    path: PathBuf,
+    scale: f64,
+    offset: f64,
+    precision: usize,
+    unit: String,
+}
+
+impl Sysfs {
+    pub fn new(path: PathBuf, scale: f64, offset: f64, precision: usize, unit: String) -> Self {
+        Self {
+            path,
+            scale,
+            offset,
+            precision,
+            unit,
+        }
+    }
+
+    fn format(&self, content: &str) -> String {
+        let content = content.trim();
+        match content.parse::<f64>() {
+            Ok(value) => format!(
+                "{:.*}{}",
+                self.precision,
+                value * self.scale + self.offset,
+                self.unit
+            ),
+            // files that contain no number are displayed as they are
+            Err(_) => format!("{}{}", content, self.unit),
+        }
+    }
+}
+
+impl SegmentKind for Sysfs {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match read_to_string(&self.path) {
+            Ok(content) => Ok(self.format(&content)),
+            Err(e) => {
+                let message = format!("error reading '{}': {}", self.path.to_str().unwrap(), e);
+                warn!("{message}");
+                Err(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sysfs(scale: f64, offset: f64, precision: usize, unit: &str) -> Sysfs {
+        Sysfs::new(PathBuf::new(), scale, offset, precision, unit.into())
+    }
+
+    #[test]
+    fn temperature() {
+        assert_eq!(sysfs(0.001, 0.0, 0, "°C").format("45000\n"), "45°C");
+    }
+
+    #[test]
+    fn precision_and_offset() {
+        assert_eq!(sysfs(0.5, 1.0, 2, "").format("3"), "2.50");
+    }
+
+    #[test]
+    fn not_a_number() {
+        assert_eq!(sysfs(1.0, 0.0, 0, "").format("Charging\n"), "Charging");
+    }
+
+    #[test]
+    fn missing_file() {
+        let mut s = Sysfs::new("/does/not/exist".into(), 1.0, 0.0, 0, "".into());
+        assert_eq!(s.compute_value(), "ERROR");
+    }
+}
@@ -0,0 +1,179 @@
+use std::{fs::read_to_string, path::Path, time::Instant};
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::template::fill_template;
+
+/// The counter that is displayed
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// the number of processes
+    Processes,
+    /// the number of open file descriptors of the system
+    OpenFiles,
+    /// the available entropy of the kernel in bits
+    Entropy,
+    /// the disk read and write operations per second since the last update
+    DiskIo,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SysstatConfig {
+    pub metric: Metric,
+    /// only count the operations of this device (e.g. `sda`), by default all disks are counted
+    pub device: Option<String>,
+    /// `{value}` is replaced by the value of the metric,
+    /// for `disk_io` `{read}` and `{write}` are also replaced
+    #[serde(default = "format_default")]
+    pub format: String,
+}
+
+fn format_default() -> String {
+    "{value}".into()
+}
+
+/// Displays small counters of the system, read from /proc
+#[derive(Debug)]
+pub struct Sysstat {
+    config: SysstatConfig,
+    /// the completed read and write operations at the time of the last update
+    last_io: Option<(Instant, u64, u64)>,
+}
+
+impl Sysstat {
+    pub fn new(config: SysstatConfig) -> Self {
+        Self {
+            config,
+            last_io: None,
+        }
+    }
+
+    fn values(&mut self) -> Result<Vec<(&'static str, String)>, String> {
+        let read =
+            |path: &str| read_to_string(path).map_err(|e| format!("error reading '{path}': {e}"));
+        let first_number = |content: String, path: &str| {
+            content
+                .split_whitespace()
+                .next()
+                .and_then(|n| n.parse::<u64>().ok())
+                .ok_or_else(|| format!("unexpected content of '{path}'"))
+        };
+
+        let value = match self.config.metric {
+            Metric::Processes => count_processes(&read("/proc/loadavg")?)
+                .ok_or_else(|| "unexpected content of '/proc/loadavg'".to_string())?,
+            Metric::OpenFiles => {
+                let path = "/proc/sys/fs/file-nr";
+                first_number(read(path)?, path)?
+            }
+            Metric::Entropy => {
+                let path = "/proc/sys/kernel/random/entropy_avail";
+                first_number(read(path)?, path)?
+            }
+            Metric::DiskIo => {
+                let (reads, writes) = disk_operations(&read("/proc/diskstats")?, |device| {
+                    match &self.config.device {
+                        Some(name) => device == name,
+                        // partitions are not listed in /sys/block, so they are not counted twice
+                        None => Path::new("/sys/block").join(device).exists(),
+                    }
+                });
+                let now = Instant::now();
+
+                let per_second = match self.last_io.replace((now, reads, writes)) {
+                    Some((last, last_reads, last_writes)) => {
+                        let seconds = now.duration_since(last).as_secs_f64().max(0.001);
+                        (
+                            (reads.saturating_sub(last_reads) as f64 / seconds).round() as u64,
+                            (writes.saturating_sub(last_writes) as f64 / seconds).round() as u64,
+                        )
+                    }
+                    // the first update has nothing to compare to
+                    None => (0, 0),
+                };
+
+                return Ok(vec![
+                    ("value", (per_second.0 + per_second.1).to_string()),
+                    ("read", per_second.0.to_string()),
+                    ("write", per_second.1.to_string()),
+                ]);
+            }
+        };
+        Ok(vec![("value", value.to_string())])
+    }
+}
+
+/// the total number of processes is the denominator of the fourth field of /proc/loadavg
+fn count_processes(loadavg: &str) -> Option<u64> {
+    loadavg
+        .split_whitespace()
+        .nth(3)?
+        .split('/')
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// sums up the completed reads and writes of the devices in /proc/diskstats
+fn disk_operations(diskstats: &str, include: impl Fn(&str) -> bool) -> (u64, u64) {
+    diskstats
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if !include(fields.get(2)?) {
+                return None;
+            }
+            let reads = fields.get(3)?.parse::<u64>().ok()?;
+            let writes = fields.get(7)?.parse::<u64>().ok()?;
+            Some((reads, writes))
+        })
+        .fold((0, 0), |(r, w), (reads, writes)| (r + reads, w + writes))
+}
+
+impl SegmentKind for Sysstat {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match self.values() {
+            Ok(values) => Ok(fill_template(&self.config.format, &values)),
+            Err(message) => {
+                warn!("{message}");
+                Err(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processes() {
+        assert_eq!(count_processes("0.52 0.58 0.59 2/1094 12345\n"), Some(1094));
+        assert_eq!(count_processes(""), None);
+    }
+
+    #[test]
+    fn diskstats() {
+        let diskstats = "   8       0 sda 100 0 0 0 50 0 0 0 0 0 0\n   8       1 sda1 90 0 0 0 40 0 0 0 0 0 0\n 259       0 nvme0n1 10 0 0 0 5 0 0 0 0 0 0\n";
+        assert_eq!(
+            disk_operations(diskstats, |d| d == "sda" || d == "nvme0n1"),
+            (110, 55)
+        );
+        assert_eq!(disk_operations(diskstats, |d| d == "sda1"), (90, 40));
+    }
+
+    #[test]
+    fn open_files() {
+        let mut sysstat = Sysstat::new(SysstatConfig {
+            metric: Metric::OpenFiles,
+            device: None,
+            format: "{value} files".into(),
+        });
+        assert!(sysstat.compute_value().ends_with(" files"));
+    }
+}
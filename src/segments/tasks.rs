@@ -0,0 +1,206 @@
+use std::{fs::read_to_string, path::PathBuf, process::Command};
+
+use async_std::channel::Receiver;
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::inotify::watch_for_changes;
+use crate::local_time::{local_time, now};
+use crate::template::fill_template;
+
+/// Where the tasks are read from
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSource {
+    /// the data directory of taskwarrior, the tasks are read with `task export`
+    Taskwarrior(String),
+    /// a todo.txt file, the due date is given with `due:YYYY-MM-DD`
+    TodoTxt(String),
+}
+
+impl TaskSource {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Taskwarrior(path) | Self::TodoTxt(path) => path,
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+pub struct TasksConfig {
+    #[serde(flatten)]
+    pub source: TaskSource,
+    #[serde(default = "format_default")]
+    pub format: String,
+}
+
+fn format_default() -> String {
+    "{overdue}/{due}".into()
+}
+
+#[derive(Deserialize, Debug)]
+struct TaskwarriorTask {
+    due: Option<String>,
+}
+
+/// Displays the number of tasks that are due today or overdue.
+/// The segment is updated as soon as the tasks change.
+#[derive(Debug)]
+pub struct Tasks {
+    source: TaskSource,
+    /// the expanded path of the source
+    path: PathBuf,
+    /// `{due}` and `{overdue}` are replaced by the number of tasks due today and before today
+    format: String,
+}
+
+impl Tasks {
+    pub fn new(source: TaskSource, path: PathBuf, format: String) -> Self {
+        Self {
+            source,
+            path,
+            format,
+        }
+    }
+
+    /// returns the due dates of all pending tasks as `YYYY-MM-DD` in local time
+    fn due_dates(&self) -> Result<Vec<String>, String> {
+        match self.source {
+            TaskSource::Taskwarrior(_) => {
+                let output = Command::new("task")
+                    .arg(format!("rc.data.location={}", self.path.display()))
+                    // garbage collection would modify the data and trigger another update
+                    .args(["rc.gc=off", "rc.hooks=off", "rc.verbose=nothing"])
+                    .args(["status:pending", "export"])
+                    .output()
+                    .map_err(|e| format!("error running taskwarrior: {e}"))?;
+                if !output.status.success() {
+                    return Err(format!(
+                        "taskwarrior exited with non-zero error code ({}): {}",
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                taskwarrior_due_dates(&String::from_utf8_lossy(&output.stdout))
+            }
+            TaskSource::TodoTxt(_) => {
+                let content = read_to_string(&self.path).map_err(|e| {
+                    format!("error reading todo.txt '{}': {}", self.path.display(), e)
+                })?;
+                Ok(todo_txt_due_dates(&content))
+            }
+        }
+    }
+
+    fn format(&self, due_dates: &[String], today: &str) -> String {
+        let due = due_dates.iter().filter(|date| *date == today).count();
+        let overdue = due_dates
+            .iter()
+            .filter(|date| date.as_str() < today)
+            .count();
+
+        // nothing to do, so the segment can be hidden
+        if due == 0 && overdue == 0 {
+            return "".into();
+        }
+        fill_template(
+            &self.format,
+            &[("due", due.to_string()), ("overdue", overdue.to_string())],
+        )
+    }
+}
+
+fn todo_txt_due_dates(content: &str) -> Vec<String> {
+    content
+        .lines()
+        // completed tasks start with `x `
+        .filter(|line| !line.starts_with("x "))
+        .filter_map(|line| {
+            line.split_whitespace()
+                .find_map(|word| word.strip_prefix("due:"))
+                .map(String::from)
+        })
+        .collect()
+}
+
+fn taskwarrior_due_dates(export: &str) -> Result<Vec<String>, String> {
+    let tasks: Vec<TaskwarriorTask> = serde_json::from_str(export)
+        .map_err(|e| format!("error parsing the tasks of taskwarrior: {e}"))?;
+    Ok(tasks
+        .into_iter()
+        .filter_map(|task| task.due)
+        .filter_map(|due| parse_taskwarrior_date(&due))
+        .map(local_date)
+        .collect())
+}
+
+/// parses dates like `20220305T230000Z` into a unix timestamp
+fn parse_taskwarrior_date(date: &str) -> Option<i64> {
+    let field = |range: std::ops::Range<usize>| date.get(range)?.parse::<i32>().ok();
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    tm.tm_year = field(0..4)? - 1900;
+    tm.tm_mon = field(4..6)? - 1;
+    tm.tm_mday = field(6..8)?;
+    tm.tm_hour = field(9..11)?;
+    tm.tm_min = field(11..13)?;
+    tm.tm_sec = field(13..15)?;
+    Some(unsafe { libc::timegm(&mut tm) })
+}
+
+/// formats the unix timestamp as `YYYY-MM-DD` in local time
+fn local_date(timestamp: i64) -> String {
+    let tm = local_time(timestamp);
+    format!(
+        "{:04}-{:02}-{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday
+    )
+}
+
+impl SegmentKind for Tasks {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match self.due_dates() {
+            Ok(due_dates) => Ok(self.format(&due_dates, &local_date(now()))),
+            Err(message) => {
+                warn!("{message}");
+                Err(message)
+            }
+        }
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        Some(watch_for_changes(vec![self.path.clone()]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn todo_txt() {
+        let content = "(A) call mom due:2022-03-01\nx 2022-02-01 done due:2022-01-01\nwrite report due:2022-03-05 +work\nno due date\n";
+        let due_dates = todo_txt_due_dates(content);
+        assert_eq!(due_dates, ["2022-03-01", "2022-03-05"]);
+
+        let tasks = Tasks::new(
+            TaskSource::TodoTxt("todo.txt".into()),
+            "todo.txt".into(),
+            format_default(),
+        );
+        assert_eq!(tasks.format(&due_dates, "2022-03-05"), "1/1");
+        assert_eq!(tasks.format(&due_dates, "2022-02-01"), "");
+    }
+
+    #[test]
+    fn taskwarrior() {
+        let export = r#"[{"description":"old","due":"20000101T120000Z"},{"description":"no due"},{"description":"future","due":"21000101T120000Z"}]"#;
+        let due_dates = taskwarrior_due_dates(export).unwrap();
+        assert_eq!(due_dates.len(), 2);
+        assert!(due_dates[0].starts_with("2000-01-0") || due_dates[0] == "1999-12-31");
+        assert!(due_dates[1].starts_with("2100-01-0") || due_dates[1] == "2099-12-31");
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::SegmentKind;
+use crate::duration::{self, DurationValue};
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TickerConfig {
+    /// the endpoint that returns JSON
+    pub url: String,
+    /// selects the displayed field, e.g. `bitcoin.usd` or `data[0].price`
+    pub field: String,
+    /// `{value}` is replaced by the selected field
+    #[serde(default = "format_default")]
+    pub format: String,
+    /// the number of decimal places of numbers
+    pub precision: Option<usize>,
+    /// the minimum time between two requests in seconds or a duration like `5m`,
+    /// also when updated by signals
+    #[serde(
+        default = "min_interval_default",
+        deserialize_with = "duration::seconds",
+        serialize_with = "duration::text"
+    )]
+    #[schemars(with = "DurationValue")]
+    pub min_interval: Duration,
+}
+
+fn format_default() -> String {
+    "{value}".into()
+}
+
+fn min_interval_default() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// Displays a value, e.g. the price of a stock, from a HTTP endpoint that returns JSON.
+/// The endpoint is requested with `curl`.
+/// If a request fails, the last value is displayed.
+#[derive(Debug)]
+pub struct Ticker {
+    config: TickerConfig,
+    /// when the endpoint was last requested, failed requests included,
+    /// and the last successfully fetched value or the error if there is none
+    last: Option<(Instant, Result<String, String>)>,
+}
+
+impl Ticker {
+    pub fn new(config: TickerConfig) -> Self {
+        Self { config, last: None }
+    }
+
+    fn fetch(&self) -> Result<String, String> {
+        let output = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location"])
+            .args(["--max-time", "10"])
+            .arg(&self.config.url)
+            .output()
+            .map_err(|e| format!("error running curl: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "error requesting '{}': {}",
+                self.config.url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let json: Value = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("error parsing response of '{}': {}", self.config.url, e))?;
+        let value = select(&json, &self.config.field).ok_or_else(|| {
+            format!(
+                "the response of '{}' has no field '{}'",
+                self.config.url, self.config.field
+            )
+        })?;
+        Ok(self.format(value))
+    }
+
+    fn format(&self, value: &Value) -> String {
+        let value = match (value, self.config.precision) {
+            (Value::String(s), _) => s.clone(),
+            (Value::Number(n), Some(precision)) => match n.as_f64() {
+                Some(n) => format!("{n:.precision$}"),
+                None => n.to_string(),
+            },
+            (value, _) => value.to_string(),
+        };
+        fill_template(&self.config.format, &[("value", value)])
+    }
+}
+
+/// selects a field of the JSON value by a path like `data[0].price`, a leading `$.` is ignored
+fn select<'a>(json: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.trim_start_matches('$').trim_start_matches('.');
+    path.split(['.', '['])
+        .filter(|key| !key.is_empty())
+        .try_fold(json, |value, key| match key.strip_suffix(']') {
+            Some(index) => value.get(index.parse::<usize>().ok()?),
+            None => value.get(key),
+        })
+}
+
+impl SegmentKind for Ticker {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        // don't request the endpoint too often
+        if let Some((requested, value)) = &self.last {
+            if requested.elapsed() < self.config.min_interval {
+                return value.clone();
+            }
+        }
+
+        let value = match self.fetch() {
+            Ok(value) => Ok(value),
+            Err(message) => {
+                warn!("{message}");
+                // keep showing the last value if there is one
+                match self.last.take() {
+                    Some((_, Ok(value))) => Ok(value),
+                    _ => Err(message),
+                }
+            }
+        };
+        self.last = Some((Instant::now(), value.clone()));
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticker(precision: Option<usize>) -> Ticker {
+        Ticker::new(TickerConfig {
+            url: "http://localhost".into(),
+            field: "".into(),
+            format: "${value}".into(),
+            precision,
+            min_interval: Duration::from_secs(60),
+        })
+    }
+
+    #[test]
+    fn select_fields() {
+        let json: Value =
+            serde_json::from_str(r#"{"bitcoin":{"usd":42000.5},"data":[{"price":"1.5"}]}"#)
+                .unwrap();
+        assert_eq!(select(&json, "bitcoin.usd"), Some(&42000.5.into()));
+        assert_eq!(select(&json, "$.data[0].price"), Some(&"1.5".into()));
+        assert_eq!(select(&json, "data[1].price"), None);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(ticker(None).format(&42000.5.into()), "$42000.5");
+        assert_eq!(ticker(Some(0)).format(&42000.5.into()), "$42000");
+        assert_eq!(ticker(Some(2)).format(&"1.5".into()), "$1.5");
+    }
+
+    #[test]
+    fn rate_limit() {
+        let mut ticker = ticker(None);
+        ticker.last = Some((Instant::now(), Ok("cached".into())));
+        assert_eq!(ticker.compute_value(), "cached");
+    }
+
+    #[test]
+    fn failed_requests_are_rate_limited() {
+        let mut ticker = ticker(None);
+        // nothing listens on the port
+        ticker.config.url = "http://127.0.0.1:1".into();
+        assert!(ticker.try_compute_value().is_err());
+        let (requested, _) = ticker.last.clone().unwrap();
+        assert!(ticker.try_compute_value().is_err());
+        assert_eq!(ticker.last.as_ref().unwrap().0, requested);
+
+        // the last value stays after a failed request
+        ticker.last = Some((
+            Instant::now() - Duration::from_secs(60),
+            Ok("cached".into()),
+        ));
+        assert_eq!(ticker.compute_value(), "cached");
+        let (requested, value) = ticker.last.clone().unwrap();
+        assert!(requested.elapsed() < Duration::from_secs(60));
+        assert_eq!(value, Ok("cached".into()));
+    }
+}
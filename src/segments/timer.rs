@@ -0,0 +1,210 @@
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::{SegmentKind, SignalValue};
+use crate::duration::{self, DurationValue};
+use crate::hooks::spawn_hooks;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimerConfig {
+    /// the length of the countdown in seconds or a duration like `25m`
+    #[serde(
+        deserialize_with = "duration::seconds",
+        serialize_with = "duration::text"
+    )]
+    #[schemars(with = "DurationValue")]
+    pub duration: Duration,
+    /// SIGRTMIN+`toggle` starts and pauses the timer
+    #[schemars(with = "Option<SignalValue>")]
+    pub toggle: Option<u32>,
+    /// SIGRTMIN+`reset` stops the timer and resets it to its full length
+    #[schemars(with = "Option<SignalValue>")]
+    pub reset: Option<u32>,
+    /// displayed while the timer runs, `{remaining}` is replaced by the remaining time
+    pub running: String,
+    /// displayed while the timer is paused or not started
+    pub paused: String,
+    /// displayed when the timer has elapsed
+    pub finished: String,
+    /// a shell command that is run when the timer elapses
+    pub command: Option<String>,
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(25 * 60),
+            toggle: None,
+            reset: None,
+            running: "{remaining}".into(),
+            paused: "{remaining}".into(),
+            finished: "00:00".into(),
+            command: None,
+        }
+    }
+}
+
+/// A countdown timer controlled by realtime signals.
+/// It should be updated every second to show the remaining time.
+#[derive(Debug)]
+pub struct Timer {
+    config: TimerConfig,
+    /// the time that passed while the timer was running before it was paused the last time
+    elapsed: Duration,
+    /// since when the timer is running
+    running_since: Option<Instant>,
+    finished: bool,
+}
+
+impl Timer {
+    pub fn new(config: TimerConfig) -> Self {
+        Self {
+            config,
+            elapsed: Duration::ZERO,
+            running_since: None,
+            finished: false,
+        }
+    }
+
+    fn toggle(&mut self, now: Instant) {
+        match self.running_since.take() {
+            Some(since) => self.elapsed += now - since,
+            None => {
+                // a finished timer starts again from the beginning
+                if self.finished {
+                    self.reset();
+                }
+                self.running_since = Some(now);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.running_since = None;
+        self.finished = false;
+    }
+
+    fn remaining(&self, now: Instant) -> Duration {
+        let elapsed = self.elapsed
+            + self
+                .running_since
+                .map_or(Duration::ZERO, |since| now - since);
+        self.config.duration.saturating_sub(elapsed)
+    }
+
+    fn value_at(&mut self, now: Instant) -> String {
+        if self.finished {
+            return self.config.finished.clone();
+        }
+
+        let remaining = self.remaining(now);
+        if remaining.is_zero() {
+            self.finished = true;
+            self.running_since = None;
+            if let Some(command) = &self.config.command {
+                spawn_hooks(vec![command.clone()]);
+            }
+            return self.config.finished.clone();
+        }
+
+        let template = if self.running_since.is_some() {
+            &self.config.running
+        } else {
+            &self.config.paused
+        };
+        fill_template(template, &[("remaining", format_duration(remaining))])
+    }
+}
+
+/// formats the duration as `MM:SS` or `H:MM:SS`, partial seconds are rounded up
+fn format_duration(duration: Duration) -> String {
+    let mut seconds = duration.as_secs();
+    if duration.subsec_nanos() > 0 {
+        seconds += 1;
+    }
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
+
+impl SegmentKind for Timer {
+    fn compute_value(&mut self) -> String {
+        self.value_at(Instant::now())
+    }
+
+    fn control_signals(&self) -> Vec<u32> {
+        self.config
+            .toggle
+            .into_iter()
+            .chain(self.config.reset)
+            .collect()
+    }
+
+    fn handle_signal(&mut self, signal_offset: u32) {
+        if self.config.toggle == Some(signal_offset) {
+            self.toggle(Instant::now());
+        } else if self.config.reset == Some(signal_offset) {
+            self.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer() -> Timer {
+        Timer::new(TimerConfig {
+            duration: Duration::from_secs(60),
+            paused: "{remaining} paused".into(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn countdown() {
+        let start = Instant::now();
+        let mut timer = timer();
+        assert_eq!(timer.value_at(start), "01:00 paused");
+
+        timer.toggle(start);
+        assert_eq!(timer.value_at(start + Duration::from_secs(15)), "00:45");
+
+        // pausing keeps the remaining time
+        timer.toggle(start + Duration::from_secs(20));
+        assert_eq!(
+            timer.value_at(start + Duration::from_secs(30)),
+            "00:40 paused"
+        );
+
+        timer.reset();
+        assert_eq!(timer.value_at(start), "01:00 paused");
+    }
+
+    #[test]
+    fn finish() {
+        let start = Instant::now();
+        let mut timer = timer();
+        timer.toggle(start);
+        assert_eq!(timer.value_at(start + Duration::from_secs(61)), "00:00");
+
+        // toggling a finished timer starts it again
+        let restart = start + Duration::from_secs(70);
+        timer.toggle(restart);
+        assert_eq!(timer.value_at(restart + Duration::from_secs(1)), "00:59");
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_duration(Duration::from_millis(500)), "00:01");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+    }
+}
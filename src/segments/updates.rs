@@ -0,0 +1,158 @@
+use std::process::Command;
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::template::fill_template;
+
+/// How pending updates are checked
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdatesBackend {
+    /// `checkupdates` from pacman-contrib
+    #[default]
+    Pacman,
+    /// `apt list --upgradable`
+    Apt,
+    /// any command that prints one line per pending update
+    Command(String),
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpdatesConfig {
+    pub backend: UpdatesBackend,
+    /// `{count}` is replaced by the number of pending updates
+    pub format: String,
+    /// displayed when the updates could not be checked
+    pub failed: String,
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            backend: Default::default(),
+            format: "{count}".into(),
+            failed: "?".into(),
+        }
+    }
+}
+
+/// Displays the number of pending package updates
+#[derive(Debug)]
+pub struct Updates {
+    config: UpdatesConfig,
+}
+
+impl UpdatesBackend {
+    fn command(&self) -> Command {
+        match self {
+            Self::Pacman => Command::new("checkupdates"),
+            Self::Apt => {
+                let mut command = Command::new("apt");
+                command.args(["list", "--upgradable"]);
+                command
+            }
+            Self::Command(cmd) => {
+                let mut command = Command::new("/bin/sh");
+                command.arg("-c").arg(cmd);
+                command
+            }
+        }
+    }
+
+    /// whether the command exited successfully, some backends use special exit codes
+    fn success(&self, code: Option<i32>) -> bool {
+        match self {
+            // checkupdates exits with 2 when there are no updates
+            Self::Pacman => matches!(code, Some(0) | Some(2)),
+            _ => code == Some(0),
+        }
+    }
+
+    fn count(&self, output: &str) -> usize {
+        let lines = output.lines().filter(|line| !line.trim().is_empty());
+        match self {
+            // apt prints a header and possibly warnings
+            Self::Apt => lines
+                .filter(|line| line.contains("upgradable from"))
+                .count(),
+            _ => lines.count(),
+        }
+    }
+}
+
+impl Updates {
+    pub fn new(config: UpdatesConfig) -> Self {
+        Self { config }
+    }
+
+    fn check(&self) -> Result<usize, String> {
+        let output = self
+            .config
+            .backend
+            .command()
+            .output()
+            .map_err(|e| format!("error checking for updates: {e}"))?;
+
+        if !self.config.backend.success(output.status.code()) {
+            return Err(format!(
+                "checking for updates exited with non-zero error code ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(self
+            .config
+            .backend
+            .count(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+impl SegmentKind for Updates {
+    fn compute_value(&mut self) -> String {
+        match self.check() {
+            // no updates, so the segment can be hidden
+            Ok(0) => "".into(),
+            Ok(count) => fill_template(&self.config.format, &[("count", count.to_string())]),
+            Err(e) => {
+                warn!("{e}");
+                self.config.failed.clone()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_segment_kinds;
+
+    fn updates(command: &str) -> Updates {
+        Updates::new(UpdatesConfig {
+            backend: UpdatesBackend::Command(command.into()),
+            ..Default::default()
+        })
+    }
+
+    test_segment_kinds!(
+        updates_count: updates("printf 'a 1 -> 2\\nb 3 -> 4\\n'") => "2",
+        no_updates: updates("true") => "",
+        updates_failed: updates("exit 1") => "?",
+    );
+
+    #[test]
+    fn apt() {
+        let output = "Listing...\nfoo/stable 2.0 amd64 [upgradable from: 1.0]\nbar/stable 3.0 amd64 [upgradable from: 2.0]\n";
+        assert_eq!(UpdatesBackend::Apt.count(output), 2);
+    }
+
+    #[test]
+    fn pacman_exit_codes() {
+        assert!(UpdatesBackend::Pacman.success(Some(2)));
+        assert!(!UpdatesBackend::Pacman.success(Some(1)));
+    }
+}
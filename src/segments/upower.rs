@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use async_std::channel::Receiver;
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use zbus::{
+    blocking::{fdo::PropertiesProxy, Connection},
+    message::Type,
+    names::InterfaceName,
+    zvariant::{OwnedObjectPath, OwnedValue},
+    MatchRule,
+};
+
+use super::SegmentKind;
+use crate::dbus::{watch_signals, Bus};
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpowerConfig {
+    /// formats for all batteries of the system together, `{percentage}` is replaced
+    pub charging: String,
+    pub discharging: String,
+    pub full: String,
+    /// format for each peripheral device (mouse, keyboard, ...), `{model}` and `{percentage}` are replaced
+    pub peripheral: String,
+    /// whether peripheral devices are displayed
+    pub peripherals: bool,
+    /// the string between the batteries of the system and the peripherals
+    pub separator: String,
+}
+
+impl Default for UpowerConfig {
+    fn default() -> Self {
+        Self {
+            charging: "+{percentage}%".into(),
+            discharging: "{percentage}%".into(),
+            full: "{percentage}%".into(),
+            peripheral: "{model} {percentage}%".into(),
+            peripherals: true,
+            separator: " ".into(),
+        }
+    }
+}
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+// https://upower.freedesktop.org/docs/Device.html
+const TYPE_LINE_POWER: u32 = 1;
+const TYPE_BATTERY: u32 = 2;
+const STATE_CHARGING: u32 = 1;
+const STATE_DISCHARGING: u32 = 2;
+const STATE_FULLY_CHARGED: u32 = 4;
+
+#[derive(Debug, Default, Clone)]
+struct Device {
+    model: String,
+    kind: u32,
+    power_supply: bool,
+    present: bool,
+    percentage: f64,
+    state: u32,
+    energy: f64,
+    energy_full: f64,
+}
+
+/// Displays the batteries reported by UPower.
+/// All batteries of the system are combined, peripheral devices are listed separately.
+/// The segment is updated as soon as UPower reports a change.
+#[derive(Debug)]
+pub struct Upower {
+    config: UpowerConfig,
+    connection: Option<Connection>,
+}
+
+impl Upower {
+    pub fn new(config: UpowerConfig) -> Self {
+        Self {
+            config,
+            connection: None,
+        }
+    }
+
+    fn devices(&mut self) -> zbus::Result<Vec<Device>> {
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(Bus::System.connect()?),
+        };
+
+        let paths = connection
+            .call_method(
+                Some(UPOWER),
+                "/org/freedesktop/UPower",
+                Some(UPOWER),
+                "EnumerateDevices",
+                &(),
+            )?
+            .body()
+            .deserialize::<Vec<OwnedObjectPath>>()?;
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let properties = PropertiesProxy::builder(connection)
+                    .destination(UPOWER)?
+                    .path(path)?
+                    .build()?
+                    .get_all(InterfaceName::from_static_str_unchecked(DEVICE_INTERFACE))?;
+                Ok(Device::from(properties))
+            })
+            .collect()
+    }
+
+    fn format(&self, devices: &[Device]) -> String {
+        let batteries = devices
+            .iter()
+            .filter(|d| d.kind == TYPE_BATTERY && d.power_supply && d.present)
+            .collect::<Vec<_>>();
+
+        let mut texts = Vec::new();
+
+        if !batteries.is_empty() {
+            let energy = batteries.iter().map(|b| b.energy).sum::<f64>();
+            let energy_full = batteries.iter().map(|b| b.energy_full).sum::<f64>();
+            let percentage = if energy_full > 0.0 {
+                energy / energy_full * 100.0
+            } else {
+                batteries.iter().map(|b| b.percentage).sum::<f64>() / batteries.len() as f64
+            };
+
+            let template = if batteries.iter().any(|b| b.state == STATE_CHARGING) {
+                &self.config.charging
+            } else if batteries.iter().any(|b| b.state == STATE_DISCHARGING) {
+                &self.config.discharging
+            } else if batteries.iter().all(|b| b.state == STATE_FULLY_CHARGED) {
+                &self.config.full
+            } else {
+                &self.config.discharging
+            };
+
+            texts.push(fill_template(
+                template,
+                &[("percentage", format!("{percentage:.0}"))],
+            ));
+        }
+
+        if self.config.peripherals {
+            texts.extend(
+                devices
+                    .iter()
+                    .filter(|d| d.kind > TYPE_LINE_POWER && !d.power_supply)
+                    .map(|d| {
+                        fill_template(
+                            &self.config.peripheral,
+                            &[
+                                ("model", d.model.clone()),
+                                ("percentage", format!("{:.0}", d.percentage)),
+                            ],
+                        )
+                    }),
+            );
+        }
+
+        texts.join(&self.config.separator)
+    }
+}
+
+impl From<HashMap<String, OwnedValue>> for Device {
+    fn from(properties: HashMap<String, OwnedValue>) -> Self {
+        let get = |name: &str| properties.get(name);
+        Self {
+            model: get("Model")
+                .and_then(|v| v.downcast_ref::<&str>().ok().map(String::from))
+                .unwrap_or_default(),
+            kind: get("Type")
+                .and_then(|v| v.downcast_ref::<u32>().ok())
+                .unwrap_or_default(),
+            power_supply: get("PowerSupply")
+                .and_then(|v| v.downcast_ref::<bool>().ok())
+                .unwrap_or_default(),
+            present: get("IsPresent")
+                .and_then(|v| v.downcast_ref::<bool>().ok())
+                .unwrap_or_default(),
+            percentage: get("Percentage")
+                .and_then(|v| v.downcast_ref::<f64>().ok())
+                .unwrap_or_default(),
+            state: get("State")
+                .and_then(|v| v.downcast_ref::<u32>().ok())
+                .unwrap_or_default(),
+            energy: get("Energy")
+                .and_then(|v| v.downcast_ref::<f64>().ok())
+                .unwrap_or_default(),
+            energy_full: get("EnergyFull")
+                .and_then(|v| v.downcast_ref::<f64>().ok())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl SegmentKind for Upower {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        match self.devices() {
+            Ok(devices) => Ok(self.format(&devices)),
+            Err(e) => {
+                let message = format!("error reading devices from upower: {e}");
+                warn!("{message}");
+                // reconnect on the next update
+                self.connection = None;
+                Err(message)
+            }
+        }
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        // property changes of the devices as well as added and removed devices
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender(UPOWER)
+            .unwrap()
+            .build();
+        Some(watch_signals(Bus::System, rule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(energy: f64, energy_full: f64, state: u32) -> Device {
+        Device {
+            kind: TYPE_BATTERY,
+            power_supply: true,
+            present: true,
+            energy,
+            energy_full,
+            state,
+            ..Default::default()
+        }
+    }
+
+    fn mouse() -> Device {
+        Device {
+            model: "Mouse".into(),
+            kind: 5,
+            percentage: 40.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn combined_batteries() {
+        let upower = Upower::new(Default::default());
+        let devices = [
+            battery(10.0, 50.0, STATE_DISCHARGING),
+            battery(40.0, 50.0, STATE_FULLY_CHARGED),
+        ];
+        assert_eq!(upower.format(&devices), "50%");
+    }
+
+    #[test]
+    fn charging() {
+        let upower = Upower::new(Default::default());
+        let devices = [battery(10.0, 50.0, STATE_CHARGING)];
+        assert_eq!(upower.format(&devices), "+20%");
+    }
+
+    #[test]
+    fn peripherals() {
+        let upower = Upower::new(Default::default());
+        let devices = [battery(50.0, 50.0, STATE_FULLY_CHARGED), mouse()];
+        assert_eq!(upower.format(&devices), "100% Mouse 40%");
+
+        let upower = Upower::new(UpowerConfig {
+            peripherals: false,
+            ..Default::default()
+        });
+        assert_eq!(upower.format(&devices), "100%");
+    }
+}
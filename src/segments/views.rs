@@ -0,0 +1,76 @@
+use async_std::channel::Receiver;
+
+use super::group::merge_update_requests;
+use super::{Segment, SegmentKind};
+
+/// Displays one of several segments, e.g. the time or the date.
+/// The next one is shown when the view signal arrives or when it is requested from outside.
+#[derive(Debug)]
+pub struct Views {
+    views: Vec<Segment>,
+    view_signal: Option<u32>,
+    current: usize,
+}
+
+impl Views {
+    pub fn new(views: Vec<Segment>, view_signal: Option<u32>) -> Result<Self, String> {
+        if views.is_empty() {
+            return Err("there are no views".into());
+        }
+        Ok(Self {
+            views,
+            view_signal,
+            current: 0,
+        })
+    }
+}
+
+impl SegmentKind for Views {
+    fn compute_value(&mut self) -> String {
+        self.views[self.current].compute_value()
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        merge_update_requests(&self.views)
+    }
+
+    fn control_signals(&self) -> Vec<u32> {
+        self.view_signal.into_iter().collect()
+    }
+
+    fn handle_signal(&mut self, signal_offset: u32) {
+        if self.view_signal == Some(signal_offset) {
+            self.next_view();
+        }
+    }
+
+    fn next_view(&mut self) {
+        self.current = (self.current + 1) % self.views.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segments::constant::Constant;
+
+    fn constant(text: &str) -> Segment {
+        Segment::builder(Box::new(Constant::new(text.into())))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn cycle() {
+        let mut views = Views::new(vec![constant("time"), constant("date")], Some(2)).unwrap();
+        assert_eq!(views.compute_value(), "time");
+        views.handle_signal(2);
+        assert_eq!(views.compute_value(), "date");
+        views.next_view();
+        assert_eq!(views.compute_value(), "time");
+        views.handle_signal(3);
+        assert_eq!(views.compute_value(), "time");
+
+        assert!(Views::new(Vec::new(), None).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    io::{BufRead, BufReader},
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use async_std::channel::{self, Receiver, Sender, TrySendError};
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::{
+    program_output::{kill_group, Running},
+    SegmentKind,
+};
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct VolumeConfig {
+    /// `{volume}` is replaced by the volume in percent
+    pub format: String,
+    /// used instead of format when the sink is muted
+    pub muted: String,
+}
+
+impl Default for VolumeConfig {
+    fn default() -> Self {
+        Self {
+            format: "{volume}%".into(),
+            muted: "muted".into(),
+        }
+    }
+}
+
+/// Displays the volume of the default PulseAudio (or PipeWire) sink.
+/// The segment is updated as soon as the volume changes.
+/// When the segment is dropped, e.g. on reload, `pactl subscribe` is killed.
+#[derive(Debug)]
+pub struct Volume {
+    config: VolumeConfig,
+    subscription: Arc<Mutex<Subscription>>,
+}
+
+/// The `pactl subscribe` that is running, shared with the thread that runs it
+#[derive(Debug, Default)]
+struct Subscription {
+    /// the process group of pactl
+    group: Option<u32>,
+    /// set when the segment is dropped, pactl is not started again
+    stopped: bool,
+}
+
+impl Volume {
+    pub fn new(config: VolumeConfig) -> Self {
+        Self {
+            config,
+            subscription: Default::default(),
+        }
+    }
+
+    fn pactl(args: &[&str]) -> Result<String, String> {
+        let output = Command::new("pactl")
+            .args(args)
+            .output()
+            .map_err(|e| format!("error running pactl: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "pactl {:?} exited with non-zero error code ({}): {}",
+                args,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into())
+    }
+
+    /// parses the first percentage in the output of `pactl get-sink-volume`
+    fn parse_volume(output: &str) -> Option<u32> {
+        output
+            .split_whitespace()
+            .find_map(|word| word.strip_suffix('%')?.parse().ok())
+    }
+
+    /// parses the output of `pactl get-sink-mute`
+    fn parse_muted(output: &str) -> bool {
+        output.trim() == "Mute: yes"
+    }
+
+    fn format(&self, volume: u32, muted: bool) -> String {
+        let template = if muted {
+            &self.config.muted
+        } else {
+            &self.config.format
+        };
+        fill_template(template, &[("volume", volume.to_string())])
+    }
+
+    /// Requests an update whenever pactl reports a change of a sink or the server (e.g. the default sink).
+    /// If pactl exits, it is restarted.
+    fn subscribe(requests: Sender<()>, subscription: Arc<Mutex<Subscription>>) {
+        loop {
+            if let Err(e) = Self::watch_events(&requests, &subscription) {
+                warn!("error subscribing to pulseaudio events: {e}");
+            }
+            let stopped = || requests.is_closed() || subscription.lock().unwrap().stopped;
+            if stopped() {
+                return;
+            }
+            thread::sleep(Duration::from_secs(5));
+            if stopped() {
+                return;
+            }
+        }
+    }
+
+    /// whether the event of `pactl subscribe` can change the volume of the default sink,
+    /// e.g. `Event 'change' on sink #0`, but not `Event 'change' on sink-input #12`
+    fn is_relevant(event: &str) -> bool {
+        let facility = event
+            .rsplit_once(" on ")
+            .map(|(_, object)| object.split(" #").next().unwrap_or(object));
+        matches!(facility, Some("sink" | "server"))
+    }
+
+    fn watch_events(
+        requests: &Sender<()>,
+        subscription: &Mutex<Subscription>,
+    ) -> std::io::Result<()> {
+        let mut child = {
+            let mut subscription = subscription.lock().unwrap();
+            if subscription.stopped {
+                return Ok(());
+            }
+            let child = Command::new("pactl")
+                .arg("subscribe")
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                // killed by its group, like the programs of other segments
+                .process_group(0)
+                .spawn()?;
+            subscription.group = Some(child.id());
+            child
+        };
+        let _running = Running::new(child.id());
+
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        for line in stdout.lines() {
+            let line = line?;
+            if Self::is_relevant(&line) {
+                // if an update is already pending, this one is not needed anymore
+                if let Err(TrySendError::Closed(_)) = requests.try_send(()) {
+                    // the segment does not exist anymore
+                    break;
+                }
+            }
+        }
+
+        // the group is forgotten before pactl is waited for, so its id cannot be reused yet
+        subscription.lock().unwrap().group = None;
+        kill_group(child.id());
+        child.wait()?;
+        Ok(())
+    }
+}
+
+impl Drop for Volume {
+    fn drop(&mut self) {
+        let mut subscription = self.subscription.lock().unwrap();
+        subscription.stopped = true;
+        if let Some(group) = subscription.group {
+            kill_group(group);
+        }
+    }
+}
+
+impl SegmentKind for Volume {
+    fn try_compute_value(&mut self) -> Result<String, String> {
+        let result = Self::pactl(&["get-sink-volume", "@DEFAULT_SINK@"]).and_then(|volume| {
+            let muted = Self::parse_muted(&Self::pactl(&["get-sink-mute", "@DEFAULT_SINK@"])?);
+            let volume = Self::parse_volume(&volume)
+                .ok_or_else(|| format!("could not parse volume '{}'", volume.trim()))?;
+            Ok(self.format(volume, muted))
+        });
+
+        if let Err(e) = &result {
+            warn!("{e}");
+        }
+        result
+    }
+
+    fn update_requests(&mut self) -> Option<Receiver<()>> {
+        let (tx, rx) = channel::bounded(1);
+        let subscription = self.subscription.clone();
+        thread::spawn(move || Self::subscribe(tx, subscription));
+        Some(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_volume() {
+        let output = "Volume: front-left: 42598 /  65% / -11.23 dB,   front-right: 42598 /  65% / -11.23 dB\n        balance 0.00\n";
+        assert_eq!(Volume::parse_volume(output), Some(65));
+        assert_eq!(Volume::parse_volume("garbage"), None);
+    }
+
+    #[test]
+    fn parse_muted() {
+        assert!(Volume::parse_muted("Mute: yes\n"));
+        assert!(!Volume::parse_muted("Mute: no\n"));
+    }
+
+    #[test]
+    fn events() {
+        assert!(Volume::is_relevant("Event 'change' on sink #0"));
+        assert!(Volume::is_relevant("Event 'change' on server #0"));
+        assert!(Volume::is_relevant("Event 'change' on server"));
+        assert!(!Volume::is_relevant("Event 'new' on sink-input #12"));
+        assert!(!Volume::is_relevant("Event 'change' on source #1"));
+    }
+
+    #[test]
+    fn format() {
+        let volume = Volume::new(Default::default());
+        assert_eq!(volume.format(65, false), "65%");
+        assert_eq!(volume.format(65, true), "muted");
+    }
+}
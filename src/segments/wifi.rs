@@ -0,0 +1,174 @@
+use std::{fs::read_to_string, mem, os::raw::c_char};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::SegmentKind;
+use crate::template::fill_template;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct WifiConfig {
+    /// the wireless interface, if not set the first one in /proc/net/wireless is used
+    pub interface: Option<String>,
+    /// `{ssid}`, `{quality}` (in percent) and `{interface}` are replaced
+    pub format: String,
+    /// displayed when there is no connection
+    pub disconnected: String,
+}
+
+impl Default for WifiConfig {
+    fn default() -> Self {
+        Self {
+            interface: None,
+            format: "{ssid} {quality}%".into(),
+            disconnected: "disconnected".into(),
+        }
+    }
+}
+
+/// Displays the SSID and link quality of a wireless interface
+#[derive(Debug)]
+pub struct Wifi {
+    config: WifiConfig,
+}
+
+/// link qualities in /proc/net/wireless usually range from 0 to 70
+const MAX_LINK_QUALITY: f64 = 70.0;
+
+const SIOCGIWESSID: libc::c_ulong = 0x8B1B;
+const IW_ESSID_MAX_SIZE: usize = 32;
+
+#[repr(C)]
+struct IwPoint {
+    pointer: *mut libc::c_void,
+    length: u16,
+    flags: u16,
+}
+
+#[repr(C)]
+struct IwReq {
+    name: [c_char; libc::IFNAMSIZ],
+    essid: IwPoint,
+}
+
+impl Wifi {
+    pub fn new(config: WifiConfig) -> Self {
+        Self { config }
+    }
+
+    /// returns the interface and its link quality in percent
+    fn parse_wireless(wireless: &str, interface: Option<&str>) -> Option<(String, u32)> {
+        // the first two lines are headers
+        wireless.lines().skip(2).find_map(|line| {
+            let (name, values) = line.split_once(':')?;
+            let name = name.trim();
+            if interface.is_some_and(|interface| interface != name) {
+                return None;
+            }
+            let link = values
+                .split_whitespace()
+                .nth(1)?
+                .trim_end_matches('.')
+                .parse::<f64>()
+                .ok()?;
+            let quality = (link / MAX_LINK_QUALITY * 100.0).round().clamp(0.0, 100.0);
+            Some((name.to_string(), quality as u32))
+        })
+    }
+
+    /// asks the kernel for the SSID of the interface using the wireless extensions ioctl
+    fn ssid(interface: &str) -> Option<String> {
+        if interface.len() >= libc::IFNAMSIZ {
+            return None;
+        }
+
+        let mut essid = [0u8; IW_ESSID_MAX_SIZE + 1];
+        let mut request: IwReq = unsafe { mem::zeroed() };
+        for (dst, src) in request.name.iter_mut().zip(interface.bytes()) {
+            *dst = src as c_char;
+        }
+        request.essid.pointer = essid.as_mut_ptr().cast();
+        request.essid.length = essid.len() as u16;
+
+        let result = unsafe {
+            let socket = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+            if socket < 0 {
+                return None;
+            }
+            let result = libc::ioctl(socket, SIOCGIWESSID, &mut request);
+            libc::close(socket);
+            result
+        };
+        if result < 0 {
+            return None;
+        }
+
+        let length = (request.essid.length as usize).min(IW_ESSID_MAX_SIZE);
+        let ssid = String::from_utf8_lossy(&essid[..length])
+            .trim_end_matches('\0')
+            .to_string();
+        Some(ssid)
+    }
+}
+
+impl SegmentKind for Wifi {
+    fn compute_value(&mut self) -> String {
+        let wireless = read_to_string("/proc/net/wireless").unwrap_or_default();
+        let (interface, quality) =
+            match Self::parse_wireless(&wireless, self.config.interface.as_deref()) {
+                Some(link) => link,
+                None => return self.config.disconnected.clone(),
+            };
+
+        match Self::ssid(&interface) {
+            Some(ssid) if !ssid.is_empty() => fill_template(
+                &self.config.format,
+                &[
+                    ("ssid", ssid),
+                    ("quality", quality.to_string()),
+                    ("interface", interface),
+                ],
+            ),
+            _ => self.config.disconnected.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIRELESS: &str =
+        "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE
+ face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22
+wlp2s0: 0000   56.  -54.  -256        0      0      0      0     12        0
+wlan1: 0000   35.  -70.  -256        0      0      0      0     12        0
+";
+
+    #[test]
+    fn first_interface() {
+        assert_eq!(
+            Wifi::parse_wireless(WIRELESS, None),
+            Some(("wlp2s0".into(), 80))
+        );
+    }
+
+    #[test]
+    fn named_interface() {
+        assert_eq!(
+            Wifi::parse_wireless(WIRELESS, Some("wlan1")),
+            Some(("wlan1".into(), 50))
+        );
+        assert_eq!(Wifi::parse_wireless(WIRELESS, Some("wlan2")), None);
+    }
+
+    #[test]
+    fn disconnected() {
+        let mut wifi = Wifi::new(WifiConfig {
+            interface: Some("does-not-exist".into()),
+            ..Default::default()
+        });
+        assert_eq!(wifi.compute_value(), "disconnected");
+    }
+}
@@ -1,61 +1,510 @@
-use std::{ffi::CString, ptr};
-use x11::xlib::{Display, XDefaultScreen, XOpenDisplay, XRootWindow, XStoreName, XSync};
+use std::{collections::HashSet, time::Duration};
 
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::color::{split_colors, Color, ColorFormat};
+use crate::output::{Block, Output, OutputConfig, Status};
+use crate::segments::Area;
 use crate::SegmentId;
 
+/// Settings of the status bar that are independent of the segments
+#[derive(Debug, Clone)]
+pub struct StatusBarConfig {
+    /// separates the top and bottom area for the extrabar/dualstatus patches
+    pub area_delimiter: String,
+    /// put before and after the text of each area
+    pub prefix: String,
+    pub suffix: String,
+    /// put between the texts of two segments that are not empty
+    pub separator: String,
+    /// the maximum number of bytes of the status, dwm cuts off longer ones
+    pub max_length: Option<usize>,
+    /// what happens when the status is longer
+    pub overflow: Overflow,
+    /// updates of segments within this time after the first one are displayed together
+    pub debounce: Duration,
+    /// displayed when the status bar exits, otherwise the previous status is restored
+    pub exit_text: Option<String>,
+    /// the format of the colors in the status, which must not be cut off
+    pub color_format: ColorFormat,
+    /// where the status is displayed, they are not changed when the configuration is reloaded
+    pub outputs: Vec<OutputConfig>,
+}
+
+/// What happens when the status is longer than the maximum length
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// a warning is logged, the status is left as it is
+    #[default]
+    Warn,
+    /// the rightmost segments are left out until the status is short enough
+    DropSegments,
+    /// the status is shortened and ends with `…`
+    Ellipsize,
+}
+
+impl Default for StatusBarConfig {
+    fn default() -> Self {
+        Self {
+            area_delimiter: ";".into(),
+            prefix: String::new(),
+            suffix: String::new(),
+            separator: String::new(),
+            max_length: Some(256),
+            overflow: Overflow::Warn,
+            debounce: Duration::from_millis(20),
+            exit_text: None,
+            color_format: ColorFormat::default(),
+            outputs: vec![Default::default()],
+        }
+    }
+}
+
+/// What the status bar needs to know about a segment besides its text
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SegmentInfo {
+    pub area: Area,
+    pub name: Option<String>,
+    pub color: Color,
+    /// the monitors the segment is displayed on, all if empty
+    pub monitors: Vec<usize>,
+}
+
 pub(crate) struct StatusBar {
-    display: *mut Display,
-    window: u64,
+    outputs: Vec<Box<dyn Output>>,
 
+    config: StatusBarConfig,
+    segments: Vec<SegmentInfo>,
     segment_texts: Vec<String>,
+    /// hidden segments are left out of the status bar
+    hidden: Vec<bool>,
     current_text: String,
+    /// whether the status is longer than the maximum length
+    too_long: bool,
 }
 
 impl StatusBar {
-    pub fn new(num_segments: usize) -> Self {
-        let display;
-        let screen;
-        let window;
+    pub fn new(segments: Vec<SegmentInfo>, config: StatusBarConfig) -> Result<Self, String> {
+        let outputs = config
+            .outputs
+            .iter()
+            .map(OutputConfig::create)
+            .collect::<Result<_, _>>()?;
 
-        unsafe {
-            display = XOpenDisplay(ptr::null());
-            screen = XDefaultScreen(display);
-            window = XRootWindow(display, screen);
-        }
-
-        let segment_texts = vec!["".to_string(); num_segments];
+        let mut s = Self {
+            outputs,
 
-        let current_text = segment_texts.join("");
+            config,
+            segments: Vec::new(),
+            segment_texts: Vec::new(),
+            hidden: Vec::new(),
+            current_text: String::new(),
+            too_long: false,
+        };
 
-        let s = Self {
-            display,
-            window,
+        s.reset(segments, s.config.clone());
+        Ok(s)
+    }
 
-            segment_texts,
-            current_text,
-        };
+    /// Displays the status on the output as well
+    pub(crate) fn add_output(&mut self, output: Box<dyn Output>) {
+        self.outputs.push(output);
+        self.set_status();
+    }
 
-        s.set_status();
-        s
+    /// Replaces the segments and the settings, e.g. after the configuration was reloaded.
+    /// Hidden segments stay hidden if there is a segment with the same name.
+    pub(crate) fn reset(&mut self, segments: Vec<SegmentInfo>, config: StatusBarConfig) {
+        let hidden_names = self
+            .segments
+            .iter()
+            .zip(&self.hidden)
+            .filter_map(|(segment, hidden)| segment.name.as_ref().filter(|_| *hidden))
+            .collect::<HashSet<_>>();
+        self.hidden = segments
+            .iter()
+            .map(|segment| {
+                segment
+                    .name
+                    .as_ref()
+                    .is_some_and(|name| hidden_names.contains(name))
+            })
+            .collect();
+        self.segment_texts = vec!["".to_string(); segments.len()];
+        self.segments = segments;
+        self.config = config;
+        self.current_text = self.join();
+        self.set_status();
     }
 
     pub(crate) fn update_segment(&mut self, id: SegmentId, text: String) {
+        self.set_segment_text(id, text);
+        self.refresh();
+    }
+
+    /// changes the text of the segment without displaying it until the next refresh
+    pub(crate) fn set_segment_text(&mut self, id: SegmentId, text: String) {
         self.segment_texts[id] = text;
-        let new_text = self.segment_texts.join("");
+    }
+
+    /// Hides a visible segment or shows a hidden one, returns whether it is visible now
+    pub(crate) fn toggle_segment(&mut self, id: SegmentId) -> bool {
+        self.hidden[id] = !self.hidden[id];
+        self.refresh();
+        !self.hidden[id]
+    }
+
+    /// Displays the exit text or what was displayed before the status bar started
+    pub(crate) fn close(&mut self) {
+        match self.config.exit_text.clone() {
+            Some(text) => {
+                let status = Status {
+                    text: &text,
+                    ..Default::default()
+                };
+                for output in &mut self.outputs {
+                    output.set_status(&status);
+                }
+            }
+            None => {
+                for output in &mut self.outputs {
+                    output.restore();
+                }
+            }
+        }
+    }
+
+    pub(crate) fn debounce(&self) -> Duration {
+        self.config.debounce
+    }
+
+    /// the current text of the status bar
+    pub(crate) fn text(&self) -> &str {
+        &self.current_text
+    }
+
+    /// joins the texts of the visible segments
+    fn join(&self) -> String {
+        self.join_where(|_| true)
+    }
+
+    /// joins the texts of the visible segments that match the filter
+    fn join_where(&self, filter: impl Fn(&SegmentInfo) -> bool) -> String {
+        let texts = self
+            .segment_texts
+            .iter()
+            .zip(&self.hidden)
+            .zip(&self.segments)
+            .map(
+                |((text, hidden), segment)| match !hidden && filter(segment) {
+                    true => text.clone(),
+                    false => "".into(),
+                },
+            )
+            .collect::<Vec<_>>();
+        let areas = self.segments.iter().map(|s| s.area).collect::<Vec<_>>();
+        join_segments(&texts, &areas, &self.config)
+    }
+
+    /// the texts for each monitor, if any segment is only displayed on some monitors
+    fn monitor_texts(&self) -> Option<Vec<String>> {
+        let monitors = self.segments.iter().flat_map(|s| s.monitors.iter()).max()? + 1;
+        let texts = (0..monitors)
+            .map(|monitor| {
+                self.join_where(|s| s.monitors.is_empty() || s.monitors.contains(&monitor))
+            })
+            .collect();
+        Some(texts)
+    }
+
+    /// sets the status if it changed
+    pub(crate) fn refresh(&mut self) {
+        let new_text = self.join();
         if self.current_text != new_text {
             self.current_text = new_text;
+            self.warn_if_too_long();
             self.set_status();
         }
     }
 
-    fn set_status(&self) {
-        // https://github.com/hugglesfox/statusd/blob/main/src/xsetroot.rs
-        // https://github.com/KJ002/simple_status/blob/main/src/status.rs
+    /// warns once when the status becomes longer than the maximum length
+    fn warn_if_too_long(&mut self) {
+        let too_long = self
+            .config
+            .max_length
+            .is_some_and(|max_length| self.current_text.len() > max_length);
+        if too_long && !self.too_long && self.config.overflow == Overflow::Warn {
+            warn!(
+                "the status is {} bytes long, dwm only displays {}",
+                self.current_text.len(),
+                self.config.max_length.unwrap_or_default()
+            );
+        }
+        self.too_long = too_long;
+    }
 
-        let c_str = CString::new(self.current_text.clone()).unwrap();
-        unsafe {
-            XStoreName(self.display, self.window, c_str.as_ptr() as *const i8);
-            XSync(self.display, 0);
+    fn set_status(&mut self) {
+        let blocks = self
+            .segments
+            .iter()
+            .zip(&self.segment_texts)
+            .zip(&self.hidden)
+            .filter(|(_, hidden)| !**hidden)
+            .map(|((segment, text), _)| Block {
+                name: segment.name.as_deref(),
+                text,
+                color: segment.color,
+            })
+            .collect::<Vec<_>>();
+        let status = Status {
+            text: &self.current_text,
+            blocks,
+            monitors: self.monitor_texts(),
+        };
+        for output in &mut self.outputs {
+            output.set_status(&status);
         }
     }
 }
+
+/// Joins the texts of the segments into the text of the status bar.
+/// If any segment is in the bottom area, the top and bottom area are separated by the delimiter.
+/// A status that is longer than the maximum length is handled as configured.
+pub(crate) fn join_segments(texts: &[String], areas: &[Area], config: &StatusBarConfig) -> String {
+    let text = join_all(texts, areas, config);
+    let max_length = match config.max_length {
+        Some(max_length) if text.len() > max_length => max_length,
+        _ => return text,
+    };
+
+    match config.overflow {
+        Overflow::Warn => text,
+        Overflow::DropSegments => {
+            let mut texts = texts.to_vec();
+            let mut text = text;
+            while text.len() > max_length {
+                match texts.iter_mut().rev().find(|text| !text.is_empty()) {
+                    Some(last) => last.clear(),
+                    None => break,
+                }
+                text = join_all(&texts, areas, config);
+            }
+            text
+        }
+        Overflow::Ellipsize => truncate_bytes(&text, max_length, "…", config.color_format),
+    }
+}
+
+/// joins the texts of all segments, regardless of the maximum length
+fn join_all(texts: &[String], areas: &[Area], config: &StatusBarConfig) -> String {
+    let area_text = |area| {
+        let texts = texts
+            .iter()
+            .zip(areas)
+            .filter(|(text, a)| **a == area && !text.is_empty())
+            .map(|(text, _)| text.as_str())
+            .collect::<Vec<_>>();
+        format!(
+            "{}{}{}",
+            config.prefix,
+            texts.join(&config.separator),
+            config.suffix
+        )
+    };
+
+    if areas.contains(&Area::Bottom) {
+        format!(
+            "{}{}{}",
+            area_text(Area::Top),
+            config.area_delimiter,
+            area_text(Area::Bottom)
+        )
+    } else {
+        area_text(Area::Top)
+    }
+}
+
+/// Shortens the text to at most `max_length` bytes, ending with the ellipsis.
+/// The text is only cut between graphemes and color escapes.
+fn truncate_bytes(text: &str, max_length: usize, ellipsis: &str, format: ColorFormat) -> String {
+    let max_length = max_length.saturating_sub(ellipsis.len());
+    let mut result = String::with_capacity(max_length + ellipsis.len());
+    'parts: for (part, escape) in split_colors(text, format) {
+        let pieces = match escape {
+            true => vec![part],
+            false => part.graphemes(true).collect(),
+        };
+        for piece in pieces {
+            if result.len() + piece.len() > max_length {
+                break 'parts;
+            }
+            result.push_str(piece);
+        }
+    }
+    result.push_str(ellipsis);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts() -> Vec<String> {
+        vec!["a".into(), "b".into(), "c".into()]
+    }
+
+    #[test]
+    fn single_area() {
+        let areas = vec![Area::Top; 3];
+        assert_eq!(join_segments(&texts(), &areas, &Default::default()), "abc");
+    }
+
+    #[test]
+    fn separator() {
+        let config = StatusBarConfig {
+            prefix: " ".into(),
+            suffix: "!".into(),
+            separator: " | ".into(),
+            ..Default::default()
+        };
+        let texts = vec!["a".into(), "".into(), "c".into(), "d".into()];
+        let areas = vec![Area::Top, Area::Top, Area::Top, Area::Bottom];
+        assert_eq!(join_segments(&texts, &areas, &config), " a | c!; d!");
+    }
+
+    #[test]
+    fn max_length() {
+        let texts = vec!["aaa".into(), "bb".into(), "".into(), "cc".into()];
+        let areas = vec![Area::Top; 4];
+        let config = |overflow| StatusBarConfig {
+            max_length: Some(6),
+            overflow,
+            ..Default::default()
+        };
+        assert_eq!(
+            join_segments(&texts, &areas, &config(Overflow::Warn)),
+            "aaabbcc"
+        );
+        assert_eq!(
+            join_segments(&texts, &areas, &config(Overflow::DropSegments)),
+            "aaabb"
+        );
+        assert_eq!(
+            join_segments(&texts, &areas, &config(Overflow::Ellipsize)),
+            "aaa…"
+        );
+    }
+
+    #[test]
+    fn ellipsize_escapes() {
+        let text = "ab^c#ff0000^cd";
+        assert_eq!(truncate_bytes(text, 10, "…", ColorFormat::Status2d), "ab…");
+        assert_eq!(
+            truncate_bytes(text, 15, "…", ColorFormat::Status2d),
+            "ab^c#ff0000^…"
+        );
+        assert_eq!(
+            truncate_bytes("äöü", 4, "…", ColorFormat::Statuscolors),
+            "…"
+        );
+        assert_eq!(
+            truncate_bytes("äöü", 5, "…", ColorFormat::Statuscolors),
+            "ä…"
+        );
+    }
+
+    #[test]
+    fn monitors() {
+        let config = StatusBarConfig {
+            outputs: Vec::new(),
+            ..Default::default()
+        };
+        let segment = |monitors: Vec<usize>| SegmentInfo {
+            monitors,
+            ..Default::default()
+        };
+        let mut status_bar = StatusBar::new(
+            vec![segment(vec![]), segment(vec![1]), segment(vec![0])],
+            config,
+        )
+        .unwrap();
+        for (id, text) in texts().into_iter().enumerate() {
+            status_bar.update_segment(id, text);
+        }
+        assert_eq!(status_bar.text(), "abc");
+        assert_eq!(
+            status_bar.monitor_texts(),
+            Some(vec!["ac".into(), "ab".into()])
+        );
+
+        status_bar.toggle_segment(0);
+        assert_eq!(status_bar.text(), "bc");
+        assert_eq!(
+            status_bar.monitor_texts(),
+            Some(vec!["c".into(), "b".into()])
+        );
+    }
+
+    #[test]
+    fn hidden_after_reset() {
+        let config = StatusBarConfig {
+            outputs: Vec::new(),
+            ..Default::default()
+        };
+        let segment = |name: Option<&str>| SegmentInfo {
+            name: name.map(String::from),
+            ..Default::default()
+        };
+        let mut status_bar = StatusBar::new(
+            vec![
+                segment(Some("mail")),
+                segment(None),
+                segment(Some("weather")),
+            ],
+            config.clone(),
+        )
+        .unwrap();
+        for id in 0..3 {
+            status_bar.toggle_segment(id);
+        }
+
+        status_bar.reset(
+            vec![
+                segment(Some("weather")),
+                segment(None),
+                segment(Some("cpu")),
+            ],
+            config,
+        );
+        for (id, text) in texts().into_iter().enumerate() {
+            status_bar.update_segment(id, text);
+        }
+        assert_eq!(status_bar.text(), "bc");
+    }
+
+    #[test]
+    fn exit_text() {
+        let path = std::env::temp_dir().join(format!("dwmblocksrs-exit-{}", std::process::id()));
+        let config = StatusBarConfig {
+            exit_text: Some("bye".into()),
+            outputs: vec![OutputConfig::File { path: path.clone() }],
+            ..Default::default()
+        };
+        let mut status_bar = StatusBar::new(vec![Default::default()], config).unwrap();
+        status_bar.update_segment(0, "a".into());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\n");
+        status_bar.close();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "bye\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn dual_areas() {
+        let areas = vec![Area::Top, Area::Bottom, Area::Top];
+        assert_eq!(join_segments(&texts(), &areas, &Default::default()), "ac;b");
+    }
+}
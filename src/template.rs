@@ -0,0 +1,193 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::color::{split_colors, ColorFormat};
+
+/// Replaces every `{key}` placeholder in the template with the value that is mapped to that key.
+/// Placeholders with an unknown key are left untouched.
+pub(crate) fn fill_template<V: AsRef<str>>(template: &str, values: &[(&str, V)]) -> String {
+    replace_placeholders(template, |placeholder| {
+        values
+            .iter()
+            .find(|(k, _)| *k == placeholder.key)
+            .map(|(_, value)| placeholder.pad(value.as_ref()))
+    })
+}
+
+/// Shortens the text to at most `max_width` visible characters, ending with the ellipsis.
+/// Color escapes don't count and are all kept, the text is only cut between graphemes.
+pub(crate) fn truncate(
+    text: &str,
+    max_width: usize,
+    ellipsis: &str,
+    format: ColorFormat,
+) -> String {
+    let parts = split_colors(text, format);
+    let width: usize = parts
+        .iter()
+        .filter(|(_, escape)| !escape)
+        .map(|(part, _)| part.graphemes(true).count())
+        .sum();
+    if width <= max_width {
+        return text.into();
+    }
+
+    // an ellipsis that is too long is left out
+    let ellipsis_width = ellipsis.graphemes(true).count();
+    let (mut remaining, mut ellipsis) = match max_width.checked_sub(ellipsis_width) {
+        Some(remaining) => (remaining, Some(ellipsis)),
+        None => (max_width, None),
+    };
+    let mut result = String::with_capacity(text.len());
+    for (part, escape) in parts {
+        if escape {
+            result.push_str(part);
+            continue;
+        }
+        for grapheme in part.graphemes(true) {
+            if remaining == 0 {
+                result.extend(ellipsis.take());
+                break;
+            }
+            result.push_str(grapheme);
+            remaining -= 1;
+        }
+    }
+    result
+}
+
+/// A `{key}` placeholder of a template, optionally with a padding like `{key:>5}`
+#[derive(Debug, PartialEq)]
+pub(crate) struct Placeholder<'a> {
+    pub key: &'a str,
+    align: Align,
+    width: usize,
+}
+
+/// How text is placed in the space of a wider width
+#[derive(Deserialize, JsonSchema, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+/// Pads the text with spaces to the width, which is counted in characters
+pub(crate) fn pad(text: &str, width: usize, align: Align) -> String {
+    let padding = width.saturating_sub(text.chars().count());
+    let (left, right) = match align {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Center => (padding / 2, padding - padding / 2),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+impl<'a> Placeholder<'a> {
+    fn parse(placeholder: &'a str) -> Option<Self> {
+        let (key, spec) = match placeholder.split_once(':') {
+            Some((key, spec)) => (key, spec),
+            None => (placeholder, ""),
+        };
+        let (align, width) = match spec.chars().next() {
+            Some('<') => (Align::Left, &spec[1..]),
+            Some('>') => (Align::Right, &spec[1..]),
+            Some('^') => (Align::Center, &spec[1..]),
+            _ => (Align::Left, spec),
+        };
+        let width = match width {
+            "" => 0,
+            width => width.parse().ok()?,
+        };
+        Some(Self { key, align, width })
+    }
+
+    /// Pads the text with spaces to the width of the placeholder
+    pub fn pad(&self, text: &str) -> String {
+        pad(text, self.width, self.align)
+    }
+}
+
+/// Replaces every placeholder in the template with the text returned for it.
+/// Placeholders for which `None` is returned, or that cannot be parsed, are left untouched.
+pub(crate) fn replace_placeholders(
+    template: &str,
+    mut replace: impl FnMut(&Placeholder) -> Option<String>,
+) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+
+        match Placeholder::parse(&rest[1..end]).and_then(|p| replace(&p)) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace() {
+        assert_eq!(
+            fill_template("{name}: {value}%", &[("name", "a"), ("value", "10")]),
+            "a: 10%"
+        );
+    }
+
+    #[test]
+    fn unknown_key() {
+        assert_eq!(fill_template("{unknown} {a}", &[("a", "b")]), "{unknown} b");
+    }
+
+    #[test]
+    fn unclosed() {
+        assert_eq!(fill_template("{a} {a", &[("a", "b")]), "b {a");
+    }
+
+    #[test]
+    fn padding() {
+        let values = [("a", "ab")];
+        assert_eq!(fill_template("[{a:5}]", &values), "[ab   ]");
+        assert_eq!(fill_template("[{a:<5}]", &values), "[ab   ]");
+        assert_eq!(fill_template("[{a:>5}]", &values), "[   ab]");
+        assert_eq!(fill_template("[{a:^5}]", &values), "[ ab  ]");
+        assert_eq!(fill_template("[{a:>1}]", &values), "[ab]");
+        assert_eq!(fill_template("[{a:x}]", &values), "[{a:x}]");
+    }
+
+    #[test]
+    fn truncation() {
+        let f = ColorFormat::Statuscolors;
+        assert_eq!(truncate("abcdef", 6, "…", f), "abcdef");
+        assert_eq!(truncate("abcdef", 4, "…", f), "abc…");
+        assert_eq!(truncate("abcdef", 4, "...", f), "a...");
+        assert_eq!(truncate("abcdef", 2, "...", f), "ab");
+        assert_eq!(
+            truncate("e\u{301}e\u{301}e\u{301}", 2, "", f),
+            "e\u{301}e\u{301}"
+        );
+        assert_eq!(truncate("\x02abc\x01def", 3, "…", f), "\x02ab…\x01");
+        assert_eq!(
+            truncate("^c#ff0000^abc^d^def", 4, "…", ColorFormat::Status2d),
+            "^c#ff0000^abc^d^…"
+        );
+    }
+}
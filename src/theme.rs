@@ -0,0 +1,88 @@
+use std::{collections::HashMap, fs::read_to_string, path::PathBuf};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Where the colors of the desktop theme are imported from
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ColorSource {
+    /// `~/.Xresources`, e.g. `*.color1: #bf616a`
+    Xresources,
+    /// `~/.cache/wal/colors` of pywal, one color per line
+    Pywal,
+}
+
+impl ColorSource {
+    pub fn path(self) -> Result<PathBuf, String> {
+        let home = dirs::home_dir().ok_or("home directory not found")?;
+        Ok(match self {
+            Self::Xresources => home.join(".Xresources"),
+            Self::Pywal => home.join(".cache/wal/colors"),
+        })
+    }
+
+    /// Reads the hex colors by their names.
+    /// The colors of pywal are named like in the Xresources: `color0` to `color15`.
+    pub fn load(self) -> Result<HashMap<String, String>, String> {
+        let path = self.path()?;
+        let text = read_to_string(&path)
+            .map_err(|e| format!("error reading colors from '{}': {}", path.display(), e))?;
+        Ok(match self {
+            Self::Xresources => parse_xresources(&text),
+            Self::Pywal => parse_pywal(&text),
+        })
+    }
+}
+
+/// Parses lines like `*.color1: #bf616a`, `*color1: #bf616a` or `dwm.foreground: #eceff4`
+fn parse_xresources(text: &str) -> HashMap<String, String> {
+    text.lines()
+        // comments and preprocessor directives
+        .filter(|line| !line.starts_with('!') && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .filter_map(|(key, value)| {
+            let name = key.rsplit(['.', '*']).next()?.trim();
+            let value = value.trim();
+            value
+                .starts_with('#')
+                .then(|| (name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn parse_pywal(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with('#'))
+        .enumerate()
+        .map(|(i, color)| (format!("color{i}"), color.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xresources() {
+        let colors = parse_xresources(
+            "! comment\n#define red #ff0000\n*.color1: #bf616a\n*color2:\t#a3be8c\nXft.dpi: 96\ndwm.foreground: #eceff4\n",
+        );
+        assert_eq!(
+            colors,
+            HashMap::from([
+                ("color1".into(), "#bf616a".into()),
+                ("color2".into(), "#a3be8c".into()),
+                ("foreground".into(), "#eceff4".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn pywal() {
+        let colors = parse_pywal("#2e3440\n#bf616a\n");
+        assert_eq!(colors["color0"], "#2e3440");
+        assert_eq!(colors["color1"], "#bf616a");
+    }
+}
@@ -0,0 +1,77 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+
+lazy_static! {
+    /// the escape sequences of terminals, e.g. for colors (CSI) or titles (OSC)
+    static ref ANSI_ESCAPE: Regex =
+        Regex::new(r"\x1b(\[[0-9:;<=>?]*[ -/]*[@-~]|\][^\x07\x1b]*(\x07|\x1b\\)|[@-_])")
+            .unwrap();
+}
+
+/// A change of the value of a segment, before it is decorated
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    /// replaces all matches of the pattern, `$1` or `${name}` in the replacement refer to groups
+    Replace {
+        #[serde(deserialize_with = "regex")]
+        #[schemars(with = "String")]
+        pattern: Regex,
+        #[serde(default)]
+        with: String,
+    },
+    /// removes whitespace at the beginning and the end
+    Trim,
+    Uppercase,
+    Lowercase,
+    /// removes the escape sequences of terminals, e.g. colors
+    StripAnsi,
+}
+
+fn regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+impl Transform {
+    pub fn apply(&self, value: String) -> String {
+        match self {
+            Self::Replace { pattern, with } => pattern.replace_all(&value, with.as_str()).into(),
+            Self::Trim => value.trim().into(),
+            Self::Uppercase => value.to_uppercase(),
+            Self::Lowercase => value.to_lowercase(),
+            Self::StripAnsi => ANSI_ESCAPE.replace_all(&value, "").into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let replace = Transform::Replace {
+            pattern: Regex::new(r"(\d+)%").unwrap(),
+            with: "$1 percent".into(),
+        };
+        assert_eq!(replace.apply("vol 40%".into()), "vol 40 percent");
+        assert_eq!(Transform::Trim.apply(" a b\n".into()), "a b");
+        assert_eq!(Transform::Uppercase.apply("aB".into()), "AB");
+        assert_eq!(Transform::Lowercase.apply("aB".into()), "ab");
+        assert_eq!(
+            Transform::StripAnsi.apply("\x1b[1;31mred\x1b[0m \x1b]0;title\x07ok".into()),
+            "red ok"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let transforms: Vec<Transform> =
+            serde_yaml::from_str("- trim\n- replace: {pattern: 'a+', with: b}\n").unwrap();
+        assert_eq!(transforms[1].apply("caat".into()), "cbt");
+        assert!(serde_yaml::from_str::<Vec<Transform>>("- replace: {pattern: '('}").is_err());
+    }
+}